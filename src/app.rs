@@ -0,0 +1,1989 @@
+use crate::{
+    backend,
+    bundle::{self, Bundle},
+    diagnose::{self, Check},
+    export, fps, get_interface_status, group_access_points,
+    helper::ConnectOptions,
+    hotplug,
+    ipconfig::{self, IpConfig},
+    iw,
+    link::{self, LinkInfo},
+    notify,
+    portal::{Portal, Probe},
+    rfkill,
+    rules::{self, HookEvent, Rules},
+    scan_for_access_points,
+    settings::{Settings, Sort},
+    survey::{Recorder, Sample},
+    switch_wlan_interface, Band, ExportDialog, InterfaceStatus, SecurityType, WirelessInterface,
+    WirelessNetwork,
+};
+use anyhow::Result;
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{Receiver, Sender},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Wait for the interface to come up, or associate before scanning.
+const SETTLE_TIME: Duration = Duration::from_millis(1000);
+const CONNECT_SETTLE_TIME: Duration = Duration::from_millis(3000);
+const MAX_LOG_ENTRIES: usize = 100;
+/// The address might not be there yet, right after connecting.
+const PORTAL_ATTEMPTS: usize = 3;
+
+/// Lets the background threads wake up the front-end, after they sent an event.
+#[derive(Clone)]
+pub struct Repaint(Arc<dyn Fn() + Send + Sync>);
+
+impl Repaint {
+    pub fn new(repaint: impl Fn() + Send + Sync + 'static) -> Self {
+        Self(Arc::new(repaint))
+    }
+
+    pub fn request_repaint(&self) {
+        (self.0)()
+    }
+}
+
+impl From<eframe::egui::Context> for Repaint {
+    fn from(ctx: eframe::egui::Context) -> Self {
+        Self::new(move || ctx.request_repaint())
+    }
+}
+
+/// Last known state of an interface, so switching between interfaces can show
+/// the previous results right away while a fresh scan is running.
+#[derive(Debug, Default)]
+pub struct CachedInterface {
+    status: Option<InterfaceStatus>,
+    networks: Option<Vec<WirelessNetwork>>,
+}
+
+pub enum Event {
+    RefreshNetworks(Repaint, String, Option<Duration>),
+    RefreshInterface(Repaint, String),
+    Connect(Repaint, String, String, Option<String>, ConnectOptions),
+    /// Interface and every access point of the scan, none while it's running.
+    UpdateNetworks(String, Option<Vec<WirelessNetwork>>),
+    UpdateInterfaceStatus(String, InterfaceStatus),
+    UpdateInterfaces(Vec<WirelessInterface>),
+    UpdateRfkill(rfkill::RfkillEvent),
+    UpdateSavedNetworks(String, Vec<String>),
+    Connected(String, Result<(), String>),
+    RunHook(Repaint, rules::Hook),
+    /// Interface, SSID and the DNS servers, none revert to the ones from DHCP.
+    SetDns(Repaint, String, String, Vec<IpAddr>),
+    Log(rules::LogEntry),
+    RefreshLinkInfo(Repaint, String),
+    UpdateLinkInfo(String, LinkInfo),
+    RefreshIpConfig(Repaint, String),
+    UpdateIpConfig(String, IpConfig),
+    RenewLease(Repaint, String),
+    LeaseRenewed(String, Result<(), String>),
+    Diagnose(Repaint, String, Probe),
+    DiagnosisCheck(String, Check),
+    DiagnosisDone,
+    RefreshBssid(Repaint, String),
+    UpdateBssid(String, Option<String>),
+    /// BSSID and the probe.
+    DetectPortal(Repaint, String, Probe),
+    PortalDetected(String, Result<Portal, String>),
+    /// Interface, where to save the bundle and whether to anonymize it.
+    ExportBundle(Repaint, String, PathBuf, bool),
+    BundleExported(Result<PathBuf, String>),
+    /// Interface, whether to switch it on and its status, to tell how to switch it.
+    SwitchWlan(Repaint, String, bool, Option<InterfaceStatus>),
+    WlanSwitched(String, bool, Result<(), String>),
+    SetAirplaneMode(Repaint, bool),
+    AirplaneModeSwitched(bool, Result<(), String>),
+}
+
+/// Narrows down the shown networks, without rescanning.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkFilter {
+    /// Part of the SSID, or BSSID, case insensitive.
+    pub text: String,
+    pub hidden_bands: HashSet<Band>,
+    pub hide_open: bool,
+    /// Only networks saved in wpa_supplicant.
+    pub only_saved: bool,
+}
+
+impl NetworkFilter {
+    pub fn matches(&self, network: &WirelessNetwork, saved_networks: &HashSet<String>) -> bool {
+        let text = self.text.trim().to_lowercase();
+        (text.is_empty()
+            || network.essid.to_lowercase().contains(&text)
+            || network.address.to_lowercase().contains(&text))
+            && !self.hidden_bands.contains(&network.band())
+            && !(self.hide_open && network.security_type == SecurityType::Invalid)
+            && (!self.only_saved || saved_networks.contains(&network.essid))
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.text.trim().is_empty()
+            || !self.hidden_bands.is_empty()
+            || self.hide_open
+            || self.only_saved
+    }
+}
+
+pub struct ConnectDialog {
+    pub ssid: String,
+    pub secured: bool,
+    pub passphrase: String,
+}
+
+pub struct AppState {
+    pub wlan_interfaces: Vec<WirelessInterface>,
+    pub selected_wlan_interface: String,
+    pub wlan_networks: Option<Vec<WirelessNetwork>>,
+    pub selected_wlan_network: String,
+    pub connected_wlan_network: Option<String>,
+    pub wlan_on: bool,
+    pub interface_status: Option<InterfaceStatus>,
+    pub rfkill_devices: HashMap<u32, rfkill::RfkillEvent>,
+    pub connecting: Option<String>,
+    /// The radio of the selected interface is being switched, polkit might ask for a password.
+    pub switching_wlan: bool,
+    pub error: Option<String>,
+    pub connect_dialog: Option<ConnectDialog>,
+    pub export_dialog: Option<ExportDialog>,
+    pub interface_cache: HashMap<String, CachedInterface>,
+    pub frame_history: fps::FrameHistory,
+    pub settings: Settings,
+    /// Only front-ends, which show notifications set this.
+    pub notifier: Option<Sender<notify::Notification>>,
+    pub saved_networks: HashSet<String>,
+    /// Saved networks in the last scan, None until the first scan of the interface.
+    pub networks_in_range: Option<HashSet<String>>,
+    /// The network, the selected interface lost the connection to.
+    pub connection_lost: Option<String>,
+    /// Only front-ends, which apply the rules from swelfi.toml set this.
+    pub rules: Option<Rules>,
+    pub rules_log: Vec<rules::LogEntry>,
+    /// Networks, which aren't connected to automatically anymore, because connecting failed.
+    pub autoconnect_failed: HashSet<String>,
+    pub filter: NetworkFilter,
+    /// Only refreshed while the connection panel is shown.
+    pub link_info: Option<LinkInfo>,
+    /// Only refreshed while the IP configuration is shown.
+    pub ip_config: Option<IpConfig>,
+    pub renewing_lease: bool,
+    /// The checks of the last diagnosis, in the order they ran.
+    pub diagnosis: Vec<Check>,
+    pub diagnosing: bool,
+    /// The access point the selected interface is connected to, read after connecting.
+    pub bssid: Option<String>,
+    /// Captive portals by BSSID, each access point is only probed once.
+    pub portals: HashMap<String, Portal>,
+    pub detecting_portal: bool,
+    pub exporting_bundle: bool,
+    /// Where the last diagnostics bundle was saved.
+    pub bundle_path: Option<PathBuf>,
+    /// Records the scans of the selected interface, while a site survey is running.
+    pub recorder: Option<Recorder>,
+    /// Recorded with the scans, like `room 3.14`.
+    pub survey_location: String,
+    /// Where on the floor plan the next scan is taken.
+    pub survey_position: Option<[f32; 2]>,
+    /// Scans taken at a position on the floor plan, for the heatmap.
+    pub measurements: Vec<Sample>,
+}
+
+impl AppState {
+    /// Reads the interfaces and the status of the one from the settings, or the first one,
+    /// which is selected.
+    pub fn load(settings: Settings) -> Result<Self> {
+        let wlan_interfaces = iw()?;
+        let selected_wlan_interface = settings
+            .interface()
+            .filter(|interface| wlan_interfaces.iter().any(|wi| wi.name == *interface))
+            .or(wlan_interfaces.first().map(|wi| wi.name.as_str()))
+            .unwrap_or_default()
+            .to_owned();
+
+        let interface_status = if selected_wlan_interface.is_empty() {
+            None
+        } else {
+            Some(get_interface_status(&selected_wlan_interface)?)
+        };
+        let mut app_state = Self::new(wlan_interfaces, selected_wlan_interface);
+        app_state.settings = settings;
+        app_state.connected_wlan_network = interface_status
+            .as_ref()
+            .and_then(|status| status.connected_ssid.clone());
+        app_state.wlan_on = interface_status
+            .as_ref()
+            .is_some_and(InterfaceStatus::is_on);
+        app_state.interface_status = interface_status;
+        Ok(app_state)
+    }
+
+    pub fn new(wlan_interfaces: Vec<WirelessInterface>, selected_wlan_interface: String) -> Self {
+        Self {
+            wlan_interfaces,
+            selected_wlan_interface,
+            wlan_networks: None,
+            selected_wlan_network: String::new(),
+            connected_wlan_network: None,
+            wlan_on: true,
+            interface_status: None,
+            rfkill_devices: HashMap::new(),
+            connecting: None,
+            switching_wlan: false,
+            error: None,
+            connect_dialog: None,
+            export_dialog: None,
+            interface_cache: HashMap::new(),
+            frame_history: fps::FrameHistory::default(),
+            settings: Settings::default(),
+            notifier: None,
+            saved_networks: HashSet::new(),
+            networks_in_range: None,
+            connection_lost: None,
+            rules: None,
+            rules_log: vec![],
+            autoconnect_failed: HashSet::new(),
+            filter: NetworkFilter::default(),
+            link_info: None,
+            ip_config: None,
+            renewing_lease: false,
+            diagnosis: vec![],
+            diagnosing: false,
+            bssid: None,
+            portals: HashMap::new(),
+            detecting_portal: false,
+            exporting_bundle: false,
+            bundle_path: None,
+            recorder: None,
+            survey_location: String::new(),
+            survey_position: None,
+            measurements: vec![],
+        }
+    }
+
+    /// Applies an event sent by the background threads and triggers follow-up actions.
+    pub fn handle_event(
+        &mut self,
+        event: Event,
+        background_sender: &Sender<Event>,
+        repaint: &Repaint,
+    ) {
+        match event {
+            Event::UpdateNetworks(interface, networks) => {
+                let is_selected = interface == self.selected_wlan_interface;
+                self.update_networks(interface, networks);
+                if is_selected {
+                    self.autoconnect(background_sender, repaint);
+                }
+            }
+            Event::UpdateInterfaceStatus(interface, status) => {
+                let is_selected = interface == self.selected_wlan_interface;
+                let previous = self.connected_wlan_network.clone();
+                self.update_interface_status(interface, status);
+                if is_selected && previous != self.connected_wlan_network {
+                    self.apply_rules(previous, background_sender, repaint);
+                    self.detect_portal(background_sender, repaint);
+                }
+            }
+            Event::UpdateRfkill(rfkill_event) => {
+                let turned_on = self.update_rfkill(rfkill_event);
+                if turned_on {
+                    background_sender
+                        .send(Event::RefreshNetworks(
+                            repaint.clone(),
+                            self.selected_wlan_interface.clone(),
+                            Some(SETTLE_TIME),
+                        ))
+                        .expect("can send on channel");
+                }
+            }
+            Event::UpdateInterfaces(wlan_interfaces) => {
+                if let Some(selected_wlan_interface) = self.update_wlan_interfaces(wlan_interfaces)
+                {
+                    background_sender
+                        .send(Event::RefreshInterface(
+                            repaint.clone(),
+                            selected_wlan_interface,
+                        ))
+                        .expect("can send on channel");
+                }
+            }
+            Event::UpdateSavedNetworks(interface, saved_networks)
+                if interface == self.selected_wlan_interface =>
+            {
+                self.saved_networks = saved_networks.into_iter().collect();
+            }
+            Event::Connected(ssid, result) => {
+                self.connecting = None;
+                if let Err(e) = result {
+                    self.error = Some(format!("Connecting to {} failed: {}", ssid, e));
+                    self.autoconnect_failed.insert(ssid.clone());
+                    self.notify(notify::Notification::ConnectFailed(ssid, e));
+                }
+            }
+            Event::Log(entry) => self.log(entry),
+            Event::UpdateLinkInfo(interface, link_info)
+                if interface == self.selected_wlan_interface =>
+            {
+                self.link_info = Some(link_info);
+            }
+            Event::UpdateIpConfig(interface, ip_config)
+                if interface == self.selected_wlan_interface =>
+            {
+                self.ip_config = Some(ip_config);
+            }
+            Event::DiagnosisCheck(interface, check)
+                if interface == self.selected_wlan_interface =>
+            {
+                self.diagnosis.push(check);
+            }
+            Event::DiagnosisDone => self.diagnosing = false,
+            Event::UpdateBssid(interface, bssid) if interface == self.selected_wlan_interface => {
+                self.bssid = bssid.clone();
+                if let Some(bssid) = bssid.filter(|bssid| !self.portals.contains_key(bssid)) {
+                    self.probe_portal(bssid, background_sender, repaint);
+                }
+            }
+            Event::PortalDetected(bssid, result) => {
+                self.detecting_portal = false;
+                match result {
+                    Ok(portal) => {
+                        if matches!(portal, Portal::LoginRequired(_))
+                            && self.bssid.as_ref() == Some(&bssid)
+                        {
+                            if let Some(ref ssid) = self.connected_wlan_network {
+                                self.notify(notify::Notification::LoginRequired(ssid.clone()));
+                            }
+                        }
+                        self.portals.insert(bssid, portal);
+                    }
+                    Err(e) => {
+                        log::warn!("Error while probing {} for a captive portal: {}", bssid, e)
+                    }
+                }
+            }
+            Event::BundleExported(result) => {
+                self.exporting_bundle = false;
+                match result {
+                    Ok(path) => self.bundle_path = Some(path),
+                    Err(e) => self.error = Some(format!("Exporting diagnostics failed: {}", e)),
+                }
+            }
+            Event::WlanSwitched(interface, wlan_on, result) => {
+                self.wlan_switched(interface, wlan_on, result, background_sender, repaint)
+            }
+            Event::AirplaneModeSwitched(on, Err(e)) => {
+                log::error!("Error while switching airplane mode: {}", e);
+                self.error = Some(format!(
+                    "Switching airplane mode {} failed: {}",
+                    if on { "on" } else { "off" },
+                    e
+                ));
+            }
+            Event::LeaseRenewed(interface, result) => {
+                self.renewing_lease = false;
+                if let Err(e) = result {
+                    self.error = Some(format!("Renewing the lease of {} failed: {}", interface, e));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn refresh_link_info(&self, background_sender: &Sender<Event>, repaint: &Repaint) {
+        if self.selected_wlan_interface.is_empty() {
+            return;
+        }
+        background_sender
+            .send(Event::RefreshLinkInfo(
+                repaint.clone(),
+                self.selected_wlan_interface.clone(),
+            ))
+            .expect("can send on channel");
+    }
+
+    pub fn refresh_ip_config(&self, background_sender: &Sender<Event>, repaint: &Repaint) {
+        if self.selected_wlan_interface.is_empty() {
+            return;
+        }
+        background_sender
+            .send(Event::RefreshIpConfig(
+                repaint.clone(),
+                self.selected_wlan_interface.clone(),
+            ))
+            .expect("can send on channel");
+    }
+
+    pub fn renew_lease(&mut self, background_sender: &Sender<Event>, repaint: &Repaint) {
+        if self.selected_wlan_interface.is_empty() || self.renewing_lease {
+            return;
+        }
+        self.renewing_lease = true;
+        self.error = None;
+        background_sender
+            .send(Event::RenewLease(
+                repaint.clone(),
+                self.selected_wlan_interface.clone(),
+            ))
+            .expect("can send on channel");
+    }
+
+    /// Runs the connectivity checks of the selected interface, one at a time.
+    pub fn diagnose(&mut self, background_sender: &Sender<Event>, repaint: &Repaint) {
+        if self.selected_wlan_interface.is_empty() || self.diagnosing {
+            return;
+        }
+        self.diagnosing = true;
+        self.diagnosis.clear();
+        background_sender
+            .send(Event::Diagnose(
+                repaint.clone(),
+                self.selected_wlan_interface.clone(),
+                self.settings.captive_portal.clone(),
+            ))
+            .expect("can send on channel");
+    }
+
+    /// Gathers a diagnostics bundle of the selected interface into the home directory.
+    pub fn export_bundle(
+        &mut self,
+        anonymize: bool,
+        background_sender: &Sender<Event>,
+        repaint: &Repaint,
+    ) {
+        if self.selected_wlan_interface.is_empty() || self.exporting_bundle {
+            return;
+        }
+        self.exporting_bundle = true;
+        self.bundle_path = None;
+        self.error = None;
+        background_sender
+            .send(Event::ExportBundle(
+                repaint.clone(),
+                self.selected_wlan_interface.clone(),
+                bundle::default_path(),
+                anonymize,
+            ))
+            .expect("can send on channel");
+    }
+
+    /// Records every scan of the selected interface from now on, appending to the file.
+    pub fn start_recording(&mut self, path: &Path) {
+        match Recorder::start(path) {
+            Ok(recorder) => {
+                self.recorder = Some(recorder);
+                self.error = None;
+            }
+            Err(e) => {
+                self.error = Some(format!("Recording to {} failed: {}", path.display(), e));
+            }
+        }
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.recorder = None;
+    }
+
+    /// Scans right away and takes the result as a measurement at the position on the floor plan.
+    pub fn measure_at(
+        &mut self,
+        position: [f32; 2],
+        background_sender: &Sender<Event>,
+        repaint: &Repaint,
+    ) {
+        self.survey_position = Some(position);
+        self.reload(background_sender, repaint);
+    }
+
+    /// Reads the access point the selected interface is connected to, which is probed for a
+    /// captive portal, unless it was probed before.
+    pub fn detect_portal(&mut self, background_sender: &Sender<Event>, repaint: &Repaint) {
+        self.bssid = None;
+        if self.selected_wlan_interface.is_empty()
+            || self.connected_wlan_network.is_none()
+            || !self.settings.captive_portal.enabled
+        {
+            return;
+        }
+        background_sender
+            .send(Event::RefreshBssid(
+                repaint.clone(),
+                self.selected_wlan_interface.clone(),
+            ))
+            .expect("can send on channel");
+    }
+
+    /// Probes the connected access point again, e.g. after logging in.
+    pub fn check_portal_again(&mut self, background_sender: &Sender<Event>, repaint: &Repaint) {
+        if let Some(bssid) = self.bssid.clone() {
+            self.portals.remove(&bssid);
+            self.probe_portal(bssid, background_sender, repaint);
+        }
+    }
+
+    fn probe_portal(
+        &mut self,
+        bssid: String,
+        background_sender: &Sender<Event>,
+        repaint: &Repaint,
+    ) {
+        self.detecting_portal = true;
+        background_sender
+            .send(Event::DetectPortal(
+                repaint.clone(),
+                bssid,
+                self.settings.captive_portal.clone(),
+            ))
+            .expect("can send on channel");
+    }
+
+    /// The page to log in at, if the connected access point has a captive portal.
+    pub fn login_required(&self) -> Option<&str> {
+        match self.portals.get(self.bssid.as_ref()?)? {
+            Portal::LoginRequired(url) => Some(url),
+            Portal::None => None,
+        }
+    }
+
+    /// Rescans the selected interface, the current results are cleared while scanning.
+    pub fn refresh(&self, background_sender: &Sender<Event>, repaint: &Repaint) {
+        if self.selected_wlan_interface.is_empty() {
+            return;
+        }
+        background_sender
+            .send(Event::RefreshNetworks(
+                repaint.clone(),
+                self.selected_wlan_interface.clone(),
+                None,
+            ))
+            .expect("can send on channel");
+    }
+
+    /// Reloads the status and rescans the selected interface, keeping the current results.
+    pub fn reload(&self, background_sender: &Sender<Event>, repaint: &Repaint) {
+        if self.selected_wlan_interface.is_empty() {
+            return;
+        }
+        background_sender
+            .send(Event::RefreshInterface(
+                repaint.clone(),
+                self.selected_wlan_interface.clone(),
+            ))
+            .expect("can send on channel");
+    }
+
+    pub fn change_wlan_interface(
+        &mut self,
+        interface: &str,
+        background_sender: &Sender<Event>,
+        repaint: &Repaint,
+    ) {
+        if interface == self.selected_wlan_interface {
+            return;
+        }
+        self.settings.interface = Some(interface.to_owned());
+        self.select_wlan_interface(interface);
+        self.reload(background_sender, repaint);
+    }
+
+    /// Switches the selected interface on, or off, in the background, the new value is only set
+    /// once the interface actually switched. Returns false, if it's being switched already.
+    pub fn toggle_wlan(&mut self, background_sender: &Sender<Event>, repaint: &Repaint) -> bool {
+        if self.switching_wlan {
+            return false;
+        }
+        self.switching_wlan = true;
+        background_sender
+            .send(Event::SwitchWlan(
+                repaint.clone(),
+                self.selected_wlan_interface.clone(),
+                !self.wlan_on,
+                self.interface_status.clone(),
+            ))
+            .expect("can send on channel");
+        true
+    }
+
+    fn wlan_switched(
+        &mut self,
+        interface: String,
+        wlan_on: bool,
+        result: Result<(), String>,
+        background_sender: &Sender<Event>,
+        repaint: &Repaint,
+    ) {
+        self.switching_wlan = false;
+        match result {
+            Ok(_) if interface == self.selected_wlan_interface => {
+                self.wlan_on = wlan_on;
+                self.error = None;
+                if wlan_on {
+                    background_sender
+                        .send(Event::RefreshNetworks(
+                            repaint.clone(),
+                            interface,
+                            Some(SETTLE_TIME),
+                        ))
+                        .expect("can send on channel");
+                } else {
+                    // if we set the interface to off, we clear the list
+                    self.wlan_networks = Some(vec![]);
+                    self.reload(background_sender, repaint);
+                }
+            }
+            Ok(_) => (),
+            Err(e) => {
+                log::error!("Error while switching wifi on, or off: {}", e);
+                self.error = Some(format!(
+                    "Switching {} {} failed: {}",
+                    interface,
+                    if wlan_on { "on" } else { "off" },
+                    e
+                ));
+            }
+        }
+    }
+
+    /// Blocks, or unblocks all radios in the background, the rfkill events update the state.
+    pub fn set_airplane_mode(
+        &self,
+        on: bool,
+        background_sender: &Sender<Event>,
+        repaint: &Repaint,
+    ) {
+        background_sender
+            .send(Event::SetAirplaneMode(repaint.clone(), on))
+            .expect("can send on channel");
+    }
+
+    pub fn selected_network(&self) -> Option<&WirelessNetwork> {
+        self.wlan_networks
+            .as_ref()?
+            .iter()
+            .find(|wn| wn.id() == self.selected_wlan_network)
+    }
+
+    pub fn open_connect_dialog(&mut self) {
+        self.connect_dialog = self.selected_network().map(|wn| ConnectDialog {
+            ssid: wn.essid.clone(),
+            secured: wn.security_type != crate::SecurityType::Invalid,
+            passphrase: String::new(),
+        });
+    }
+
+    /// Connects to the network of the connect dialog in the background.
+    pub fn connect(&mut self, background_sender: &Sender<Event>, repaint: &Repaint) {
+        let dialog = match self.connect_dialog.take() {
+            Some(dialog) => dialog,
+            None => return,
+        };
+        self.autoconnect_failed.remove(&dialog.ssid);
+        let options = self
+            .rule(&dialog.ssid)
+            .zip(self.wlan_networks.as_deref())
+            .map(|(rule, networks)| rule.connect_options(&dialog.ssid, networks))
+            .unwrap_or_default();
+        self.start_connect(
+            dialog.ssid,
+            Some(dialog.passphrase).filter(|_| dialog.secured),
+            options,
+            background_sender,
+            repaint,
+        );
+    }
+
+    fn start_connect(
+        &mut self,
+        ssid: String,
+        passphrase: Option<String>,
+        options: ConnectOptions,
+        background_sender: &Sender<Event>,
+        repaint: &Repaint,
+    ) {
+        self.error = None;
+        self.connecting = Some(ssid.clone());
+        background_sender
+            .send(Event::Connect(
+                repaint.clone(),
+                self.selected_wlan_interface.clone(),
+                ssid,
+                passphrase,
+                options,
+            ))
+            .expect("can send on channel");
+    }
+
+    /// The rule for the network, matched against its access points in range.
+    fn rule(&self, ssid: &str) -> Option<&rules::Rule> {
+        let bssids: Vec<&str> = self
+            .wlan_networks
+            .iter()
+            .flatten()
+            .filter(|wn| wn.essid == ssid)
+            .map(|wn| wn.address.as_str())
+            .collect();
+        self.rules.as_ref()?.find(ssid, &bssids)
+    }
+
+    /// Connects to the strongest network in range with autoconnect in its rule, while the
+    /// selected interface isn't connected.
+    fn autoconnect(&mut self, background_sender: &Sender<Event>, repaint: &Repaint) {
+        if !self.wlan_on || self.connected_wlan_network.is_some() || self.connecting.is_some() {
+            return;
+        }
+        let (rules, networks) = match (&self.rules, &self.wlan_networks) {
+            (Some(rules), Some(networks)) => (rules, networks),
+            _ => return,
+        };
+        let mut by_signal: Vec<&WirelessNetwork> = networks.iter().collect();
+        by_signal.sort_by_key(|wn| std::cmp::Reverse(wn.quality.value));
+        let candidate = by_signal.into_iter().find_map(|wn| {
+            if wn.essid.is_empty() || self.autoconnect_failed.contains(&wn.essid) {
+                return None;
+            }
+            let rule = rules.find(&wn.essid, &[&wn.address])?;
+            let secured = wn.security_type != SecurityType::Invalid;
+            if !rule.autoconnect || (secured && rule.passphrase.is_none()) {
+                return None;
+            }
+            Some((
+                wn.essid.clone(),
+                rule.passphrase.clone().filter(|_| secured),
+                rule.connect_options(&wn.essid, networks),
+            ))
+        });
+        if let Some((ssid, passphrase, options)) = candidate {
+            log::info!("connecting to {} automatically", ssid);
+            self.start_connect(ssid, passphrase, options, background_sender, repaint);
+        }
+    }
+
+    /// Runs the hooks and sets the DNS servers from the rules, when the selected interface
+    /// disconnected from the previous network, or connected to a new one.
+    fn apply_rules(
+        &self,
+        previous: Option<String>,
+        background_sender: &Sender<Event>,
+        repaint: &Repaint,
+    ) {
+        let interface = &self.selected_wlan_interface;
+        let changes = [
+            (previous.as_ref(), HookEvent::Disconnect),
+            (self.connected_wlan_network.as_ref(), HookEvent::Connect),
+        ];
+        for (ssid, event) in changes {
+            let (ssid, rule) = match ssid.and_then(|ssid| Some((ssid, self.rule(ssid)?))) {
+                Some(matched) => matched,
+                None => continue,
+            };
+            if !rule.dns.is_empty() {
+                let servers = match event {
+                    HookEvent::Connect => rule.dns.clone(),
+                    HookEvent::Disconnect => vec![],
+                };
+                background_sender
+                    .send(Event::SetDns(
+                        repaint.clone(),
+                        interface.clone(),
+                        ssid.clone(),
+                        servers,
+                    ))
+                    .expect("can send on channel");
+            }
+            if let Some(hook) = rule.hook(event, interface, ssid) {
+                background_sender
+                    .send(Event::RunHook(repaint.clone(), hook))
+                    .expect("can send on channel");
+            }
+        }
+    }
+
+    fn log(&mut self, entry: rules::LogEntry) {
+        if entry.success {
+            log::info!("{} ({})", entry.title, entry.ssid);
+        } else {
+            log::warn!("{} ({}): {}", entry.title, entry.ssid, entry.output);
+        }
+        self.rules_log.push(entry);
+        if self.rules_log.len() > MAX_LOG_ENTRIES {
+            self.rules_log.remove(0);
+        }
+    }
+
+    /// Exports the current scan results, or status of the selected interface to the given file.
+    pub fn export(
+        &self,
+        content: export::Content,
+        format: export::Format,
+        path: &str,
+    ) -> Result<()> {
+        let out = match content {
+            export::Content::Scan => {
+                let networks = self
+                    .wlan_networks
+                    .as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("there are no scan results yet"))?;
+                export::networks(&self.selected_wlan_interface, networks, format)?
+            }
+            export::Content::Status => {
+                let status = self
+                    .interface_status
+                    .as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("the status isn't known yet"))?;
+                export::status(&self.selected_wlan_interface, status, format)?
+            }
+        };
+        std::fs::write(path, out)?;
+        Ok(())
+    }
+
+    /// Sorts the current and the cached scan results.
+    pub fn set_sort(&mut self, sort: Sort) {
+        self.settings.sort = sort;
+        let cached_networks = self
+            .interface_cache
+            .values_mut()
+            .filter_map(|cached| cached.networks.as_mut());
+        for networks in self.wlan_networks.iter_mut().chain(cached_networks) {
+            sort.sort(networks);
+        }
+    }
+
+    /// Hands the notification to the notifier, if it's enabled in the settings.
+    pub fn notify(&self, notification: notify::Notification) {
+        if !self.settings.notifications.enabled(&notification) {
+            return;
+        }
+        if let Some(ref notifier) = self.notifier {
+            let _ = notifier.send(notification);
+        }
+    }
+
+    pub fn select_wlan_interface(&mut self, interface: &str) {
+        self.selected_wlan_interface = interface.to_owned();
+        self.selected_wlan_network = String::new();
+        self.saved_networks.clear();
+        self.networks_in_range = None;
+        self.connection_lost = None;
+        self.link_info = None;
+        self.ip_config = None;
+        self.diagnosis.clear();
+        self.bssid = None;
+        let cached = self
+            .interface_cache
+            .entry(interface.to_owned())
+            .or_default();
+        self.wlan_networks = cached.networks.clone();
+        self.error = None;
+        match cached.status {
+            Some(ref status) => {
+                self.wlan_on = status.is_on();
+                self.connected_wlan_network = status.connected_ssid.clone();
+            }
+            None => self.connected_wlan_network = None,
+        }
+        self.interface_status = cached.status.clone();
+        if let Some(ref networks) = self.wlan_networks {
+            if !networks.is_empty() {
+                self.selected_wlan_network = networks[0].id();
+            }
+        }
+    }
+
+    /// Records every access point of the scan, but lists only the strongest of each SSID.
+    pub fn update_networks(
+        &mut self,
+        interface: String,
+        access_points: Option<Vec<WirelessNetwork>>,
+    ) {
+        let is_selected = interface == self.selected_wlan_interface;
+        if let (true, Some(access_points)) = (is_selected, &access_points) {
+            self.record(&interface, access_points);
+        }
+        let networks = access_points.map(|mut networks| {
+            group_access_points(&mut networks);
+            self.settings.sort.sort(&mut networks);
+            networks
+        });
+        if is_selected {
+            if let Some(ref networks) = networks {
+                if !networks.is_empty() {
+                    self.selected_wlan_network = networks[0].id()
+                };
+            }
+            if let Some(ref networks) = networks {
+                self.update_networks_in_range(networks);
+            }
+            self.wlan_networks = networks.clone();
+        }
+        // an in-progress scan keeps the previous results cached
+        if networks.is_some() {
+            self.interface_cache.entry(interface).or_default().networks = networks;
+        }
+    }
+
+    /// Appends the scan to the recording and keeps it as a measurement, if it was taken for
+    /// one. Recording stops, if the scan can't be written.
+    fn record(&mut self, interface: &str, networks: &[WirelessNetwork]) {
+        let position = self.survey_position.take();
+        if self.recorder.is_none() && position.is_none() {
+            return;
+        }
+        let sample = Sample::new(interface, Some(&self.survey_location), position, networks);
+        if let Some(ref mut recorder) = self.recorder {
+            if let Err(e) = recorder.record(&sample) {
+                self.error = Some(format!(
+                    "Recording to {} failed: {}",
+                    recorder.path.display(),
+                    e
+                ));
+                self.recorder = None;
+            }
+        }
+        if position.is_some() {
+            self.measurements.push(sample);
+        }
+    }
+
+    /// Notifies about saved networks, which weren't in range during the previous scan.
+    fn update_networks_in_range(&mut self, networks: &[WirelessNetwork]) {
+        let in_range: HashSet<String> = networks
+            .iter()
+            .filter(|wn| self.saved_networks.contains(&wn.essid))
+            .map(|wn| wn.essid.clone())
+            .collect();
+        if let Some(ref previous) = self.networks_in_range {
+            let mut new: Vec<&String> = in_range
+                .difference(previous)
+                .filter(|ssid| self.connected_wlan_network.as_ref() != Some(*ssid))
+                .collect();
+            new.sort();
+            for ssid in new {
+                self.notify(notify::Notification::NetworkInRange(ssid.to_owned()));
+            }
+        }
+        self.networks_in_range = Some(in_range);
+    }
+
+    /// Updates the list of interfaces and returns the newly selected interface, if the selection
+    /// changed, because the selected interface was removed, or there was none before.
+    pub fn update_wlan_interfaces(
+        &mut self,
+        wlan_interfaces: Vec<WirelessInterface>,
+    ) -> Option<String> {
+        self.interface_cache
+            .retain(|name, _| wlan_interfaces.iter().any(|wi| wi.name == *name));
+        self.wlan_interfaces = wlan_interfaces;
+        if self
+            .wlan_interfaces
+            .iter()
+            .any(|wi| wi.name == self.selected_wlan_interface)
+        {
+            return None;
+        }
+
+        match self.wlan_interfaces.first() {
+            Some(wi) => {
+                let name = wi.name.clone();
+                self.select_wlan_interface(&name);
+                Some(name)
+            }
+            None => {
+                self.selected_wlan_interface = String::new();
+                self.selected_wlan_network = String::new();
+                self.connected_wlan_network = None;
+                self.wlan_networks = None;
+                self.interface_status = None;
+                None
+            }
+        }
+    }
+
+    /// Applies an rfkill event to all interfaces backed by the switch and returns true, if the
+    /// selected interface was turned on by it.
+    pub fn update_rfkill(&mut self, event: rfkill::RfkillEvent) -> bool {
+        let apply = |status: &mut InterfaceStatus| {
+            if status.rfkill.index == Some(event.index) {
+                status.rfkill.soft_blocked = event.soft;
+                status.rfkill.hard_blocked = event.hard;
+            }
+        };
+        self.interface_cache
+            .values_mut()
+            .filter_map(|cached| cached.status.as_mut())
+            .for_each(apply);
+
+        let was_on = self.wlan_on;
+        if let Some(ref mut status) = self.interface_status {
+            apply(status);
+            self.wlan_on = status.is_on();
+            if !self.wlan_on {
+                self.wlan_networks = Some(vec![]);
+            }
+        }
+
+        if event.is_removed() {
+            self.rfkill_devices.remove(&event.index);
+        } else {
+            self.rfkill_devices.insert(event.index, event);
+        }
+        !was_on && self.wlan_on
+    }
+
+    /// Airplane mode is on, if all radios are soft blocked.
+    pub fn airplane_mode(&self) -> bool {
+        !self.rfkill_devices.is_empty() && self.rfkill_devices.values().all(|event| event.soft)
+    }
+
+    pub fn update_interface_status(&mut self, interface: String, status: InterfaceStatus) {
+        if interface == self.selected_wlan_interface {
+            self.update_connection(&status);
+            self.wlan_on = status.is_on();
+            self.connected_wlan_network = status.connected_ssid.clone();
+            if !self.wlan_on {
+                self.wlan_networks = Some(vec![]);
+            }
+            self.interface_status = Some(status.clone());
+        }
+        self.interface_cache.entry(interface).or_default().status = Some(status);
+    }
+
+    /// Notifies, when the selected interface lost its connection while the radio is on, or got
+    /// it back afterwards.
+    fn update_connection(&mut self, status: &InterfaceStatus) {
+        if !status.is_on() {
+            self.connection_lost = None;
+            return;
+        }
+        match (&self.connected_wlan_network, &status.connected_ssid) {
+            (Some(previous), None) => {
+                self.connection_lost = Some(previous.clone());
+                self.notify(notify::Notification::ConnectionLost(previous.clone()));
+            }
+            (previous, Some(ssid))
+                if previous.as_ref() != Some(ssid) && self.connection_lost.is_some() =>
+            {
+                self.connection_lost = None;
+                self.notify(notify::Notification::Reconnected(ssid.clone()));
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Runs the slow commands, like scanning, for the front-end and sends the results back.
+pub fn spawn_background_thread(
+    background_event_receiver: Receiver<Event>,
+    event_sender: Sender<Event>,
+) {
+    std::thread::spawn(move || {
+        while let Ok(event) = background_event_receiver.recv() {
+            match event {
+                Event::RefreshNetworks(repaint, selected_wlan_interface, wait_time) => {
+                    event_sender
+                        .send(Event::UpdateNetworks(selected_wlan_interface.clone(), None))
+                        .expect("can send on channel");
+
+                    if let Some(wait) = wait_time {
+                        std::thread::sleep(wait);
+                    }
+
+                    refresh_networks(&event_sender, &repaint, &selected_wlan_interface);
+                    refresh_interface_status(&event_sender, &repaint, &selected_wlan_interface);
+                }
+                Event::RefreshInterface(repaint, selected_wlan_interface) => {
+                    if let Some(status) =
+                        refresh_interface_status(&event_sender, &repaint, &selected_wlan_interface)
+                    {
+                        if status.up {
+                            refresh_networks(&event_sender, &repaint, &selected_wlan_interface);
+                        }
+                    }
+                }
+                Event::Connect(repaint, selected_wlan_interface, ssid, passphrase, options) => {
+                    let result = backend::get()
+                        .connect(
+                            &selected_wlan_interface,
+                            &ssid,
+                            passphrase.as_deref(),
+                            &options,
+                        )
+                        .map_err(|e| e.to_string());
+                    let connected = result.is_ok();
+                    event_sender
+                        .send(Event::Connected(ssid, result))
+                        .expect("can send on channel");
+                    repaint.request_repaint();
+                    if connected {
+                        std::thread::sleep(CONNECT_SETTLE_TIME);
+                        refresh_interface_status(&event_sender, &repaint, &selected_wlan_interface);
+                    }
+                }
+                Event::RunHook(repaint, hook) => {
+                    // hooks may run until they time out, don't hold up scanning
+                    let event_sender = event_sender.clone();
+                    std::thread::spawn(move || {
+                        event_sender
+                            .send(Event::Log(rules::run_hook(&hook)))
+                            .expect("can send on channel");
+                        repaint.request_repaint();
+                    });
+                }
+                Event::SetDns(repaint, interface, ssid, servers) => {
+                    let result = backend::get().set_dns(&interface, &servers);
+                    event_sender
+                        .send(Event::Log(rules::LogEntry::dns(&ssid, &servers, result)))
+                        .expect("can send on channel");
+                    repaint.request_repaint();
+                }
+                Event::RefreshLinkInfo(repaint, interface) => {
+                    match link::get_link_info(&interface) {
+                        Ok(link_info) => {
+                            event_sender
+                                .send(Event::UpdateLinkInfo(interface, link_info))
+                                .expect("can send on channel");
+                            repaint.request_repaint();
+                        }
+                        Err(e) => log::error!("Error while getting link of {}: {}", interface, e),
+                    }
+                }
+                Event::RefreshIpConfig(repaint, interface) => {
+                    refresh_ip_config(&event_sender, &repaint, &interface);
+                }
+                Event::Diagnose(repaint, interface, probe) => {
+                    // the checks can take a while to time out, don't hold up scanning
+                    let event_sender = event_sender.clone();
+                    std::thread::spawn(move || {
+                        let report = |check| {
+                            event_sender
+                                .send(Event::DiagnosisCheck(interface.clone(), check))
+                                .expect("can send on channel");
+                            repaint.request_repaint();
+                        };
+                        diagnose::diagnose(&diagnose::SystemProbes, &interface, &probe, report);
+                        event_sender
+                            .send(Event::DiagnosisDone)
+                            .expect("can send on channel");
+                        repaint.request_repaint();
+                    });
+                }
+                Event::RefreshBssid(repaint, interface) => match link::get_bssid(&interface) {
+                    Ok(bssid) => {
+                        event_sender
+                            .send(Event::UpdateBssid(interface, bssid))
+                            .expect("can send on channel");
+                        repaint.request_repaint();
+                    }
+                    Err(e) => log::error!(
+                        "Error while getting the access point of {}: {}",
+                        interface,
+                        e
+                    ),
+                },
+                Event::DetectPortal(repaint, bssid, probe) => {
+                    // probing times out slowly without a connection, don't hold up scanning
+                    let event_sender = event_sender.clone();
+                    std::thread::spawn(move || {
+                        let mut result = probe.detect();
+                        for _ in 1..PORTAL_ATTEMPTS {
+                            if result.is_ok() {
+                                break;
+                            }
+                            std::thread::sleep(CONNECT_SETTLE_TIME);
+                            result = probe.detect();
+                        }
+                        event_sender
+                            .send(Event::PortalDetected(
+                                bssid,
+                                result.map_err(|e| e.to_string()),
+                            ))
+                            .expect("can send on channel");
+                        repaint.request_repaint();
+                    });
+                }
+                Event::ExportBundle(repaint, interface, path, anonymize) => {
+                    // gathering runs a lot of commands, don't hold up scanning
+                    let event_sender = event_sender.clone();
+                    std::thread::spawn(move || {
+                        let mut bundle = Bundle::gather(&interface);
+                        if anonymize {
+                            bundle.anonymize();
+                        }
+                        let result = bundle.write(&path).map(|_| path).map_err(|e| e.to_string());
+                        event_sender
+                            .send(Event::BundleExported(result))
+                            .expect("can send on channel");
+                        repaint.request_repaint();
+                    });
+                }
+                Event::SwitchWlan(repaint, interface, on, status) => {
+                    let result = switch_wlan_interface(&interface, on, status.as_ref())
+                        .map_err(|e| e.to_string());
+                    event_sender
+                        .send(Event::WlanSwitched(interface, on, result))
+                        .expect("can send on channel");
+                    repaint.request_repaint();
+                }
+                Event::SetAirplaneMode(repaint, on) => {
+                    let result = backend::get().set_all_rfkill(on).map_err(|e| e.to_string());
+                    event_sender
+                        .send(Event::AirplaneModeSwitched(on, result))
+                        .expect("can send on channel");
+                    repaint.request_repaint();
+                }
+                Event::RenewLease(repaint, interface) => {
+                    let result = backend::get()
+                        .renew_lease(&interface)
+                        .map_err(|e| e.to_string());
+                    let renewed = result.is_ok();
+                    event_sender
+                        .send(Event::LeaseRenewed(interface.clone(), result))
+                        .expect("can send on channel");
+                    repaint.request_repaint();
+                    if renewed {
+                        std::thread::sleep(SETTLE_TIME);
+                        refresh_ip_config(&event_sender, &repaint, &interface);
+                    }
+                }
+                _ => (),
+            }
+        }
+    });
+}
+
+/// Watches for interfaces being added, or removed and for rfkill changes.
+pub fn watch_system(
+    event_sender: &Sender<Event>,
+    repaint: &Repaint,
+    wlan_interfaces: &[WirelessInterface],
+) {
+    hotplug::watch_interfaces(event_sender.clone(), repaint.clone(), wlan_interfaces);
+    backend::get().watch_rfkill(event_sender.clone(), repaint.clone());
+}
+
+fn refresh_networks(event_sender: &Sender<Event>, repaint: &Repaint, interface: &str) {
+    // the saved networks are needed to tell, which of them came into range
+    match backend::get().saved_networks(interface) {
+        Ok(saved_networks) => event_sender
+            .send(Event::UpdateSavedNetworks(
+                interface.to_owned(),
+                saved_networks,
+            ))
+            .expect("can send on channel"),
+        Err(e) => log::debug!("Error while getting saved networks: {}", e),
+    }
+    match scan_for_access_points(interface) {
+        Ok(networks) => {
+            event_sender
+                .send(Event::UpdateNetworks(interface.to_owned(), Some(networks)))
+                .expect("can send on channel");
+            repaint.request_repaint();
+        }
+        Err(e) => log::error!("Error while scanning for networks: {}", e),
+    }
+}
+
+fn refresh_ip_config(event_sender: &Sender<Event>, repaint: &Repaint, interface: &str) {
+    match ipconfig::get_ip_config(interface) {
+        Ok(ip_config) => {
+            event_sender
+                .send(Event::UpdateIpConfig(interface.to_owned(), ip_config))
+                .expect("can send on channel");
+            repaint.request_repaint();
+        }
+        Err(e) => log::error!(
+            "Error while getting IP configuration of {}: {}",
+            interface,
+            e
+        ),
+    }
+}
+
+fn refresh_interface_status(
+    event_sender: &Sender<Event>,
+    repaint: &Repaint,
+    interface: &str,
+) -> Option<InterfaceStatus> {
+    match get_interface_status(interface) {
+        Ok(status) => {
+            event_sender
+                .send(Event::UpdateInterfaceStatus(
+                    interface.to_owned(),
+                    status.clone(),
+                ))
+                .expect("can send on channel");
+            repaint.request_repaint();
+            Some(status)
+        }
+        Err(e) => {
+            log::error!("Error while getting status of {}: {}", interface, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::Backend,
+        helper::MacPolicy,
+        interface_status, parse_access_points, portal,
+        simulate::{Scenario, Simulation},
+        Quality, SecurityType,
+    };
+
+    fn app_state(selected_wlan_interface: &str) -> AppState {
+        AppState::new(vec![], selected_wlan_interface.to_owned())
+    }
+
+    fn network(essid: &str) -> WirelessNetwork {
+        WirelessNetwork {
+            essid: essid.to_owned(),
+            security_type: SecurityType::Wpa2,
+            last_beacon: None,
+            access_points: 1,
+            frequency: 2.437,
+            quality: Quality {
+                value: 42,
+                limit: 70,
+            },
+            address: String::from("D4:1A:D1:51:67:F2"),
+        }
+    }
+
+    #[test]
+    fn switching_interfaces_restores_cached_state() {
+        let mut app_state = app_state("first");
+        app_state.update_interface_status(
+            String::from("first"),
+            InterfaceStatus {
+                up: true,
+                oper_state: String::from("UP"),
+                rfkill: rfkill::RfkillState::default(),
+                connected_ssid: Some(String::from("some network")),
+            },
+        );
+        app_state.update_networks(String::from("first"), Some(vec![network("some network")]));
+        app_state.update_networks(String::from("second"), Some(vec![network("other")]));
+
+        app_state.select_wlan_interface("second");
+        assert_eq!(app_state.wlan_networks, Some(vec![network("other")]));
+        assert_eq!(app_state.connected_wlan_network, None);
+
+        // a scan starting on the selected interface doesn't drop the cache
+        app_state.update_networks(String::from("second"), None);
+        app_state.select_wlan_interface("first");
+        assert_eq!(app_state.wlan_networks, Some(vec![network("some network")]));
+        assert_eq!(
+            app_state.connected_wlan_network,
+            Some(String::from("some network"))
+        );
+        assert_eq!(
+            app_state.selected_wlan_network,
+            network("some network").id()
+        );
+
+        app_state.select_wlan_interface("second");
+        assert_eq!(app_state.wlan_networks, Some(vec![network("other")]));
+    }
+
+    #[test]
+    fn removing_selected_interface_selects_next() {
+        let mut app_state = app_state("");
+        let interface = |name: &str| WirelessInterface {
+            name: name.to_owned(),
+            ..WirelessInterface::default()
+        };
+
+        assert_eq!(
+            app_state.update_wlan_interfaces(vec![interface("wlan0")]),
+            Some(String::from("wlan0"))
+        );
+        assert_eq!(
+            app_state.update_wlan_interfaces(vec![interface("wlan0"), interface("wlan1")]),
+            None
+        );
+        assert_eq!(
+            app_state.update_wlan_interfaces(vec![interface("wlan1")]),
+            Some(String::from("wlan1"))
+        );
+        assert_eq!(app_state.update_wlan_interfaces(vec![]), None);
+        assert_eq!(app_state.selected_wlan_interface, "");
+        assert_eq!(app_state.wlan_networks, None);
+    }
+
+    #[test]
+    fn rfkill_events_switch_interface() {
+        let mut app_state = app_state("wlan0");
+        app_state.update_interface_status(
+            String::from("wlan0"),
+            InterfaceStatus {
+                up: true,
+                oper_state: String::from("UP"),
+                rfkill: rfkill::RfkillState {
+                    index: Some(1),
+                    soft_blocked: false,
+                    hard_blocked: false,
+                },
+                connected_ssid: None,
+            },
+        );
+        let event = |index: u32, soft: bool| rfkill::RfkillEvent {
+            index,
+            rfkill_type: 1,
+            op: 2,
+            soft,
+            hard: false,
+        };
+
+        assert!(!app_state.update_rfkill(event(1, false)));
+        assert!(!app_state.update_rfkill(event(0, true)));
+        assert!(app_state.wlan_on);
+        assert!(!app_state.airplane_mode());
+
+        assert!(!app_state.update_rfkill(event(1, true)));
+        assert!(!app_state.wlan_on);
+        assert!(app_state.airplane_mode());
+
+        assert!(app_state.update_rfkill(event(1, false)));
+        assert!(app_state.wlan_on);
+    }
+
+    #[test]
+    fn notifications() {
+        use notify::Notification;
+
+        let mut app_state = app_state("wlan0");
+        let (notifier, notifications) = std::sync::mpsc::channel();
+        app_state.notifier = Some(notifier);
+        let status = |connected_ssid: Option<&str>| InterfaceStatus {
+            up: true,
+            oper_state: String::from("UP"),
+            rfkill: rfkill::RfkillState::default(),
+            connected_ssid: connected_ssid.map(|ssid| ssid.to_owned()),
+        };
+
+        app_state.update_interface_status(String::from("wlan0"), status(Some("home")));
+        app_state.update_interface_status(String::from("wlan0"), status(None));
+        app_state.update_interface_status(String::from("wlan0"), status(Some("home")));
+        assert_eq!(
+            notifications.try_iter().collect::<Vec<_>>(),
+            vec![
+                Notification::ConnectionLost(String::from("home")),
+                Notification::Reconnected(String::from("home")),
+            ]
+        );
+
+        app_state.saved_networks = HashSet::from([String::from("home"), String::from("cafe")]);
+        app_state.update_networks(String::from("wlan0"), Some(vec![network("home")]));
+        app_state.update_networks(
+            String::from("wlan0"),
+            Some(vec![network("home"), network("cafe"), network("other")]),
+        );
+        app_state.update_networks(String::from("wlan0"), Some(vec![network("cafe")]));
+        assert_eq!(
+            notifications.try_iter().collect::<Vec<_>>(),
+            vec![Notification::NetworkInRange(String::from("cafe"))]
+        );
+
+        app_state.settings.notifications.connect_failed = false;
+        let (background_sender, _background_receiver) = std::sync::mpsc::channel();
+        app_state.handle_event(
+            Event::Connected(String::from("cafe"), Err(String::from("FAIL"))),
+            &background_sender,
+            &Repaint::new(|| ()),
+        );
+        assert!(app_state.error.is_some());
+        assert!(notifications.try_recv().is_err());
+    }
+
+    #[test]
+    fn filter_networks() {
+        let mut app_state = app_state("wlan0");
+        let mut open = network("Cafe Guest");
+        open.security_type = SecurityType::Invalid;
+        open.frequency = 5.18;
+        let mut saved = network("home");
+        saved.address = String::from("00:11:22:33:44:55");
+        app_state.wlan_networks = Some(vec![network("office"), open, saved]);
+        app_state.saved_networks = HashSet::from([String::from("home")]);
+        let visible = |app_state: &AppState| {
+            app_state
+                .wlan_networks
+                .iter()
+                .flatten()
+                .filter(|wn| app_state.filter.matches(wn, &app_state.saved_networks))
+                .map(|wn| wn.essid.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(visible(&app_state), vec!["office", "Cafe Guest", "home"]);
+        assert!(!app_state.filter.is_active());
+
+        app_state.filter.text = String::from(" cafe ");
+        assert_eq!(visible(&app_state), vec!["Cafe Guest"]);
+        app_state.filter.text = String::from("00:11:22");
+        assert_eq!(visible(&app_state), vec!["home"]);
+        app_state.filter.text.clear();
+
+        app_state.filter.hidden_bands.insert(Band::TwoFour);
+        assert_eq!(visible(&app_state), vec!["Cafe Guest"]);
+        app_state.filter.hide_open = true;
+        assert!(visible(&app_state).is_empty());
+        app_state.filter.hidden_bands.clear();
+        assert_eq!(visible(&app_state), vec!["office", "home"]);
+        app_state.filter.only_saved = true;
+        assert_eq!(visible(&app_state), vec!["home"]);
+        assert!(app_state.filter.is_active());
+    }
+
+    #[test]
+    fn rules_on_connection_changes() {
+        let mut app_state = app_state("wlan0");
+        app_state.rules = Some(Rules {
+            networks: vec![rules::Rule {
+                ssid: Some(String::from("home")),
+                dns: vec!["192.168.1.2".parse().unwrap()],
+                on_connect: Some(String::from("mount /mnt/nas")),
+                on_disconnect: Some(String::from("umount /mnt/nas")),
+                ..Default::default()
+            }],
+        });
+        app_state.settings.captive_portal.enabled = false;
+        let (background_sender, background_receiver) = std::sync::mpsc::channel();
+        let mut set_status = |connected_ssid: Option<&str>| {
+            let status = InterfaceStatus {
+                up: true,
+                oper_state: String::from("UP"),
+                rfkill: rfkill::RfkillState::default(),
+                connected_ssid: connected_ssid.map(|ssid| ssid.to_owned()),
+            };
+            app_state.handle_event(
+                Event::UpdateInterfaceStatus(String::from("wlan0"), status),
+                &background_sender,
+                &Repaint::new(|| ()),
+            );
+            background_receiver
+                .try_iter()
+                .map(|event| match event {
+                    Event::SetDns(_, interface, ssid, servers) => {
+                        format!("dns {} {} {:?}", interface, ssid, servers)
+                    }
+                    Event::RunHook(_, hook) => format!("{} {}", hook.event, hook.command),
+                    _ => String::from("other"),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            set_status(Some("home")),
+            vec!["dns wlan0 home [192.168.1.2]", "connect mount /mnt/nas"]
+        );
+        assert!(set_status(Some("home")).is_empty());
+        assert_eq!(
+            set_status(Some("cafe")),
+            vec!["dns wlan0 home []", "disconnect umount /mnt/nas"]
+        );
+        assert!(set_status(None).is_empty());
+    }
+
+    #[test]
+    fn captive_portals_per_bssid() {
+        let mut app_state = app_state("wlan0");
+        let (notifier, notifications) = std::sync::mpsc::channel();
+        app_state.notifier = Some(notifier);
+        let (background_sender, background_receiver) = std::sync::mpsc::channel();
+        let handle = |app_state: &mut AppState, event| {
+            app_state.handle_event(event, &background_sender, &Repaint::new(|| ()));
+            background_receiver
+                .try_iter()
+                .map(|event| match event {
+                    Event::RefreshBssid(_, interface) => format!("bssid {}", interface),
+                    Event::DetectPortal(_, bssid, probe) => {
+                        format!("probe {} {}", bssid, probe.url)
+                    }
+                    _ => String::from("other"),
+                })
+                .collect::<Vec<_>>()
+        };
+        let status = |connected_ssid: Option<&str>| {
+            Event::UpdateInterfaceStatus(
+                String::from("wlan0"),
+                InterfaceStatus {
+                    up: true,
+                    oper_state: String::from("UP"),
+                    rfkill: rfkill::RfkillState::default(),
+                    connected_ssid: connected_ssid.map(|ssid| ssid.to_owned()),
+                },
+            )
+        };
+        let bssid = |bssid: &str| Event::UpdateBssid(String::from("wlan0"), Some(bssid.to_owned()));
+        let login = String::from("http://portal.example/login");
+
+        assert_eq!(
+            handle(&mut app_state, status(Some("hotel"))),
+            ["bssid wlan0"]
+        );
+        assert_eq!(
+            handle(&mut app_state, bssid("D4:1A:D1:51:67:F2")),
+            [format!("probe D4:1A:D1:51:67:F2 {}", portal::PROBE_URL)]
+        );
+        assert!(app_state.detecting_portal);
+        handle(
+            &mut app_state,
+            Event::PortalDetected(
+                String::from("D4:1A:D1:51:67:F2"),
+                Ok(Portal::LoginRequired(login.clone())),
+            ),
+        );
+        assert_eq!(app_state.login_required(), Some(login.as_str()));
+        assert_eq!(
+            notifications.try_iter().collect::<Vec<_>>(),
+            [notify::Notification::LoginRequired(String::from("hotel"))]
+        );
+
+        // reconnecting to a known access point doesn't probe again
+        assert!(handle(&mut app_state, status(None)).is_empty());
+        assert_eq!(app_state.login_required(), None);
+        assert_eq!(
+            handle(&mut app_state, status(Some("hotel"))),
+            ["bssid wlan0"]
+        );
+        assert!(handle(&mut app_state, bssid("D4:1A:D1:51:67:F2")).is_empty());
+        assert_eq!(app_state.login_required(), Some(login.as_str()));
+
+        // checking again after logging in
+        app_state.check_portal_again(&background_sender, &Repaint::new(|| ()));
+        assert_eq!(app_state.login_required(), None);
+        assert_eq!(background_receiver.try_iter().count(), 1);
+
+        // failed probes aren't cached
+        handle(
+            &mut app_state,
+            Event::PortalDetected(
+                String::from("D4:1A:D1:51:67:F2"),
+                Err(String::from("timeout")),
+            ),
+        );
+        assert!(!app_state.detecting_portal);
+        assert_eq!(handle(&mut app_state, bssid("D4:1A:D1:51:67:F2")).len(), 1);
+    }
+
+    #[test]
+    fn record_scans_of_selected_interface() {
+        let path = std::env::temp_dir().join(format!("swelfi-record-{}.jsonl", std::process::id()));
+        let mut app_state = app_state("wlan0");
+        app_state.start_recording(&path);
+        app_state.survey_location = String::from("room 3.14");
+
+        app_state.update_networks(String::from("wlan0"), None);
+        app_state.update_networks(String::from("wlan0"), Some(vec![network("home")]));
+        app_state.update_networks(String::from("wlan1"), Some(vec![network("other")]));
+        app_state.stop_recording();
+        app_state.update_networks(String::from("wlan0"), Some(vec![network("home")]));
+
+        let recording = crate::survey::Recording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(recording.samples.len(), 1);
+        assert_eq!(recording.samples[0].location.as_deref(), Some("room 3.14"));
+        assert_eq!(recording.samples[0].networks, [network("home")]);
+    }
+
+    #[test]
+    fn record_every_access_point() {
+        let scan = "wlan0     Scan completed :
+          Cell 01 - Address: AA:00:00:00:00:01
+                    Channel:36
+                    Frequency:5.18 GHz (Channel 36)
+                    Quality=60/70  Signal level=-50 dBm
+                    Encryption key:on
+                    ESSID:\"home\"
+                    Extra: Last beacon: 100ms ago
+                    IE: IEEE 802.11i/WPA2 Version 1
+                        Group Cipher : CCMP
+                        Pairwise Ciphers (1) : CCMP
+                        Authentication Suites (1) : PSK
+          Cell 02 - Address: AA:00:00:00:00:02
+                    Channel:1
+                    Frequency:2.412 GHz (Channel 1)
+                    Quality=35/70  Signal level=-75 dBm
+                    Encryption key:on
+                    ESSID:\"home\"
+                    Extra: Last beacon: 300ms ago
+                    IE: IEEE 802.11i/WPA2 Version 1
+                        Group Cipher : CCMP
+                        Pairwise Ciphers (1) : CCMP
+                        Authentication Suites (1) : PSK
+          Cell 03 - Address: AA:00:00:00:00:03
+                    Channel:6
+                    Frequency:2.437 GHz (Channel 6)
+                    Quality=14/70  Signal level=-96 dBm
+                    Encryption key:off
+                    ESSID:\"guest\"
+                    Extra: Last beacon: 200ms ago
+          Cell 04 - Address: AA:00:00:00:00:04
+                    Channel:11
+                    Frequency:2.462 GHz (Channel 11)
+                    Quality=40/70  Signal level=-70 dBm
+                    Encryption key:on
+                    ESSID:\"\"
+                    Extra: Last beacon: 100ms ago
+                    IE: IEEE 802.11i/WPA2 Version 1
+                        Group Cipher : CCMP
+                        Pairwise Ciphers (1) : CCMP
+                        Authentication Suites (1) : PSK
+";
+        let path =
+            std::env::temp_dir().join(format!("swelfi-access-points-{}.jsonl", std::process::id()));
+        let mut app_state = app_state("wlan0");
+        app_state.start_recording(&path);
+        app_state.update_networks(
+            String::from("wlan0"),
+            Some(parse_access_points(scan).unwrap()),
+        );
+        app_state.stop_recording();
+
+        let listed: Vec<(&str, usize)> = app_state
+            .wlan_networks
+            .iter()
+            .flatten()
+            .map(|wn| (wn.essid.as_str(), wn.access_points))
+            .collect();
+        assert_eq!(listed, [("home", 2), ("guest", 1)]);
+
+        let recording = crate::survey::Recording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let seen: Vec<(String, u64)> = recording.locations()[0]
+            .networks
+            .iter()
+            .map(|seen| (seen.network.address.clone(), seen.mean))
+            .collect();
+        assert_eq!(
+            seen,
+            [
+                (String::from("AA:00:00:00:00:01"), 85),
+                (String::from("AA:00:00:00:00:02"), 50),
+                (String::from("AA:00:00:00:00:03"), 20),
+            ]
+        );
+    }
+
+    #[test]
+    fn measure_on_floor_plan() {
+        let mut app_state = app_state("wlan0");
+        let (background_sender, background_receiver) = std::sync::mpsc::channel();
+        let repaint = Repaint::new(|| ());
+
+        app_state.update_networks(String::from("wlan0"), Some(vec![network("home")]));
+        assert!(app_state.measurements.is_empty());
+        app_state.measure_at([0.25, 0.75], &background_sender, &repaint);
+        assert!(matches!(
+            background_receiver.try_recv(),
+            Ok(Event::RefreshInterface(_, _))
+        ));
+        app_state.update_networks(String::from("wlan0"), Some(vec![network("guest")]));
+        app_state.update_networks(String::from("wlan0"), Some(vec![network("home")]));
+
+        assert_eq!(app_state.survey_position, None);
+        assert_eq!(app_state.measurements.len(), 1);
+        assert_eq!(app_state.measurements[0].position, Some([0.25, 0.75]));
+        assert_eq!(app_state.measurements[0].networks, [network("guest")]);
+    }
+
+    #[test]
+    fn toggle_wlan_in_the_background() {
+        let mut app_state = app_state("wlan0");
+        app_state.wlan_networks = Some(vec![network("home")]);
+        let (background_sender, background_receiver) = std::sync::mpsc::channel();
+        let repaint = Repaint::new(|| ());
+
+        assert!(app_state.toggle_wlan(&background_sender, &repaint));
+        assert!(!app_state.toggle_wlan(&background_sender, &repaint));
+        assert!(matches!(
+            background_receiver.try_recv(),
+            Ok(Event::SwitchWlan(_, ref interface, false, None)) if interface == "wlan0"
+        ));
+        assert!(background_receiver.try_recv().is_err());
+        assert!(app_state.wlan_on);
+
+        let switched = |result| Event::WlanSwitched(String::from("wlan0"), false, result);
+        app_state.handle_event(
+            switched(Err(String::from("not authorized"))),
+            &background_sender,
+            &repaint,
+        );
+        assert!(app_state.wlan_on);
+        assert_eq!(
+            app_state.error.as_deref(),
+            Some("Switching wlan0 off failed: not authorized")
+        );
+
+        assert!(app_state.toggle_wlan(&background_sender, &repaint));
+        app_state.handle_event(switched(Ok(())), &background_sender, &repaint);
+        assert!(!app_state.wlan_on);
+        assert!(!app_state.switching_wlan);
+        assert_eq!(app_state.error, None);
+        assert_eq!(app_state.wlan_networks, Some(vec![]));
+    }
+
+    #[test]
+    fn autoconnect() {
+        let mut app_state = app_state("wlan0");
+        app_state.rules = Some(Rules {
+            networks: vec![
+                rules::Rule {
+                    ssid: Some(String::from("home")),
+                    autoconnect: true,
+                    passphrase: Some(String::from("correct horse")),
+                    mac: Some(MacPolicy::Random),
+                    ..Default::default()
+                },
+                rules::Rule {
+                    ssid: Some(String::from("cafe")),
+                    autoconnect: true,
+                    ..Default::default()
+                },
+            ],
+        });
+        let (background_sender, background_receiver) = std::sync::mpsc::channel();
+        let repaint = Repaint::new(|| ());
+        let scan = |app_state: &mut AppState| {
+            app_state.handle_event(
+                Event::UpdateNetworks(
+                    String::from("wlan0"),
+                    Some(vec![network("cafe"), network("home"), network("other")]),
+                ),
+                &background_sender,
+                &repaint,
+            );
+            background_receiver
+                .try_iter()
+                .filter_map(|event| match event {
+                    Event::Connect(_, _, ssid, passphrase, options) => {
+                        Some((ssid, passphrase, options.mac))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // the secured cafe has no passphrase in its rule
+        assert_eq!(
+            scan(&mut app_state),
+            vec![(
+                String::from("home"),
+                Some(String::from("correct horse")),
+                Some(MacPolicy::Random)
+            )]
+        );
+        assert!(scan(&mut app_state).is_empty());
+
+        app_state.handle_event(
+            Event::Connected(String::from("home"), Err(String::from("FAIL"))),
+            &background_sender,
+            &repaint,
+        );
+        assert!(scan(&mut app_state).is_empty());
+    }
+
+    #[test]
+    fn connect_in_simulation() {
+        let simulation = Simulation::new(
+            Scenario::parse(
+                r#"
+                [[access_point]]
+                ssid = "home"
+                signal = -50
+                passphrase = "correct horse"
+
+                [[access_point]]
+                ssid = "cafe"
+                security = "open"
+                signal = -70
+                connect = "fails"
+                "#,
+            )
+            .unwrap(),
+        );
+        let mut app_state = app_state("wlan0");
+        let (background_sender, background_receiver) = std::sync::mpsc::channel();
+        let repaint = Repaint::new(|| ());
+
+        // answers like the background thread, with the simulation instead of the system
+        let connect = |app_state: &mut AppState, ssid: &str, passphrase: &str| {
+            app_state.selected_wlan_network = app_state
+                .wlan_networks
+                .iter()
+                .flatten()
+                .find(|wn| wn.essid == ssid)
+                .unwrap()
+                .id();
+            app_state.open_connect_dialog();
+            app_state.connect_dialog.as_mut().unwrap().passphrase = passphrase.to_owned();
+            app_state.connect(&background_sender, &repaint);
+            let events: Vec<Event> = background_receiver.try_iter().collect();
+            for event in events {
+                if let Event::Connect(_, interface, ssid, passphrase, options) = event {
+                    let result = simulation
+                        .connect(&interface, &ssid, passphrase.as_deref(), &options)
+                        .map_err(|e| e.to_string());
+                    let connected = result.is_ok();
+                    app_state.handle_event(
+                        Event::Connected(ssid, result),
+                        &background_sender,
+                        &repaint,
+                    );
+                    if connected {
+                        let status = interface_status(&simulation, &interface).unwrap();
+                        app_state.handle_event(
+                            Event::UpdateInterfaceStatus(interface, status),
+                            &background_sender,
+                            &repaint,
+                        );
+                    }
+                }
+            }
+        };
+
+        let networks = parse_access_points(&simulation.scan("wlan0").unwrap()).unwrap();
+        app_state.handle_event(
+            Event::UpdateNetworks(String::from("wlan0"), Some(networks)),
+            &background_sender,
+            &repaint,
+        );
+        let essids: Vec<&str> = app_state
+            .wlan_networks
+            .iter()
+            .flatten()
+            .map(|wn| wn.essid.as_str())
+            .collect();
+        assert_eq!(essids, ["home", "cafe"]);
+
+        connect(&mut app_state, "home", "wrong horse");
+        assert_eq!(
+            app_state.error.as_deref(),
+            Some("Connecting to home failed: wrong passphrase for home")
+        );
+        assert_eq!(app_state.connected_wlan_network, None);
+
+        connect(&mut app_state, "home", "correct horse");
+        assert_eq!(app_state.error, None);
+        assert_eq!(app_state.connecting, None);
+        assert_eq!(app_state.connected_wlan_network, Some(String::from("home")));
+
+        connect(&mut app_state, "cafe", "ignored");
+        assert_eq!(
+            app_state.error.as_deref(),
+            Some("Connecting to cafe failed: associating with cafe failed")
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{
+    app::{Event, Repaint},
+    error::SwelfiError,
+    helper::{self, ConnectOptions},
+    replay,
+    rfkill::{self, RfkillState},
+    simulate,
+};
+use anyhow::{anyhow, Result};
+use std::{
+    net::IpAddr,
+    path::PathBuf,
+    process::Command,
+    sync::{mpsc::Sender, OnceLock},
+};
+
+/// Where the state of the wlan interfaces comes from, its output is parsed the same either way.
+pub trait Backend: Send + Sync {
+    /// The output of `iw dev`.
+    fn iw_dev(&self) -> Result<String>;
+    /// The output of `iw dev <interface> info`.
+    fn iw_info(&self, interface: &str) -> Result<String>;
+    /// The output of `ip -o link show dev <interface>`.
+    fn ip_link(&self, interface: &str) -> Result<String>;
+    /// The output of `iwlist <interface> scan`.
+    fn scan(&self, interface: &str) -> Result<String>;
+
+    /// Whether this is the state of the system, rather than a replayed, or simulated one.
+    fn is_system(&self) -> bool {
+        true
+    }
+
+    /// The rfkill switch of the interface's phy.
+    fn rfkill(&self, interface: &str) -> Result<RfkillState> {
+        rfkill::state(interface)
+    }
+
+    /// Sends the rfkill switches and their changes, until the receiver is gone.
+    fn watch_rfkill(&self, event_sender: Sender<Event>, repaint: Repaint) {
+        rfkill::watch(event_sender, repaint)
+    }
+
+    /// Changes go to the helper, unless the backend simulates them.
+    fn connect(
+        &self,
+        interface: &str,
+        ssid: &str,
+        passphrase: Option<&str>,
+        options: &ConnectOptions,
+    ) -> Result<()> {
+        helper::Client::default().connect(interface, ssid, passphrase, options)
+    }
+
+    fn disconnect(&self, interface: &str) -> Result<()> {
+        helper::Client::default().disconnect(interface)
+    }
+
+    fn set_link(&self, interface: &str, on: bool) -> Result<()> {
+        helper::Client::default().set_link(interface, on)
+    }
+
+    fn set_rfkill(&self, index: u32, blocked: bool) -> Result<()> {
+        helper::Client::default().set_rfkill(index, blocked)
+    }
+
+    /// Blocks, or unblocks every radio, for airplane mode.
+    fn set_all_rfkill(&self, blocked: bool) -> Result<()> {
+        helper::Client::default().set_all_rfkill(blocked)
+    }
+
+    /// The SSIDs of the networks saved for the interface.
+    fn saved_networks(&self, interface: &str) -> Result<Vec<String>> {
+        helper::Client::default().saved_networks(interface)
+    }
+
+    fn set_dns(&self, interface: &str, servers: &[IpAddr]) -> Result<()> {
+        helper::Client::default().set_dns(interface, servers)
+    }
+
+    fn renew_lease(&self, interface: &str) -> Result<()> {
+        helper::Client::default().renew_lease(interface)
+    }
+}
+
+/// Runs the commands, scanning goes through the helper, as it needs root.
+pub struct System;
+
+impl Backend for System {
+    fn iw_dev(&self) -> Result<String> {
+        run(
+            "iw",
+            &["dev"],
+            String::from("getting wireless interfaces using 'iw' failed"),
+        )
+    }
+
+    fn iw_info(&self, interface: &str) -> Result<String> {
+        run(
+            "iw",
+            &["dev", interface, "info"],
+            format!(
+                "getting wireless interface info for {} using 'iw' failed",
+                interface
+            ),
+        )
+    }
+
+    fn ip_link(&self, interface: &str) -> Result<String> {
+        run(
+            "ip",
+            &["-o", "link", "show", "dev", interface],
+            format!("getting link state for {} using 'ip' failed", interface),
+        )
+    }
+
+    fn scan(&self, interface: &str) -> Result<String> {
+        helper::Client::default().scan(interface)
+    }
+}
+
+fn run(program: &str, args: &[&str], failed: String) -> Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| anyhow!("running '{}' failed: {}", program, e))?;
+    if !output.status.success() {
+        return Err(anyhow!(failed));
+    }
+    String::from_utf8(output.stdout).map_err(|_| {
+        SwelfiError::Parse(format!("output of '{}' wasn't valid utf-8", program)).into()
+    })
+}
+
+/// The backend picked on the command line.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub enum Source {
+    #[default]
+    System,
+    /// A recorded session, or a directory of captured outputs.
+    Replay(PathBuf),
+    /// Runs the commands and records a session to the file.
+    Record(PathBuf),
+    /// Simulates the environment described by the scenario file.
+    Simulate(PathBuf),
+}
+
+static BACKEND: OnceLock<Box<dyn Backend>> = OnceLock::new();
+
+/// Sets the backend for the rest of the process, before anything asks for the state.
+pub fn install(source: Source) -> Result<()> {
+    let backend: Box<dyn Backend> = match source {
+        Source::System => return Ok(()),
+        Source::Replay(path) => Box::new(replay::Replay::load(&path)?),
+        Source::Record(path) => Box::new(replay::Recorder::start(&path, System)?),
+        Source::Simulate(path) => Box::new(simulate::Simulation::load(&path)?),
+    };
+    BACKEND
+        .set(backend)
+        .map_err(|_| anyhow!("the backend was installed already"))
+}
+
+pub fn get() -> &'static dyn Backend {
+    BACKEND.get_or_init(|| Box::new(System)).as_ref()
+}
@@ -0,0 +1,247 @@
+use crate::{
+    app::{self, AppState, Event, Repaint},
+    error::SwelfiError,
+    settings::Settings,
+};
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use std::{fmt, io::Write, str::FromStr, sync::mpsc::channel};
+
+const SECURED: &str = "🔒";
+const OPEN: &str = "🔓";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Format {
+    /// One line of text, for i3blocks (`interval=persist`) and polybar (`tail = true`).
+    Text,
+    /// One JSON object per line, for a waybar custom module with `"return-type": "json"`.
+    Waybar,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(Format::Text),
+            "waybar" => Ok(Format::Waybar),
+            _ => Err(anyhow!("unknown bar format: {}, use text, or waybar", s)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Text => write!(f, "text"),
+            Format::Waybar => write!(f, "waybar"),
+        }
+    }
+}
+
+/// Prints the state of the interface for a status bar, a new line is only written when it
+/// changed. The state is updated on link and rfkill events, instead of polling.
+pub fn run(interface: Option<String>, format: Format) -> Result<()> {
+    let (background_event_sender, background_event_receiver) = channel::<Event>();
+    let (event_sender, event_receiver) = channel::<Event>();
+    app::spawn_background_thread(background_event_receiver, event_sender.clone());
+
+    let mut app_state = AppState::load(Settings::load())?;
+    let repaint = Repaint::new(|| ());
+    if let Some(interface) = interface {
+        if !app_state
+            .wlan_interfaces
+            .iter()
+            .any(|wi| wi.name == interface)
+        {
+            return Err(anyhow!(SwelfiError::NoInterface)
+                .context(format!("{} isn't a wlan interface", interface)));
+        }
+        app_state.change_wlan_interface(&interface, &background_event_sender, &repaint);
+    }
+    app::watch_system(&event_sender, &repaint, &app_state.wlan_interfaces);
+    // the signal comes from the scan results
+    app_state.refresh(&background_event_sender, &repaint);
+
+    let mut stdout = std::io::stdout();
+    let mut last_line = String::new();
+    let mut connected_wlan_network = app_state.connected_wlan_network.clone();
+    loop {
+        let line = output(&app_state, format);
+        if line != last_line {
+            writeln!(stdout, "{}", line)?;
+            stdout.flush()?;
+            last_line = line;
+        }
+
+        let event = match event_receiver.recv() {
+            Ok(event) => event,
+            Err(_) => return Ok(()),
+        };
+        app_state.handle_event(event, &background_event_sender, &repaint);
+        if app_state.connected_wlan_network != connected_wlan_network {
+            connected_wlan_network = app_state.connected_wlan_network.clone();
+            if connected_wlan_network.is_some() {
+                app_state.reload(&background_event_sender, &repaint);
+            }
+        }
+    }
+}
+
+/// Formats the state of the selected interface as one line.
+fn output(app_state: &AppState, format: Format) -> String {
+    let (text, class, percentage, tooltip) = bar_state(app_state);
+    match format {
+        Format::Text => text,
+        Format::Waybar => json!({
+            "text": text,
+            "alt": class,
+            "class": class,
+            "percentage": percentage,
+            "tooltip": tooltip,
+        })
+        .to_string(),
+    }
+}
+
+fn bar_state(app_state: &AppState) -> (String, &'static str, u64, String) {
+    let interface = &app_state.selected_wlan_interface;
+    if interface.is_empty() {
+        return (
+            String::from("no wifi"),
+            "none",
+            0,
+            String::from("No wlan interface found"),
+        );
+    }
+    if !app_state.wlan_on {
+        return (
+            String::from("wifi off"),
+            "disabled",
+            0,
+            format!("{} is switched off", interface),
+        );
+    }
+    if let Some(ref ssid) = app_state.connecting {
+        return (
+            format!("{}…", ssid),
+            "connecting",
+            0,
+            format!("{} is connecting to {}", interface, ssid),
+        );
+    }
+    let ssid = match app_state.connected_wlan_network {
+        Some(ref ssid) => ssid,
+        None => {
+            return (
+                String::from("disconnected"),
+                "disconnected",
+                0,
+                format!("{} isn't connected", interface),
+            )
+        }
+    };
+
+    let mut tooltip = format!("Interface: {}\nSSID: {}", interface, ssid);
+    let network = app_state
+        .wlan_networks
+        .as_ref()
+        .and_then(|networks| networks.iter().find(|wn| wn.essid == *ssid));
+    match network {
+        Some(wn) => {
+            let percentage = wn.quality.percent();
+            let icon = if wn.security_type == crate::SecurityType::Invalid {
+                OPEN
+            } else {
+                SECURED
+            };
+            tooltip.push_str(&format!(
+                "\nSignal: {}%\nSecurity: {}\nFrequency: {} GHz",
+                percentage,
+                wn.security_type.label(),
+                wn.frequency
+            ));
+            (
+                format!("{} {}% {}", ssid, percentage, icon),
+                "connected",
+                percentage,
+                tooltip,
+            )
+        }
+        None => (ssid.to_owned(), "connected", 0, tooltip),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Quality, SecurityType, WirelessNetwork};
+
+    fn app_state() -> AppState {
+        AppState::new(vec![], String::from("wlan0"))
+    }
+
+    #[test]
+    fn text() {
+        let mut app_state = app_state();
+        assert_eq!(output(&app_state, Format::Text), "disconnected");
+
+        app_state.connecting = Some(String::from("home"));
+        assert_eq!(output(&app_state, Format::Text), "home…");
+
+        app_state.connecting = None;
+        app_state.connected_wlan_network = Some(String::from("home"));
+        assert_eq!(output(&app_state, Format::Text), "home");
+
+        app_state.wlan_networks = Some(vec![WirelessNetwork {
+            address: String::from("D4:1A:D1:51:67:F2"),
+            quality: Quality {
+                value: 42,
+                limit: 70,
+            },
+            frequency: 2.437,
+            essid: String::from("home"),
+            security_type: SecurityType::Wpa2,
+            last_beacon: None,
+            access_points: 1,
+        }]);
+        assert_eq!(output(&app_state, Format::Text), "home 60% 🔒");
+
+        app_state.wlan_on = false;
+        assert_eq!(output(&app_state, Format::Text), "wifi off");
+
+        app_state.selected_wlan_interface = String::new();
+        assert_eq!(output(&app_state, Format::Text), "no wifi");
+    }
+
+    #[test]
+    fn waybar() {
+        let mut app_state = app_state();
+        app_state.connected_wlan_network = Some(String::from("cafe"));
+        app_state.wlan_networks = Some(vec![WirelessNetwork {
+            address: String::from("D4:1A:D1:51:67:F2"),
+            quality: Quality {
+                value: 70,
+                limit: 70,
+            },
+            frequency: 5.18,
+            essid: String::from("cafe"),
+            security_type: SecurityType::Invalid,
+            last_beacon: None,
+            access_points: 1,
+        }]);
+
+        let value: serde_json::Value =
+            serde_json::from_str(&output(&app_state, Format::Waybar)).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "text": "cafe 100% 🔓",
+                "alt": "connected",
+                "class": "connected",
+                "percentage": 100,
+                "tooltip": "Interface: wlan0\nSSID: cafe\nSignal: 100%\nSecurity: open\nFrequency: 5.18 GHz",
+            })
+        );
+    }
+}
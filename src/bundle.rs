@@ -0,0 +1,757 @@
+use crate::{backend, export, get_interface_status, helper, link, logs, parse_scan};
+use anyhow::Result;
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::File,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The files are put into this directory of the archive.
+const DIRECTORY: &str = "swelfi-diagnostics";
+const HELPER_LOG_LINES: &str = "500";
+/// Read from `/sys/class/net/<interface>`.
+const SYS_FILES: [&str; 7] = [
+    "operstate",
+    "carrier",
+    "mtu",
+    "device/uevent",
+    "device/vendor",
+    "device/device",
+    "phy80211/name",
+];
+/// Kernel messages mentioning these are kept, along with the interface, its driver and phy.
+const KERNEL_KEYWORDS: [&str; 3] = ["cfg80211", "mac80211", "wlan"];
+
+/// Everything needed to look into a problem with an interface, for support tickets.
+#[derive(Debug, Default)]
+pub struct Bundle {
+    /// Names and contents of the files.
+    files: Vec<(String, String)>,
+    /// SSIDs which showed up while gathering, they are replaced when anonymizing.
+    ssids: BTreeSet<String>,
+}
+
+impl Bundle {
+    /// Gathers the logs, raw and parsed command output, state and driver info of the interface.
+    /// What can't be gathered is noted in the files instead, so it never fails. A replayed, or
+    /// simulated state only has the outputs the backend answers with.
+    pub fn gather(interface: &str) -> Self {
+        let backend = backend::get();
+        let mut bundle = Self::default();
+        let driver = if backend.is_system() {
+            driver(interface)
+        } else {
+            None
+        };
+        bundle.add("summary.txt", summary(interface, driver.as_deref()));
+        bundle.add("swelfi.log", logs::recent().join("\n"));
+
+        bundle.add_output("iw-dev.txt", "iw dev", backend.iw_dev());
+        bundle.add_output(
+            "iw-info.txt",
+            &format!("iw dev {} info", interface),
+            backend.iw_info(interface),
+        );
+        bundle.add_output(
+            "ip-link.txt",
+            &format!("ip -o link show dev {}", interface),
+            backend.ip_link(interface),
+        );
+        bundle.add_scan(interface);
+
+        match get_interface_status(interface) {
+            Ok(status) => {
+                bundle.ssids.extend(status.connected_ssid.clone());
+                bundle.add_result(
+                    "status.json",
+                    export::status(interface, &status, export::Format::Json),
+                );
+            }
+            Err(e) => bundle.add("status.json", format!("getting the status failed: {}", e)),
+        }
+        match backend.saved_networks(interface) {
+            Ok(saved_networks) => {
+                bundle.add("saved-networks.txt", saved_networks.join("\n"));
+                bundle.ssids.extend(saved_networks);
+            }
+            Err(e) => bundle.add(
+                "saved-networks.txt",
+                format!("getting the saved networks failed: {}", e),
+            ),
+        }
+        if backend.is_system() {
+            bundle.add_system(interface, driver);
+        }
+        bundle
+    }
+
+    /// What only the system can tell, the driver, kernel and helper logs and more details.
+    fn add_system(&mut self, interface: &str, driver: Option<String>) {
+        self.add_command(
+            "swelfi-helper.log",
+            "journalctl",
+            &["-u", "swelfi-helper", "-n", HELPER_LOG_LINES, "--no-pager"],
+        );
+        self.add_command("iw-link.txt", "iw", &["dev", interface, "link"]);
+        self.add_command(
+            "iw-station.txt",
+            "iw",
+            &["dev", interface, "station", "dump"],
+        );
+        self.add_command("iw-survey.txt", "iw", &["dev", interface, "survey", "dump"]);
+        self.add(
+            "link.txt",
+            match link::get_link_info(interface) {
+                Ok(link_info) => format!("{:#?}", link_info),
+                Err(e) => format!("getting the link failed: {}", e),
+            },
+        );
+        self.add_command(
+            "ip-details.txt",
+            "ip",
+            &["-details", "link", "show", "dev", interface],
+        );
+        self.add_command("ip-addr.txt", "ip", &["addr", "show", "dev", interface]);
+        self.add_command("ip-route.txt", "ip", &["route", "show"]);
+        self.add_command("rfkill.txt", "rfkill", &["list"]);
+
+        self.add_command("ethtool.txt", "ethtool", &["-i", interface]);
+        for file in SYS_FILES {
+            let path = format!("/sys/class/net/{}/{}", interface, file);
+            let content = std::fs::read_to_string(&path)
+                .unwrap_or_else(|e| format!("reading {} failed: {}", path, e));
+            self.add(&format!("sys/{}", file), content);
+        }
+
+        let mut keywords: Vec<String> = KERNEL_KEYWORDS.map(String::from).into();
+        keywords.push(interface.to_owned());
+        keywords.extend(driver);
+        keywords.extend(
+            std::fs::read_to_string(format!("/sys/class/net/{}/phy80211/name", interface))
+                .ok()
+                .map(|phy| format!("{}:", phy.trim())),
+        );
+        self.add(
+            "kernel.log",
+            filter_kernel_messages(&kernel_messages(), &keywords),
+        );
+    }
+
+    fn add(&mut self, name: &str, content: String) {
+        self.files.push((name.to_owned(), content));
+    }
+
+    fn add_result(&mut self, name: &str, content: Result<String>) {
+        self.add(name, content.unwrap_or_else(|e| format!("failed: {}", e)));
+    }
+
+    fn add_command(&mut self, name: &str, program: &str, args: &[&str]) {
+        self.add(name, command_output(program, args));
+    }
+
+    /// Starts with the command line, like the output of the commands run directly.
+    fn add_output(&mut self, name: &str, command: &str, output: Result<String>) {
+        let output = output.unwrap_or_else(|e| format!("'{}' failed: {}\n", command, e));
+        self.add(name, format!("$ {}\n{}", command, output));
+    }
+
+    /// The raw output of `iwlist` from the backend and the networks parsed from it.
+    fn add_scan(&mut self, interface: &str) {
+        let output = match backend::get().scan(interface) {
+            Ok(output) => output,
+            Err(e) => {
+                self.add("iwlist-scan.txt", format!("scanning failed: {}", e));
+                return;
+            }
+        };
+        let networks = parse_scan(&output);
+        self.add("iwlist-scan.txt", output);
+        match networks {
+            Ok(networks) => {
+                self.ssids
+                    .extend(networks.iter().map(|wn| wn.essid.clone()));
+                self.add_result(
+                    "scan.json",
+                    export::networks(interface, &networks, export::Format::Json),
+                );
+            }
+            Err(e) => self.add("scan.json", format!("parsing the scan failed: {}", e)),
+        }
+    }
+
+    /// Replaces the SSIDs and MAC addresses, each one by the same alias in every file.
+    pub fn anonymize(&mut self) {
+        let mut anonymizer = Anonymizer::new(&self.ssids);
+        for (name, content) in &mut self.files {
+            *content = if name == "saved-networks.txt" {
+                anonymizer.anonymize_lines(content)
+            } else {
+                anonymizer.anonymize(content)
+            };
+        }
+        self.add(
+            "anonymized.txt",
+            String::from("SSIDs and MAC addresses were replaced by aliases.\n"),
+        );
+    }
+
+    /// Writes the files into a gzipped tar archive.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mtime = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut archive =
+            tar::Builder::new(GzEncoder::new(File::create(path)?, Compression::default()));
+        for (name, content) in &self.files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(mtime);
+            archive.append_data(
+                &mut header,
+                format!("{}/{}", DIRECTORY, name),
+                content.as_bytes(),
+            )?;
+        }
+        archive.into_inner()?.finish()?;
+        Ok(())
+    }
+}
+
+/// Where the bundle goes, unless another path is given.
+pub fn default_path() -> PathBuf {
+    let directory = std::env::var("HOME").unwrap_or_else(|_| String::from("."));
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    PathBuf::from(format!("{}/swelfi-diagnostics-{}.tar.gz", directory, time))
+}
+
+/// The output of a command, or why there is none, a missing tool doesn't spoil the bundle.
+fn command_output(program: &str, args: &[&str]) -> String {
+    let mut content = format!("$ {} {}\n", program, args.join(" "));
+    match Command::new(program).args(args).output() {
+        Ok(output) => {
+            content.push_str(&String::from_utf8_lossy(&output.stdout));
+            content.push_str(&String::from_utf8_lossy(&output.stderr));
+            if !output.status.success() {
+                content.push_str(&format!("{} exited with {}\n", program, output.status));
+            }
+        }
+        Err(e) => content.push_str(&format!("running '{}' failed: {}\n", program, e)),
+    }
+    content
+}
+
+fn summary(interface: &str, driver: Option<&str>) -> String {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    // without the host name
+    let kernel = command_output("uname", &["-srvm"]);
+    format!(
+        "swelfi {}\ninterface: {}\ndriver: {}\ncreated: {} (unix time)\n\n{}",
+        env!("CARGO_PKG_VERSION"),
+        interface,
+        driver.unwrap_or("unknown"),
+        time,
+        kernel
+    )
+}
+
+fn driver(interface: &str) -> Option<String> {
+    let driver = std::fs::read_link(format!("/sys/class/net/{}/device/driver", interface)).ok()?;
+    Some(driver.file_name()?.to_string_lossy().into_owned())
+}
+
+/// `dmesg` is restricted on some systems, the journal has the kernel messages as well.
+fn kernel_messages() -> String {
+    match Command::new("dmesg").output() {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        }
+        _ => command_output("journalctl", &["-k", "-b", "--no-pager"]),
+    }
+}
+
+/// Keeps the lines mentioning one of the keywords, case insensitive.
+fn filter_kernel_messages(messages: &str, keywords: &[String]) -> String {
+    let keywords: Vec<String> = keywords.iter().map(|k| k.to_lowercase()).collect();
+    messages
+        .lines()
+        .filter(|line| {
+            let line = line.to_lowercase();
+            keywords.iter().any(|keyword| line.contains(keyword))
+        })
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+/// Where SSIDs are replaced, between these, so a network called `home`, or `up` doesn't change
+/// paths, or link states. `\n` stands for the end of the line.
+const SSID_CONTEXTS: [(&str, &str); 5] = [
+    // iwlist
+    ("ESSID:\"", "\""),
+    // iw link
+    ("SSID: ", "\n"),
+    // iw dev
+    ("\tssid ", "\n"),
+    // autoconnecting and the rules in swelfi.log
+    ("connecting to ", " automatically"),
+    ("(", ")"),
+];
+/// JSON fields with an SSID, the value is matched as a JSON string.
+const SSID_FIELDS: [&str; 2] = ["\"essid\":", "\"connected_ssid\":"];
+/// The SSID element in iwlist's output, its ID 00 is followed by the length and the SSID, in hex.
+const SSID_ELEMENT: &str = "IE: Unknown: 00";
+
+/// Replaces SSIDs and MAC addresses consistently, so the bundle still tells, which lines are
+/// about the same network, or access point.
+struct Anonymizer {
+    /// The spellings of the SSIDs and their aliases.
+    ssids: Vec<(String, String)>,
+    /// The same, as JSON strings.
+    json_ssids: Vec<(String, String)>,
+    /// The bytes of the SSIDs and their aliases.
+    ssid_bytes: Vec<(Vec<u8>, String)>,
+    macs: HashMap<String, String>,
+}
+
+impl Anonymizer {
+    fn new(ssids: &BTreeSet<String>) -> Self {
+        let ssids: Vec<(String, String)> = ssids
+            .iter()
+            .filter(|ssid| !ssid.is_empty())
+            .enumerate()
+            .flat_map(|(i, ssid)| {
+                let alias = format!("ssid-{}", i + 1);
+                spellings(ssid)
+                    .into_iter()
+                    .map(move |spelling| (spelling, alias.clone()))
+            })
+            .collect();
+        let json_ssids = ssids
+            .iter()
+            .map(|(ssid, alias)| (json_string(ssid), json_string(alias)))
+            .collect();
+        let mut ssid_bytes: Vec<(Vec<u8>, String)> = ssids
+            .iter()
+            .map(|(ssid, alias)| (unescape(ssid), alias.clone()))
+            .collect();
+        ssid_bytes.dedup();
+        Self {
+            ssids,
+            json_ssids,
+            ssid_bytes,
+            macs: HashMap::new(),
+        }
+    }
+
+    fn anonymize(&mut self, text: &str) -> String {
+        let mut anonymized = String::with_capacity(text.len());
+        let mut rest = text;
+        let mut previous = None;
+        'next: while let Some(c) = rest.chars().next() {
+            if let Some(mac) = rest.get(..17).filter(|mac| is_mac(mac)) {
+                anonymized.push_str(&self.mac_alias(mac));
+                rest = &rest[17..];
+                previous = None;
+                continue;
+            }
+            if !previous.is_some_and(|previous: char| previous.is_ascii_hexdigit()) {
+                if let Some((mac, len)) = eui64_mac(rest) {
+                    anonymized.push_str(&eui64(&self.mac_alias(&mac)));
+                    rest = &rest[len..];
+                    previous = None;
+                    continue;
+                }
+            }
+            for (before, after) in SSID_CONTEXTS {
+                let value = match rest.strip_prefix(before) {
+                    Some(value) => value,
+                    None => continue,
+                };
+                if let Some((alias, len)) = find_ssid(&self.ssids, value, after) {
+                    anonymized.push_str(before);
+                    anonymized.push_str(alias);
+                    rest = &value[len..];
+                    previous = None;
+                    continue 'next;
+                }
+            }
+            if let Some(value) = rest.strip_prefix(SSID_ELEMENT) {
+                if let Some((alias, len)) = self.ssid_element(value) {
+                    anonymized.push_str(SSID_ELEMENT);
+                    anonymized.push_str(&alias);
+                    rest = &value[len..];
+                    previous = None;
+                    continue;
+                }
+            }
+            for field in SSID_FIELDS {
+                let value = match rest.strip_prefix(field) {
+                    Some(value) => value,
+                    None => continue,
+                };
+                let space = if value.starts_with(' ') { " " } else { "" };
+                let value = &value[space.len()..];
+                if let Some((alias, len)) = find_ssid(&self.json_ssids, value, "") {
+                    anonymized.push_str(field);
+                    anonymized.push_str(space);
+                    anonymized.push_str(alias);
+                    rest = &value[len..];
+                    previous = None;
+                    continue 'next;
+                }
+            }
+            anonymized.push(c);
+            rest = &rest[c.len_utf8()..];
+            previous = Some(c);
+        }
+        anonymized
+    }
+
+    /// For files listing one SSID per line.
+    fn anonymize_lines(&mut self, text: &str) -> String {
+        text.split_inclusive('\n')
+            .map(|line| {
+                let ssid = line.trim_end_matches('\n');
+                match find_ssid(&self.ssids, ssid, "\n") {
+                    Some((alias, len)) => format!("{}{}", alias, &line[len..]),
+                    None => self.anonymize(line),
+                }
+            })
+            .collect()
+    }
+
+    /// The payload of an SSID element with the alias, if it's one of the SSIDs, and the length
+    /// of the payload it replaces.
+    fn ssid_element(&self, payload: &str) -> Option<(String, usize)> {
+        let len = 2 + 2 * usize::from(helper::from_hex(payload.get(..2)?).ok()?[0]);
+        let hex = payload.get(2..len)?;
+        let rest = &payload[len..];
+        if !(rest.is_empty() || rest.starts_with(['\n', '\r'])) {
+            return None;
+        }
+        let bytes = helper::from_hex(hex).ok()?;
+        let (_, alias) = self.ssid_bytes.iter().find(|(ssid, _)| *ssid == bytes)?;
+        let hex: String = alias.bytes().map(|b| format!("{:02X}", b)).collect();
+        Some((format!("{:02X}{}", alias.len(), hex), len))
+    }
+
+    fn mac_alias(&mut self, mac: &str) -> String {
+        let alias = self.macs.len() + 1;
+        self.macs
+            .entry(mac.to_lowercase())
+            .or_insert_with(|| {
+                format!("02:00:00:00:{:02x}:{:02x}", alias >> 8 & 0xff, alias & 0xff)
+            })
+            .clone()
+    }
+}
+
+/// The alias of the SSID at the start of the text, if `after` follows it, and its length.
+fn find_ssid<'a>(
+    ssids: &'a [(String, String)],
+    text: &str,
+    after: &str,
+) -> Option<(&'a str, usize)> {
+    ssids.iter().find_map(|(ssid, alias)| {
+        let rest = text.strip_prefix(ssid.as_str())?;
+        let ends = match after {
+            "\n" => rest.is_empty() || rest.starts_with(['\n', '\r']),
+            after => rest.starts_with(after),
+        };
+        ends.then_some((alias.as_str(), ssid.len()))
+    })
+}
+
+fn json_string(s: &str) -> String {
+    serde_json::Value::from(s).to_string()
+}
+
+/// The ways an SSID shows up, as it is, with the bytes escaped like `\xNN`, the way iw,
+/// iwlist and wpa_cli do, and unescaped.
+fn spellings(ssid: &str) -> Vec<String> {
+    let bytes = unescape(ssid);
+    let mut spellings = vec![ssid.to_owned(), escape(&bytes)];
+    spellings.extend(String::from_utf8(bytes).ok());
+    spellings.sort();
+    spellings.dedup();
+    spellings
+}
+
+/// Like iw, which only keeps printable ascii and spaces, which aren't leading, or trailing.
+fn escape(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| match b {
+            b' ' if i != 0 && i != bytes.len() - 1 => String::from(" "),
+            b'\\' => String::from("\\x5c"),
+            b if b.is_ascii_graphic() => char::from(b).to_string(),
+            b => format!("\\x{:02x}", b),
+        })
+        .collect()
+}
+
+fn unescape(s: &str) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        let escaped = tail
+            .strip_prefix(b"x")
+            .filter(|_| b == b'\\')
+            .and_then(|hex| hex.get(..2))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(escaped) => {
+                bytes.push(escaped);
+                rest = &tail[3..];
+            }
+            None => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+    bytes
+}
+
+/// Like `d4:1a:d1:51:67:f2`, in either case.
+fn is_mac(s: &str) -> bool {
+    s.len() == 17
+        && s.chars().enumerate().all(|(i, c)| {
+            if i % 3 == 2 {
+                c == ':'
+            } else {
+                c.is_ascii_hexdigit()
+            }
+        })
+}
+
+/// The MAC address in an EUI-64 interface identifier at the start of the text, like
+/// `9efc:e8ff:feb8:fa60` at the end of an IPv6 address, and the length of the identifier.
+fn eui64_mac(s: &str) -> Option<(String, usize)> {
+    let mut groups = [0u16; 4];
+    let mut len = 0;
+    for (i, group) in groups.iter_mut().enumerate() {
+        if i > 0 {
+            s[len..].strip_prefix(':')?;
+            len += 1;
+        }
+        let digits = s[len..]
+            .chars()
+            .take_while(|c| c.is_ascii_hexdigit())
+            .count();
+        if !(1..=4).contains(&digits) {
+            return None;
+        }
+        *group = u16::from_str_radix(&s[len..len + digits], 16).ok()?;
+        len += digits;
+    }
+    // more groups follow, so these aren't the last ones
+    if s[len..].starts_with(|c: char| c == ':' || c == '.' || c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let [a, b, c, d] = groups;
+    if b & 0xff != 0xff || c >> 8 != 0xfe {
+        return None;
+    }
+    let mac = format!(
+        "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+        (a >> 8) ^ 0x02,
+        a & 0xff,
+        b >> 8,
+        c & 0xff,
+        d >> 8,
+        d & 0xff
+    );
+    Some((mac, len))
+}
+
+/// The EUI-64 interface identifier of a MAC address, the universal/local bit is flipped.
+fn eui64(mac: &str) -> String {
+    let octets: Vec<u16> = mac
+        .split(':')
+        .map(|octet| u16::from_str_radix(octet, 16).unwrap_or_default())
+        .collect();
+    format!(
+        "{:x}:{:x}:{:x}:{:x}",
+        (octets[0] ^ 0x02) << 8 | octets[1],
+        octets[2] << 8 | 0xff,
+        0xfe00 | octets[3],
+        octets[4] << 8 | octets[5]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn anonymize_ssids_and_macs() {
+        let ssids = BTreeSet::from([String::from("home"), String::from("home 5G"), String::new()]);
+        let mut anonymizer = Anonymizer::new(&ssids);
+
+        assert_eq!(
+            anonymizer.anonymize(
+                "Cell 01 - Address: D4:1A:D1:51:67:F2\n  ESSID:\"home 5G\"\n\
+                 Cell 02 - Address: 00:11:22:33:44:55\n  ESSID:\"home\""
+            ),
+            "Cell 01 - Address: 02:00:00:00:00:01\n  ESSID:\"ssid-2\"\n\
+             Cell 02 - Address: 02:00:00:00:00:02\n  ESSID:\"ssid-1\""
+        );
+        // the same address in another file, or case gets the same alias
+        assert_eq!(
+            anonymizer.anonymize("Connected to d4:1a:d1:51:67:f2 (on wlp64s0)\n\tSSID: home"),
+            "Connected to 02:00:00:00:00:01 (on wlp64s0)\n\tSSID: ssid-1"
+        );
+        assert_eq!(
+            anonymizer.anonymize("fe80::1/64 ⚠ 12:34"),
+            "fe80::1/64 ⚠ 12:34"
+        );
+    }
+
+    #[test]
+    fn anonymize_ssids_only_where_they_are() {
+        let ssids = BTreeSet::from([
+            String::from("up"),
+            String::from("home"),
+            String::from("caf\\xc3\\xa9"),
+        ]);
+        let mut anonymizer = Anonymizer::new(&ssids);
+
+        assert_eq!(
+            anonymizer.anonymize(
+                "$ ip -o link show dev wlan0\n\
+                 3: wlan0: <BROADCAST,MULTICAST,UP,LOWER_UP> state UP\n\
+                 reading /home/user failed\n\
+                 \t\tssid up\n\
+                 \tSSID: café\n\
+                 ESSID:\"caf\\xc3\\xa9\"\n\
+                 \"essid\": \"home\",\n\
+                 \"connected_ssid\": \"caf\\\\xc3\\\\xa9\"\n\
+                 connecting to up automatically\n\
+                 Connected (home): done\n\
+                 IE: Unknown: 0004686F6D65\n\
+                 IE: Unknown: 0005636166C3A9\n\
+                 IE: Unknown: 00086D696E6B616E6574\n\
+                 IE: Unknown: 0004686F6D6565\n\
+                 IE: Unknown: 010882848B962430486C\n"
+            ),
+            "$ ip -o link show dev wlan0\n\
+             3: wlan0: <BROADCAST,MULTICAST,UP,LOWER_UP> state UP\n\
+             reading /home/user failed\n\
+             \t\tssid ssid-3\n\
+             \tSSID: ssid-1\n\
+             ESSID:\"ssid-1\"\n\
+             \"essid\": \"ssid-2\",\n\
+             \"connected_ssid\": \"ssid-1\"\n\
+             connecting to ssid-3 automatically\n\
+             Connected (ssid-2): done\n\
+             IE: Unknown: 0006737369642D32\n\
+             IE: Unknown: 0006737369642D31\n\
+             IE: Unknown: 00086D696E6B616E6574\n\
+             IE: Unknown: 0004686F6D6565\n\
+             IE: Unknown: 010882848B962430486C\n"
+        );
+        assert_eq!(
+            anonymizer.anonymize_lines("home\nup\nhome 5G\n"),
+            "ssid-2\nssid-3\nhome 5G\n"
+        );
+    }
+
+    #[test]
+    fn anonymize_eui64_addresses() {
+        let mut anonymizer = Anonymizer::new(&BTreeSet::new());
+
+        assert_eq!(
+            anonymizer.anonymize(
+                "link/ether 9c:fc:e8:b8:fa:60 brd ff:ff:ff:ff:ff:ff\n\
+                 inet6 2001:db8::9efc:e8ff:feb8:fa60/64 scope global\n\
+                 inet6 fe80::211:22ff:fe33:4455/64 scope link\n\
+                 inet6 fe80::1:2:3:4/64 scope link\n"
+            ),
+            "link/ether 02:00:00:00:00:01 brd 02:00:00:00:00:02\n\
+             inet6 2001:db8::0:ff:fe00:1/64 scope global\n\
+             inet6 fe80::0:ff:fe00:3/64 scope link\n\
+             inet6 fe80::1:2:3:4/64 scope link\n"
+        );
+        assert_eq!(
+            anonymizer.anonymize("00:11:22:33:44:55"),
+            "02:00:00:00:00:03"
+        );
+    }
+
+    #[test]
+    fn filter_kernel() {
+        let messages = "[    1.0] ACPI: button: Power Button [PWRF]
+[    5.1] iwlwifi 0000:00:14.3: loaded firmware version 77.2dda880d.0
+[    5.2] Intel(R) Wireless WiFi driver for Linux
+[    9.3] wlp64s0: authenticate with d4:1a:d1:51:67:f2
+[    9.4] usb 1-2: new high-speed USB device
+[    9.5] IPv6: ADDRCONF(NETDEV_CHANGE): wlp64s0: link becomes ready
+";
+        let keywords = [String::from("wlp64s0"), String::from("IWLWIFI")];
+        assert_eq!(
+            filter_kernel_messages(messages, &keywords),
+            "[    5.1] iwlwifi 0000:00:14.3: loaded firmware version 77.2dda880d.0
+[    9.3] wlp64s0: authenticate with d4:1a:d1:51:67:f2
+[    9.5] IPv6: ADDRCONF(NETDEV_CHANGE): wlp64s0: link becomes ready
+"
+        );
+    }
+
+    #[test]
+    fn write_archive() {
+        let mut bundle = Bundle::default();
+        bundle.add(
+            "iw-link.txt",
+            String::from("Connected to d4:1a:d1:51:67:f2\n"),
+        );
+        bundle.add("sys/operstate", String::from("up\n"));
+        bundle.anonymize();
+        let path = std::env::temp_dir().join(format!("swelfi-test-{}.tar.gz", std::process::id()));
+        bundle.write(&path).unwrap();
+
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(&path).unwrap()));
+        let files: Vec<(String, String)> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let mut content = String::new();
+                entry.read_to_string(&mut content).unwrap();
+                (entry.path().unwrap().display().to_string(), content)
+            })
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            files,
+            [
+                (
+                    String::from("swelfi-diagnostics/iw-link.txt"),
+                    String::from("Connected to 02:00:00:00:00:01\n")
+                ),
+                (
+                    String::from("swelfi-diagnostics/sys/operstate"),
+                    String::from("up\n")
+                ),
+                (
+                    String::from("swelfi-diagnostics/anonymized.txt"),
+                    String::from("SSIDs and MAC addresses were replaced by aliases.\n")
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,797 @@
+use crate::{
+    backend::{self, Source},
+    bar, bundle,
+    diagnose::{self, Outcome},
+    error::SwelfiError,
+    export, get_interface_status, helper, iw, scan_for_networks,
+    settings::{Overrides, Settings, Theme},
+    switch_wlan_interface, SecurityType, WirelessNetwork,
+};
+use anyhow::{anyhow, Result};
+use std::{io::BufRead, path::PathBuf};
+
+const EXIT_OK: i32 = 0;
+const EXIT_ERROR: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_NO_INTERFACE: i32 = 3;
+const EXIT_PERMISSION_DENIED: i32 = 4;
+const EXIT_PARSE: i32 = 5;
+
+const USAGE: &str = "Usage: swelfi [COMMAND]
+
+Without a command, the GUI is started.
+
+Commands:
+  gui [--networks] [<settings>]    start the GUI, focused on the network list with --networks
+  tui [<settings>]                 interactive terminal UI
+  bar [--interface <if>] [--format text|waybar]
+                                   print a line for status bars, whenever the state changes
+  daemon                           publish the state over JSON-RPC for status bars and scripts
+  list                             list wlan interfaces
+  scan [--interface <if>] [--format <f>]
+                                   scan for networks
+  status [--interface <if>] [--format <f>]
+                                   show the state of an interface
+  connect <ssid> [--interface <if>] [--passphrase-stdin]
+                                   connect to a network, reading the passphrase from stdin,
+                                   put -- before an ssid starting with a dash
+  disconnect [--interface <if>]    disconnect from the current network
+  radio on|off [--interface <if>]  switch the radio of an interface on, or off
+  diagnose [--interface <if>]      check the link, address, gateway, DNS and internet access
+  bundle [<file>] [--interface <if>] [--anonymize]
+                                   save logs, command output and driver info as a tar.gz
+  helper                           run the privileged helper (as root)
+  help                             show this message
+
+Settings:
+  --interface <if>, --theme dark|light and --refresh <seconds> (0 turns rescanning off)
+  override the saved settings for this run
+
+Backends:
+  --replay <path>                  answer with a recorded session, or the outputs captured in a
+                                   directory, like an unpacked bundle, instead of running iw
+  --record <file>                  record what iw, ip and iwlist answer, to replay it later
+  --simulate <file>                simulate the access points of a scenario file, connecting
+                                   included, instead of using the wlan interfaces
+
+Formats:
+  json, jsonl (one object per line), or csv instead of a table, see the README for the schema
+
+Exit codes:
+  0 success, 1 error, 2 usage error, 3 no wlan interface, 4 permission denied, 5 parse failure";
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Command {
+    Gui {
+        focus_networks: bool,
+        overrides: Overrides,
+    },
+    Tui {
+        overrides: Overrides,
+    },
+    Daemon,
+    Helper,
+    Help,
+    List,
+    Scan {
+        interface: Option<String>,
+        format: Option<export::Format>,
+    },
+    Status {
+        interface: Option<String>,
+        format: Option<export::Format>,
+    },
+    Connect {
+        interface: Option<String>,
+        ssid: String,
+        passphrase_stdin: bool,
+    },
+    Disconnect {
+        interface: Option<String>,
+    },
+    Radio {
+        interface: Option<String>,
+        on: bool,
+    },
+    Diagnose {
+        interface: Option<String>,
+    },
+    Bundle {
+        interface: Option<String>,
+        path: Option<PathBuf>,
+        anonymize: bool,
+    },
+    Bar {
+        interface: Option<String>,
+        format: bar::Format,
+    },
+}
+
+impl Command {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut args = args.into_iter();
+        let command = match args.next() {
+            Some(command) => command,
+            None => {
+                return Ok(Command::Gui {
+                    focus_networks: false,
+                    overrides: Overrides::default(),
+                })
+            }
+        };
+
+        let mut interface = None;
+        let mut passphrase_stdin = false;
+        let mut focus_networks = false;
+        let mut anonymize = false;
+        let mut format = None;
+        let mut theme = None;
+        let mut refresh_interval = None;
+        let mut positional = vec![];
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--interface" | "-i" => {
+                    interface = Some(
+                        args.next()
+                            .ok_or_else(|| anyhow!("--interface needs a value"))?,
+                    )
+                }
+                "--passphrase-stdin" => passphrase_stdin = true,
+                "--networks" => focus_networks = true,
+                "--anonymize" => anonymize = true,
+                "--theme" => {
+                    theme = Some(
+                        args.next()
+                            .ok_or_else(|| anyhow!("--theme needs a value"))?
+                            .parse::<Theme>()?,
+                    )
+                }
+                "--refresh" => {
+                    let secs = args
+                        .next()
+                        .ok_or_else(|| anyhow!("--refresh needs a value"))?;
+                    refresh_interval = Some(
+                        secs.parse::<u64>()
+                            .map_err(|_| anyhow!("invalid refresh interval: {}", secs))?,
+                    )
+                }
+                "--format" | "-f" => {
+                    format = Some(
+                        args.next()
+                            .ok_or_else(|| anyhow!("--format needs a value"))?,
+                    )
+                }
+                // the rest is positional, even if it starts with a dash
+                "--" => positional.extend(args.by_ref()),
+                _ if arg.starts_with('-') => return Err(anyhow!("unknown option: {}", arg)),
+                _ => positional.push(arg),
+            }
+        }
+
+        // the formats depend on the command
+        let export_format = match (command.as_str(), &format) {
+            ("scan" | "status", Some(format)) => Some(format.parse::<export::Format>()?),
+            _ => None,
+        };
+        let has_overrides = theme.is_some() || refresh_interval.is_some();
+        let overrides = Overrides {
+            interface: interface.clone(),
+            theme,
+            refresh_interval,
+        };
+        let command = match (command.as_str(), positional.as_slice()) {
+            ("gui", []) => Command::Gui {
+                focus_networks,
+                overrides,
+            },
+            ("tui", []) => Command::Tui { overrides },
+            ("daemon", []) => Command::Daemon,
+            ("helper", []) => Command::Helper,
+            ("help" | "--help" | "-h", []) => Command::Help,
+            ("list", []) => Command::List,
+            ("scan", []) => Command::Scan {
+                interface,
+                format: export_format,
+            },
+            ("status", []) => Command::Status {
+                interface,
+                format: export_format,
+            },
+            ("connect", [ssid]) => Command::Connect {
+                interface,
+                ssid: ssid.to_owned(),
+                passphrase_stdin,
+            },
+            ("disconnect", []) => Command::Disconnect { interface },
+            ("radio", [state]) if state == "on" || state == "off" => Command::Radio {
+                interface,
+                on: state == "on",
+            },
+            ("diagnose", []) => Command::Diagnose { interface },
+            ("bundle", []) => Command::Bundle {
+                interface,
+                path: None,
+                anonymize,
+            },
+            ("bundle", [path]) => Command::Bundle {
+                interface,
+                path: Some(PathBuf::from(path)),
+                anonymize,
+            },
+            ("bar", []) => Command::Bar {
+                interface,
+                format: match format {
+                    Some(ref format) => format.parse()?,
+                    None => bar::Format::Text,
+                },
+            },
+            _ => return Err(anyhow!("invalid command: {}", command)),
+        };
+        if passphrase_stdin && !matches!(command, Command::Connect { .. }) {
+            return Err(anyhow!("--passphrase-stdin is only valid for connect"));
+        }
+        if format.is_some()
+            && !matches!(
+                command,
+                Command::Scan { .. } | Command::Status { .. } | Command::Bar { .. }
+            )
+        {
+            return Err(anyhow!("--format is only valid for scan, status and bar"));
+        }
+        if anonymize && !matches!(command, Command::Bundle { .. }) {
+            return Err(anyhow!("--anonymize is only valid for bundle"));
+        }
+        if focus_networks && !matches!(command, Command::Gui { .. }) {
+            return Err(anyhow!("--networks is only valid for gui"));
+        }
+        if has_overrides && !matches!(command, Command::Gui { .. } | Command::Tui { .. }) {
+            return Err(anyhow!(
+                "--theme and --refresh are only valid for gui and tui"
+            ));
+        }
+        Ok(command)
+    }
+}
+
+/// Parses the command and the backend, which can be picked for every command, but the helper.
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<(Command, Source)> {
+    let mut source = Source::System;
+    let mut rest = vec![];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let picked = match arg.as_str() {
+            "--replay" => Source::Replay(PathBuf::from(
+                args.next()
+                    .ok_or_else(|| anyhow!("--replay needs a value"))?,
+            )),
+            "--record" => Source::Record(PathBuf::from(
+                args.next()
+                    .ok_or_else(|| anyhow!("--record needs a value"))?,
+            )),
+            "--simulate" => Source::Simulate(PathBuf::from(
+                args.next()
+                    .ok_or_else(|| anyhow!("--simulate needs a value"))?,
+            )),
+            "--" => {
+                rest.push(arg);
+                rest.extend(args.by_ref());
+                continue;
+            }
+            _ => {
+                rest.push(arg);
+                continue;
+            }
+        };
+        if source != Source::System {
+            return Err(anyhow!(
+                "only one of --replay, --record and --simulate can be given"
+            ));
+        }
+        source = picked;
+    }
+    let command = Command::parse(rest)?;
+    if source != Source::System && command == Command::Helper {
+        return Err(anyhow!(
+            "--replay, --record and --simulate aren't valid for helper"
+        ));
+    }
+    Ok((command, source))
+}
+
+/// Runs a headless command and returns the exit code.
+pub fn run(command: Command) -> i32 {
+    let result = match command {
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Command::List => list(),
+        Command::Scan { interface, format } => scan(interface, format),
+        Command::Status { interface, format } => status(interface, format),
+        Command::Connect {
+            interface,
+            ssid,
+            passphrase_stdin,
+        } => connect(interface, &ssid, passphrase_stdin),
+        Command::Disconnect { interface } => {
+            select_interface(interface).and_then(|interface| backend::get().disconnect(&interface))
+        }
+        Command::Radio { interface, on } => radio(interface, on),
+        Command::Diagnose { interface } => run_diagnose(interface),
+        Command::Bundle {
+            interface,
+            path,
+            anonymize,
+        } => save_bundle(interface, path, anonymize),
+        Command::Bar { interface, format } => bar::run(interface, format),
+        Command::Gui { .. } | Command::Tui { .. } | Command::Daemon | Command::Helper => {
+            unreachable!("not a headless command")
+        }
+    };
+
+    match result {
+        Ok(_) => EXIT_OK,
+        Err(e) => error(&e),
+    }
+}
+
+/// Prints the error and returns the exit code.
+pub fn error(e: &anyhow::Error) -> i32 {
+    eprintln!("swelfi: {}", e);
+    exit_code(e)
+}
+
+/// Prints the usage after a command line parsing error and returns the exit code.
+pub fn usage_error(e: &anyhow::Error) -> i32 {
+    eprintln!("swelfi: {}\n\n{}", e, USAGE);
+    EXIT_USAGE
+}
+
+fn exit_code(e: &anyhow::Error) -> i32 {
+    match e.downcast_ref::<SwelfiError>() {
+        Some(SwelfiError::NoInterface) => EXIT_NO_INTERFACE,
+        Some(SwelfiError::PermissionDenied(_)) => EXIT_PERMISSION_DENIED,
+        Some(SwelfiError::Parse(_)) => EXIT_PARSE,
+        None => EXIT_ERROR,
+    }
+}
+
+/// Picks the given interface, or the first one, if none was given.
+fn select_interface(interface: Option<String>) -> Result<String> {
+    let wlan_interfaces = iw()?;
+    match interface {
+        Some(interface) if wlan_interfaces.iter().any(|wi| wi.name == interface) => Ok(interface),
+        Some(interface) => Err(anyhow!(SwelfiError::NoInterface)
+            .context(format!("{} isn't a wlan interface", interface))),
+        None => wlan_interfaces
+            .into_iter()
+            .next()
+            .map(|wi| wi.name)
+            .ok_or_else(|| SwelfiError::NoInterface.into()),
+    }
+}
+
+fn list() -> Result<()> {
+    let wlan_interfaces = iw()?;
+    if wlan_interfaces.is_empty() {
+        return Err(SwelfiError::NoInterface.into());
+    }
+    let mut rows = vec![];
+    for wi in wlan_interfaces {
+        let status = get_interface_status(&wi.name)?;
+        rows.push(vec![
+            wi.name,
+            on_off(status.is_on()).to_owned(),
+            status.connected_ssid.unwrap_or_default(),
+        ]);
+    }
+    print_table(&["INTERFACE", "RADIO", "CONNECTED"], &rows);
+    Ok(())
+}
+
+fn scan(interface: Option<String>, format: Option<export::Format>) -> Result<()> {
+    let interface = select_interface(interface)?;
+    let networks = scan_for_networks(&interface)?;
+    if let Some(format) = format {
+        print!("{}", export::networks(&interface, &networks, format)?);
+        return Ok(());
+    }
+    print_table(
+        &["SSID", "SIGNAL", "FREQUENCY", "SECURITY", "ADDRESS"],
+        &networks.iter().map(network_row).collect::<Vec<_>>(),
+    );
+    Ok(())
+}
+
+fn network_row(wn: &WirelessNetwork) -> Vec<String> {
+    vec![
+        wn.essid.clone(),
+        format!("{}/{}", wn.quality.value, wn.quality.limit),
+        format!("{} GHz", wn.frequency),
+        format!("{:?}", wn.security_type),
+        wn.address.clone(),
+    ]
+}
+
+fn status(interface: Option<String>, format: Option<export::Format>) -> Result<()> {
+    let interface = select_interface(interface)?;
+    let status = get_interface_status(&interface)?;
+    if let Some(format) = format {
+        print!("{}", export::status(&interface, &status, format)?);
+        return Ok(());
+    }
+    let mut rows = vec![
+        vec![String::from("Interface"), interface],
+        vec![String::from("Radio"), on_off(status.is_on()).to_owned()],
+        vec![String::from("Link state"), status.oper_state.clone()],
+        vec![
+            String::from("Connected"),
+            status.connected_ssid.clone().unwrap_or_default(),
+        ],
+    ];
+    if status.rfkill.hard_blocked {
+        rows.push(vec![
+            String::from("Blocked"),
+            String::from("hardware switch"),
+        ]);
+    } else if status.rfkill.soft_blocked {
+        rows.push(vec![String::from("Blocked"), String::from("rfkill")]);
+    }
+    print_table(&[], &rows);
+    Ok(())
+}
+
+fn connect(interface: Option<String>, ssid: &str, passphrase_stdin: bool) -> Result<()> {
+    let interface = select_interface(interface)?;
+    let passphrase = if passphrase_stdin {
+        let mut passphrase = String::new();
+        std::io::stdin().lock().read_line(&mut passphrase)?;
+        Some(passphrase.trim_end_matches(['\n', '\r']).to_owned())
+    } else {
+        // fail early only if the scan shows the network needs one, otherwise the helper tells
+        match scan_for_networks(&interface) {
+            Ok(networks)
+                if networks.iter().any(|network| {
+                    network.essid == ssid && network.security_type != SecurityType::Invalid
+                }) =>
+            {
+                return Err(anyhow!(
+                    "{} needs a passphrase, pass it with --passphrase-stdin",
+                    ssid
+                ))
+            }
+            Ok(_) => (),
+            Err(e) => log::debug!("scanning before connecting to {} failed: {}", ssid, e),
+        }
+        None
+    };
+    backend::get().connect(
+        &interface,
+        ssid,
+        passphrase.as_deref(),
+        &helper::ConnectOptions::default(),
+    )
+}
+
+fn radio(interface: Option<String>, on: bool) -> Result<()> {
+    let interface = select_interface(interface)?;
+    let status = get_interface_status(&interface)?;
+    if status.rfkill.hard_blocked {
+        return Err(anyhow!(
+            "{} is switched off by a hardware switch",
+            interface
+        ));
+    }
+    switch_wlan_interface(&interface, on, Some(&status))
+}
+
+/// Prints the checks as they finish, fails if any of them did.
+fn run_diagnose(interface: Option<String>) -> Result<()> {
+    let interface = select_interface(interface)?;
+    let passed = diagnose::diagnose(
+        &diagnose::SystemProbes,
+        &interface,
+        &Settings::load().captive_portal,
+        |check| {
+            let stage = check.stage.to_string();
+            match check.outcome {
+                Outcome::Passed(detail) => println!(
+                    "ok    {:19}  {} ({} ms)",
+                    stage,
+                    detail,
+                    check.duration.as_millis()
+                ),
+                Outcome::Failed(reason) => println!(
+                    "FAIL  {:19}  {} ({} ms)",
+                    stage,
+                    reason,
+                    check.duration.as_millis()
+                ),
+                Outcome::Skipped => println!("skip  {}", stage),
+            }
+        },
+    );
+    if !passed {
+        return Err(anyhow!("some checks failed"));
+    }
+    Ok(())
+}
+
+fn save_bundle(interface: Option<String>, path: Option<PathBuf>, anonymize: bool) -> Result<()> {
+    let interface = select_interface(interface)?;
+    let mut bundle = bundle::Bundle::gather(&interface);
+    if anonymize {
+        bundle.anonymize();
+    }
+    let path = path.unwrap_or_else(bundle::default_path);
+    bundle.write(&path)?;
+    println!("saved diagnostics to {}", path.display());
+    Ok(())
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    print!("{}", format_table(headers, rows));
+}
+
+/// Formats rows as left aligned columns, separated by two spaces.
+fn format_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let header_row: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    let all_rows: Vec<&Vec<String>> = std::iter::once(&header_row)
+        .filter(|row| !row.is_empty())
+        .chain(rows.iter())
+        .collect();
+
+    let columns = all_rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            all_rows
+                .iter()
+                .filter_map(|row| row.get(i))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut out = String::new();
+    for row in all_rows {
+        let line: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| format!("{:width$}", cell, width = widths[i]))
+            .collect();
+        out.push_str(line.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(
+            Command::parse(args(&[])).unwrap(),
+            Command::Gui {
+                focus_networks: false,
+                overrides: Overrides::default(),
+            }
+        );
+        assert_eq!(
+            Command::parse(args(&["gui", "--networks"])).unwrap(),
+            Command::Gui {
+                focus_networks: true,
+                overrides: Overrides::default(),
+            }
+        );
+        assert_eq!(
+            Command::parse(args(&[
+                "tui",
+                "-i",
+                "wlan1",
+                "--theme",
+                "light",
+                "--refresh",
+                "0"
+            ]))
+            .unwrap(),
+            Command::Tui {
+                overrides: Overrides {
+                    interface: Some(String::from("wlan1")),
+                    theme: Some(Theme::Light),
+                    refresh_interval: Some(0),
+                }
+            }
+        );
+        assert_eq!(Command::parse(args(&["daemon"])).unwrap(), Command::Daemon);
+        assert_eq!(
+            Command::parse(args(&["scan", "--interface", "wlan0"])).unwrap(),
+            Command::Scan {
+                interface: Some(String::from("wlan0")),
+                format: None,
+            }
+        );
+        assert_eq!(
+            Command::parse(args(&["status", "--format", "jsonl"])).unwrap(),
+            Command::Status {
+                interface: None,
+                format: Some(export::Format::JsonLines),
+            }
+        );
+        assert_eq!(
+            Command::parse(args(&["connect", "some network", "--passphrase-stdin"])).unwrap(),
+            Command::Connect {
+                interface: None,
+                ssid: String::from("some network"),
+                passphrase_stdin: true,
+            }
+        );
+        assert_eq!(
+            Command::parse(args(&["connect", "-i", "wlan1", "--", "-dash"])).unwrap(),
+            Command::Connect {
+                interface: Some(String::from("wlan1")),
+                ssid: String::from("-dash"),
+                passphrase_stdin: false,
+            }
+        );
+        assert!(Command::parse(args(&["connect", "-dash"])).is_err());
+        assert_eq!(
+            Command::parse(args(&["radio", "off", "-i", "wlan1"])).unwrap(),
+            Command::Radio {
+                interface: Some(String::from("wlan1")),
+                on: false,
+            }
+        );
+        assert_eq!(
+            Command::parse(args(&["diagnose", "-i", "wlan1"])).unwrap(),
+            Command::Diagnose {
+                interface: Some(String::from("wlan1"))
+            }
+        );
+        assert_eq!(
+            Command::parse(args(&["bundle", "/tmp/wifi.tar.gz", "--anonymize"])).unwrap(),
+            Command::Bundle {
+                interface: None,
+                path: Some(PathBuf::from("/tmp/wifi.tar.gz")),
+                anonymize: true,
+            }
+        );
+        assert_eq!(
+            Command::parse(args(&["bar", "--format", "waybar"])).unwrap(),
+            Command::Bar {
+                interface: None,
+                format: bar::Format::Waybar,
+            }
+        );
+    }
+
+    #[test]
+    fn reject_invalid_commands() {
+        for invalid in [
+            &["scan", "--interface"][..],
+            &["scan", "--verbose"],
+            &["radio", "sideways"],
+            &["connect"],
+            &["status", "--passphrase-stdin"],
+            &["scan", "--format", "xml"],
+            &["list", "--format", "json"],
+            &["bar", "--format", "json"],
+            &["scan", "--networks"],
+            &["gui", "--theme", "blue"],
+            &["gui", "--refresh", "-1"],
+            &["scan", "--refresh", "10"],
+            &["status", "--anonymize"],
+            &["bundle", "a.tar.gz", "b.tar.gz"],
+            &["frobnicate"],
+        ] {
+            assert!(Command::parse(args(invalid)).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn parse_backends() {
+        assert_eq!(
+            parse(args(&["scan"])).unwrap(),
+            (
+                Command::Scan {
+                    interface: None,
+                    format: None
+                },
+                Source::System
+            )
+        );
+        assert_eq!(
+            parse(args(&["--replay", "session.jsonl"])).unwrap(),
+            (
+                Command::Gui {
+                    focus_networks: false,
+                    overrides: Overrides::default(),
+                },
+                Source::Replay(PathBuf::from("session.jsonl"))
+            )
+        );
+        assert_eq!(
+            parse(args(&["list", "--record", "session.jsonl"])).unwrap(),
+            (
+                Command::List,
+                Source::Record(PathBuf::from("session.jsonl"))
+            )
+        );
+        assert_eq!(
+            parse(args(&["--simulate", "scenario.toml", "daemon"])).unwrap(),
+            (
+                Command::Daemon,
+                Source::Simulate(PathBuf::from("scenario.toml"))
+            )
+        );
+        assert_eq!(
+            parse(args(&["connect", "--", "--replay"])).unwrap(),
+            (
+                Command::Connect {
+                    interface: None,
+                    ssid: String::from("--replay"),
+                    passphrase_stdin: false,
+                },
+                Source::System
+            )
+        );
+        for invalid in [
+            &["list", "--replay"][..],
+            &["list", "--replay", "a", "--record", "b"],
+            &["helper", "--replay", "a"],
+            &["scan", "--simulate", "a", "--replay", "b"],
+            &["list", "--verbose"],
+        ] {
+            assert!(parse(args(invalid)).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(
+            exit_code(&SwelfiError::NoInterface.into()),
+            EXIT_NO_INTERFACE
+        );
+        assert_eq!(
+            exit_code(&anyhow!(SwelfiError::NoInterface).context("wlan9 isn't a wlan interface")),
+            EXIT_NO_INTERFACE
+        );
+        assert_eq!(
+            exit_code(&SwelfiError::PermissionDenied(String::from("not authorized")).into()),
+            EXIT_PERMISSION_DENIED
+        );
+        assert_eq!(
+            exit_code(&SwelfiError::Parse(String::from("parsing 'iw' output failed")).into()),
+            EXIT_PARSE
+        );
+        assert_eq!(exit_code(&anyhow!("something else")), EXIT_ERROR);
+    }
+
+    #[test]
+    fn table() {
+        assert_eq!(
+            format_table(
+                &["SSID", "SIGNAL"],
+                &[
+                    vec![String::from("some network"), String::from("42/70")],
+                    vec![String::from("x"), String::from("7/70")],
+                ]
+            ),
+            "SSID          SIGNAL\nsome network  42/70\nx             7/70\n"
+        );
+    }
+}
@@ -0,0 +1,609 @@
+use crate::{
+    app::{self, AppState, ConnectDialog, Event, Repaint},
+    helper::{self, Handling},
+    rules::Rules,
+    settings::Settings,
+};
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    io::{BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::AtomicUsize,
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+const SOCKET_NAME: &str = "swelfi.sock";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const OPERATION_FAILED: i64 = -32000;
+
+/// A JSON-RPC 2.0 request, requests without an id are notifications and get no response.
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+enum Message {
+    App(Event),
+    Call(Request, Sender<String>),
+}
+
+/// The socket lives in the runtime directory of the user, so only they can talk to the daemon.
+pub fn socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => Path::new(&dir).join(SOCKET_NAME),
+        None => std::env::temp_dir().join(format!(
+            "swelfi-{}.sock",
+            // SAFETY: getuid can't fail
+            unsafe { libc::getuid() }
+        )),
+    }
+}
+
+/// Runs without a front-end and publishes the state over a unix socket, using JSON-RPC 2.0 with
+/// one message per line.
+pub fn serve(socket_path: &Path) -> Result<()> {
+    if socket_path.exists() {
+        std::fs::remove_file(socket_path)?;
+    }
+    let listener = UnixListener::bind(socket_path)?;
+    log::info!("swelfi daemon listening on {}", socket_path.display());
+
+    let (message_sender, message_receiver) = channel::<Message>();
+    let (background_event_sender, background_event_receiver) = channel::<Event>();
+    let (event_sender, event_receiver) = channel::<Event>();
+    app::spawn_background_thread(background_event_receiver, event_sender.clone());
+
+    let forward_sender = message_sender.clone();
+    std::thread::spawn(move || {
+        while let Ok(event) = event_receiver.recv() {
+            if forward_sender.send(Message::App(event)).is_err() {
+                break;
+            }
+        }
+    });
+    std::thread::spawn(move || {
+        let connections = Arc::new(AtomicUsize::new(0));
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log::error!("Error while accepting connection: {}", e);
+                    continue;
+                }
+            };
+            // like the helper, until the client subscribes
+            if let Err(e) = stream
+                .set_read_timeout(Some(helper::SERVER_TIMEOUT))
+                .and_then(|_| stream.set_write_timeout(Some(helper::SERVER_TIMEOUT)))
+            {
+                log::error!("Error while setting timeouts: {}", e);
+                continue;
+            }
+            let handling = match Handling::start(&connections) {
+                Some(handling) => handling,
+                None => {
+                    log::warn!("Turning away connection, too many are handled already");
+                    let error = RpcError::new(OPERATION_FAILED, "too many connections");
+                    let _ = writeln!(stream, "{}", response(Value::Null, Err(error)));
+                    continue;
+                }
+            };
+            let message_sender = message_sender.clone();
+            std::thread::spawn(move || {
+                let _handling = handling;
+                if let Err(e) = handle_connection(stream, message_sender) {
+                    log::error!("Error while handling connection: {}", e);
+                }
+            });
+        }
+    });
+
+    let mut app_state = AppState::load(Settings::load())?;
+    app_state.rules = Some(Rules::load()?);
+    let repaint = Repaint::new(|| ());
+    app::watch_system(&event_sender, &repaint, &app_state.wlan_interfaces);
+    app_state.refresh(&background_event_sender, &repaint);
+
+    let mut subscribers: Vec<Sender<String>> = vec![];
+    let mut last_refresh = Instant::now();
+    loop {
+        let before = (state(&app_state), networks(&app_state));
+        match message_receiver.recv_timeout(Duration::from_secs(1)) {
+            Ok(Message::App(event)) => {
+                app_state.handle_event(event, &background_event_sender, &repaint)
+            }
+            Ok(Message::Call(request, reply_sender)) => {
+                let result = if request.method == "subscribe" {
+                    subscribers.push(reply_sender.clone());
+                    Ok(json!(true))
+                } else {
+                    call(
+                        &mut app_state,
+                        &request.method,
+                        &request.params,
+                        &background_event_sender,
+                        &repaint,
+                    )
+                };
+                if let Some(id) = request.id {
+                    let _ = reply_sender.send(response(id, result));
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let refresh_due = app_state
+            .settings
+            .refresh_interval()
+            .is_some_and(|interval| last_refresh.elapsed() >= interval);
+        if app_state.wlan_on && refresh_due {
+            app_state.reload(&background_event_sender, &repaint);
+            last_refresh = Instant::now();
+        }
+        let notifications = changes(&before, &(state(&app_state), networks(&app_state)));
+        if !notifications.is_empty() {
+            subscribers.retain(|subscriber| {
+                notifications
+                    .iter()
+                    .all(|notification| subscriber.send(notification.clone()).is_ok())
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Reads requests from the connection and passes them to the daemon, responses and
+/// notifications are written by a separate thread, as they can arrive at any time.
+fn handle_connection(stream: UnixStream, message_sender: Sender<Message>) -> Result<()> {
+    let mut writer = stream.try_clone()?;
+    let (line_sender, line_receiver) = channel::<String>();
+    std::thread::spawn(move || {
+        while let Ok(line) = line_receiver.recv() {
+            if writeln!(writer, "{}", line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut reader = BufReader::new(stream);
+    loop {
+        let line = helper::read_line(&mut reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if !line.ends_with('\n') {
+            // the rest of the line can't be told from the next request
+            let error = RpcError::new(
+                INVALID_REQUEST,
+                format!("request is longer than {} bytes", helper::MAX_LINE),
+            );
+            line_sender.send(response(Value::Null, Err(error)))?;
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_request(&line) {
+            Ok(request) => {
+                // subscribers only listen, so they may stay quiet
+                if request.method == "subscribe" {
+                    reader.get_ref().set_read_timeout(None)?;
+                }
+                if message_sender
+                    .send(Message::Call(request, line_sender.clone()))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+            Err(e) => line_sender.send(response(Value::Null, Err(e)))?,
+        }
+    }
+    Ok(())
+}
+
+fn parse_request(line: &str) -> std::result::Result<Request, RpcError> {
+    let value: Value = serde_json::from_str(line)
+        .map_err(|e| RpcError::new(PARSE_ERROR, format!("invalid JSON: {}", e)))?;
+    let request: Request = serde_json::from_value(value)
+        .map_err(|e| RpcError::new(INVALID_REQUEST, format!("invalid request: {}", e)))?;
+    if request.jsonrpc != "2.0" {
+        return Err(RpcError::new(
+            INVALID_REQUEST,
+            "only JSON-RPC 2.0 is supported",
+        ));
+    }
+    Ok(request)
+}
+
+fn response(id: Value, result: std::result::Result<Value, RpcError>) -> String {
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": e.code, "message": e.message },
+        }),
+    };
+    response.to_string()
+}
+
+fn notification(method: &str, params: Value) -> String {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params }).to_string()
+}
+
+fn call(
+    app_state: &mut AppState,
+    method: &str,
+    params: &Value,
+    background_sender: &Sender<Event>,
+    repaint: &Repaint,
+) -> std::result::Result<Value, RpcError> {
+    match method {
+        "get_state" => Ok(state(app_state)),
+        "get_networks" => Ok(networks(app_state)),
+        "select_interface" => {
+            select_interface(app_state, params, background_sender, repaint)?;
+            Ok(state(app_state))
+        }
+        "scan" => {
+            select_interface(app_state, params, background_sender, repaint)?;
+            app_state.refresh(background_sender, repaint);
+            Ok(json!(true))
+        }
+        "connect" => {
+            let ssid = string_param(params, "ssid")?
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, "ssid is missing"))?;
+            let passphrase = string_param(params, "passphrase")?;
+            select_interface(app_state, params, background_sender, repaint)?;
+            app_state.connect_dialog = Some(ConnectDialog {
+                ssid,
+                secured: passphrase.is_some(),
+                passphrase: passphrase.unwrap_or_default(),
+            });
+            app_state.connect(background_sender, repaint);
+            Ok(json!(true))
+        }
+        "set_radio" => {
+            let on = params
+                .get("on")
+                .and_then(Value::as_bool)
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, "on has to be true, or false"))?;
+            select_interface(app_state, params, background_sender, repaint)?;
+            if on != app_state.wlan_on {
+                toggle(app_state, background_sender, repaint)?;
+            }
+            Ok(json!(true))
+        }
+        "toggle" => {
+            select_interface(app_state, params, background_sender, repaint)?;
+            toggle(app_state, background_sender, repaint)?;
+            Ok(json!(true))
+        }
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method: {}", method),
+        )),
+    }
+}
+
+fn string_param(params: &Value, name: &str) -> std::result::Result<Option<String>, RpcError> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value.to_owned())),
+        Some(_) => Err(RpcError::new(
+            INVALID_PARAMS,
+            format!("{} has to be a string", name),
+        )),
+    }
+}
+
+/// Switches to the interface given in the params, if there is one.
+fn select_interface(
+    app_state: &mut AppState,
+    params: &Value,
+    background_sender: &Sender<Event>,
+    repaint: &Repaint,
+) -> std::result::Result<(), RpcError> {
+    let interface = match string_param(params, "interface")? {
+        Some(interface) => interface,
+        None if app_state.selected_wlan_interface.is_empty() => {
+            return Err(RpcError::new(
+                OPERATION_FAILED,
+                "there is no wlan interface",
+            ))
+        }
+        None => return Ok(()),
+    };
+    if !app_state
+        .wlan_interfaces
+        .iter()
+        .any(|wi| wi.name == interface)
+    {
+        return Err(RpcError::new(
+            INVALID_PARAMS,
+            format!("{} isn't a wlan interface", interface),
+        ));
+    }
+    app_state.change_wlan_interface(&interface, background_sender, repaint);
+    Ok(())
+}
+
+fn toggle(
+    app_state: &mut AppState,
+    background_sender: &Sender<Event>,
+    repaint: &Repaint,
+) -> std::result::Result<(), RpcError> {
+    if app_state.toggle_wlan(background_sender, repaint) {
+        Ok(())
+    } else {
+        Err(RpcError::new(
+            OPERATION_FAILED,
+            "the radio is being switched already",
+        ))
+    }
+}
+
+fn state(app_state: &AppState) -> Value {
+    json!({
+        "interface": app_state.selected_wlan_interface,
+        "interfaces": app_state
+            .wlan_interfaces
+            .iter()
+            .map(|wi| wi.name.as_str())
+            .collect::<Vec<_>>(),
+        "radio_on": app_state.wlan_on,
+        "airplane_mode": app_state.airplane_mode(),
+        "status": app_state.interface_status,
+        "connected_ssid": app_state.connected_wlan_network,
+        "connecting": app_state.connecting,
+        "error": app_state.error,
+    })
+}
+
+/// The scan results of the selected interface, networks is null while scanning.
+fn networks(app_state: &AppState) -> Value {
+    json!({
+        "interface": app_state.selected_wlan_interface,
+        "networks": app_state.wlan_networks,
+    })
+}
+
+/// Compares the state and networks before and after handling a message and returns the
+/// notifications for the subscribers.
+fn changes(before: &(Value, Value), after: &(Value, Value)) -> Vec<String> {
+    let mut notifications = vec![];
+    let connection = |state: &Value| {
+        (
+            state["interface"].clone(),
+            state["connected_ssid"].clone(),
+            state["connecting"].clone(),
+        )
+    };
+    if connection(&before.0) != connection(&after.0) {
+        notifications.push(notification(
+            "connection_changed",
+            json!({
+                "interface": after.0["interface"],
+                "connected_ssid": after.0["connected_ssid"],
+                "connecting": after.0["connecting"],
+            }),
+        ));
+    }
+    if before.0 != after.0 {
+        notifications.push(notification("state_changed", after.0.clone()));
+    }
+    if before.1 != after.1 {
+        notifications.push(notification("networks_changed", after.1.clone()));
+    }
+    notifications
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Quality, SecurityType, WirelessInterface, WirelessNetwork};
+    use std::io::BufRead;
+
+    fn app_state() -> AppState {
+        let wlan_interfaces = vec![WirelessInterface {
+            name: String::from("wlan0"),
+            ..WirelessInterface::default()
+        }];
+        AppState::new(wlan_interfaces, String::from("wlan0"))
+    }
+
+    fn call_method(app_state: &mut AppState, method: &str, params: Value) -> Value {
+        let (sender, _receiver) = channel();
+        match call(app_state, method, &params, &sender, &Repaint::new(|| ())) {
+            Ok(result) => result,
+            Err(e) => json!({ "code": e.code }),
+        }
+    }
+
+    #[test]
+    fn parse_requests() {
+        let request = parse_request(r#"{"jsonrpc":"2.0","id":1,"method":"get_state"}"#).unwrap();
+        assert_eq!(request.method, "get_state");
+        assert_eq!(request.id, Some(json!(1)));
+        assert_eq!(request.params, Value::Null);
+
+        let request = parse_request(r#"{"jsonrpc":"2.0","method":"subscribe"}"#).unwrap();
+        assert_eq!(request.id, None);
+
+        assert_eq!(parse_request("{").unwrap_err().code, PARSE_ERROR);
+        assert_eq!(
+            parse_request(r#"{"jsonrpc":"2.0","id":1}"#)
+                .unwrap_err()
+                .code,
+            INVALID_REQUEST
+        );
+        assert_eq!(
+            parse_request(r#"{"jsonrpc":"1.0","id":1,"method":"scan"}"#)
+                .unwrap_err()
+                .code,
+            INVALID_REQUEST
+        );
+    }
+
+    #[test]
+    fn calls() {
+        let mut app_state = app_state();
+        app_state.connected_wlan_network = Some(String::from("home"));
+
+        let state = call_method(&mut app_state, "get_state", Value::Null);
+        assert_eq!(state["interface"], "wlan0");
+        assert_eq!(state["interfaces"], json!(["wlan0"]));
+        assert_eq!(state["connected_ssid"], "home");
+        assert_eq!(
+            call_method(&mut app_state, "get_networks", Value::Null),
+            json!({ "interface": "wlan0", "networks": null })
+        );
+
+        assert_eq!(
+            call_method(&mut app_state, "unknown", Value::Null),
+            json!({ "code": METHOD_NOT_FOUND })
+        );
+        assert_eq!(
+            call_method(&mut app_state, "scan", json!({ "interface": "eth0" })),
+            json!({ "code": INVALID_PARAMS })
+        );
+        assert_eq!(
+            call_method(
+                &mut app_state,
+                "connect",
+                json!({ "passphrase": "secret123" })
+            ),
+            json!({ "code": INVALID_PARAMS })
+        );
+        assert_eq!(
+            call_method(&mut app_state, "set_radio", json!({ "on": "yes" })),
+            json!({ "code": INVALID_PARAMS })
+        );
+
+        assert_eq!(
+            call_method(&mut app_state, "connect", json!({ "ssid": "work" })),
+            json!(true)
+        );
+        assert_eq!(app_state.connecting.as_deref(), Some("work"));
+    }
+
+    #[test]
+    fn notifications() {
+        let mut app_state = app_state();
+        let before = (state(&app_state), networks(&app_state));
+        assert!(changes(&before, &before).is_empty());
+
+        app_state.update_networks(
+            String::from("wlan0"),
+            Some(vec![WirelessNetwork {
+                address: String::from("00:11:22:33:44:55"),
+                essid: String::from("home"),
+                frequency: 2.412,
+                quality: Quality {
+                    value: 42,
+                    limit: 70,
+                },
+                security_type: SecurityType::Wpa2,
+                last_beacon: None,
+                access_points: 1,
+            }]),
+        );
+        let after = (state(&app_state), networks(&app_state));
+        let notifications = changes(&before, &after);
+        assert_eq!(notifications.len(), 1);
+        let notification: Value = serde_json::from_str(&notifications[0]).unwrap();
+        assert_eq!(notification["method"], "networks_changed");
+        assert_eq!(notification["params"]["networks"][0]["essid"], "home");
+
+        let before = after;
+        app_state.connecting = Some(String::from("home"));
+        let methods: Vec<Value> = changes(&before, &(state(&app_state), networks(&app_state)))
+            .iter()
+            .map(|n| serde_json::from_str::<Value>(n).unwrap()["method"].clone())
+            .collect();
+        assert_eq!(methods, vec!["connection_changed", "state_changed"]);
+    }
+
+    #[test]
+    fn connection() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let (message_sender, message_receiver) = channel();
+        std::thread::spawn(move || handle_connection(server, message_sender));
+
+        let mut writer = client.try_clone().unwrap();
+        let mut reader = BufReader::new(client);
+        writeln!(writer, "not json").unwrap();
+        writeln!(writer, r#"{{"jsonrpc":"2.0","id":7,"method":"get_state"}}"#).unwrap();
+
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let error: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(error["error"]["code"], PARSE_ERROR);
+
+        match message_receiver.recv().unwrap() {
+            Message::Call(request, reply_sender) => {
+                assert_eq!(request.method, "get_state");
+                reply_sender
+                    .send(response(request.id.unwrap(), Ok(json!("state"))))
+                    .unwrap();
+            }
+            Message::App(_) => panic!("expected a call"),
+        }
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&line).unwrap(),
+            json!({ "jsonrpc": "2.0", "id": 7, "result": "state" })
+        );
+    }
+
+    #[test]
+    fn overlong_requests() {
+        let (mut client, server) = UnixStream::pair().unwrap();
+        let (message_sender, message_receiver) = channel();
+        let handler = std::thread::spawn(move || handle_connection(server, message_sender));
+
+        client
+            .write_all(&[b' '; helper::MAX_LINE as usize])
+            .unwrap();
+        let mut lines = BufReader::new(client).lines();
+        let error: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(error["error"]["code"], INVALID_REQUEST);
+        assert!(lines.next().is_none());
+        assert!(handler.join().unwrap().is_ok());
+        assert!(message_receiver.try_recv().is_err());
+    }
+}
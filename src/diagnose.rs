@@ -0,0 +1,366 @@
+use crate::{
+    get_interface_status, http, ipconfig,
+    ipconfig::IpConfig,
+    portal::{Portal, Probe},
+    InterfaceStatus,
+};
+use anyhow::{anyhow, Result};
+use std::{
+    fmt,
+    net::{IpAddr, ToSocketAddrs},
+    process::Command,
+    time::{Duration, Instant},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Stage {
+    LinkUp,
+    Associated,
+    Address,
+    Gateway,
+    Dns,
+    Internet,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::LinkUp => write!(f, "Link up"),
+            Stage::Associated => write!(f, "Associated"),
+            Stage::Address => write!(f, "IP address"),
+            Stage::Gateway => write!(f, "Gateway reachable"),
+            Stage::Dns => write!(f, "DNS resolves"),
+            Stage::Internet => write!(f, "Probe URL reachable"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Outcome {
+    Passed(String),
+    Failed(String),
+    /// A stage it depends on failed.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Check {
+    pub stage: Stage,
+    pub outcome: Outcome,
+    pub duration: Duration,
+}
+
+/// Everything the checks need from the system, so tests can stand in for the network.
+pub trait Probes {
+    fn interface_status(&self, interface: &str) -> Result<InterfaceStatus>;
+    fn ip_config(&self, interface: &str) -> Result<IpConfig>;
+    /// Returns how the gateway answered.
+    fn reach(&self, interface: &str, gateway: IpAddr) -> Result<String>;
+    fn resolve(&self, host: &str) -> Result<Vec<IpAddr>>;
+    fn http_get(&self, url: &str) -> Result<http::Response>;
+}
+
+pub struct SystemProbes;
+
+impl Probes for SystemProbes {
+    fn interface_status(&self, interface: &str) -> Result<InterfaceStatus> {
+        get_interface_status(interface)
+    }
+
+    fn ip_config(&self, interface: &str) -> Result<IpConfig> {
+        ipconfig::get_ip_config(interface)
+    }
+
+    /// Pings the gateway, many of them drop ICMP though, so a neighbour entry counts as well.
+    fn reach(&self, interface: &str, gateway: IpAddr) -> Result<String> {
+        let gateway = gateway.to_string();
+        let ping = Command::new("ping")
+            .args(["-c", "1", "-W", "1", "-I", interface, &gateway])
+            .output()?;
+        if ping.status.success() {
+            return Ok(String::from("answers ping"));
+        }
+        let neighbour = Command::new("ip")
+            .args(["neigh", "show", &gateway, "dev", interface])
+            .output()?;
+        let neighbour = String::from_utf8_lossy(&neighbour.stdout);
+        if ["REACHABLE", "STALE", "DELAY", "PERMANENT"]
+            .iter()
+            .any(|state| neighbour.contains(state))
+        {
+            return Ok(String::from("answers ARP, but not ping"));
+        }
+        Err(anyhow!("doesn't answer ping, or ARP"))
+    }
+
+    fn resolve(&self, host: &str) -> Result<Vec<IpAddr>> {
+        Ok((host, 0)
+            .to_socket_addrs()?
+            .map(|address| address.ip())
+            .collect())
+    }
+
+    fn http_get(&self, url: &str) -> Result<http::Response> {
+        http::get(url, TIMEOUT)
+    }
+}
+
+/// Reports the checks and remembers, if all of them passed.
+struct Checks<R> {
+    report: R,
+    passed: bool,
+}
+
+impl<R: FnMut(Check)> Checks<R> {
+    /// Skips the check, unless it's `ready`. Returns true, if it passed.
+    fn run(&mut self, stage: Stage, ready: bool, check: impl FnOnce() -> Result<String>) -> bool {
+        let start = Instant::now();
+        let outcome = if ready {
+            match check() {
+                Ok(detail) => Outcome::Passed(detail),
+                Err(e) => Outcome::Failed(e.to_string()),
+            }
+        } else {
+            Outcome::Skipped
+        };
+        let passed = matches!(outcome, Outcome::Passed(_));
+        self.passed &= passed;
+        (self.report)(Check {
+            stage,
+            outcome,
+            duration: start.elapsed(),
+        });
+        passed
+    }
+}
+
+/// Runs the checks in order and reports each one, as soon as it's done. Checks, which depend on
+/// a failed one are skipped. Returns true, if all of them passed.
+pub fn diagnose(
+    probes: &impl Probes,
+    interface: &str,
+    probe: &Probe,
+    report: impl FnMut(Check),
+) -> bool {
+    let mut checks = Checks {
+        report,
+        passed: true,
+    };
+
+    let mut status = None;
+    let link_up = checks.run(Stage::LinkUp, true, || {
+        let s = probes.interface_status(interface)?;
+        let result = if s.rfkill.hard_blocked || s.rfkill.soft_blocked {
+            Err(anyhow!("blocked by rfkill"))
+        } else if !s.up {
+            Err(anyhow!("{} is down", interface))
+        } else {
+            Ok(format!("state {}", s.oper_state))
+        };
+        status = Some(s);
+        result
+    });
+
+    let associated = checks.run(Stage::Associated, link_up, || {
+        match status.as_ref().and_then(|s| s.connected_ssid.as_ref()) {
+            Some(ssid) => Ok(format!("to {}", ssid)),
+            None => Err(anyhow!("not connected to a network")),
+        }
+    });
+
+    let mut ip_config = None;
+    let has_address = checks.run(Stage::Address, associated, || {
+        let config = probes.ip_config(interface)?;
+        let global: Vec<String> = config
+            .addresses
+            .iter()
+            .filter(|address| address.scope == "global")
+            .map(|address| address.to_string())
+            .collect();
+        ip_config = Some(config);
+        if global.is_empty() {
+            Err(anyhow!("no global address, DHCP might have failed"))
+        } else {
+            Ok(global.join(", "))
+        }
+    });
+
+    checks.run(Stage::Gateway, has_address, || {
+        let gateway = ip_config
+            .as_ref()
+            .and_then(|config| config.gateways.first().copied())
+            .ok_or_else(|| anyhow!("no default route"))?;
+        probes
+            .reach(interface, gateway)
+            .map(|how| format!("{} {}", gateway, how))
+            .map_err(|e| anyhow!("{} {}", gateway, e))
+    });
+
+    let resolves = checks.run(Stage::Dns, has_address, || {
+        let (host, _, _) = http::parse_url(&probe.url)?;
+        match probes.resolve(&host)?.first() {
+            Some(address) => Ok(format!("{} is {}", host, address)),
+            None => Err(anyhow!("{} has no address", host)),
+        }
+    });
+
+    checks.run(Stage::Internet, resolves, || {
+        let response = probes.http_get(&probe.url)?;
+        match probe.classify(&response) {
+            Portal::None => Ok(format!("HTTP {}", response.status)),
+            Portal::LoginRequired(url) => Err(anyhow!("captive portal, log in at {}", url)),
+        }
+    });
+
+    checks.passed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ipconfig::Address, rfkill::RfkillState, tests::serve_http};
+
+    /// The interface is faked, name resolution and HTTP go to a local stand-in.
+    struct FakeProbes {
+        status: InterfaceStatus,
+        ip_config: IpConfig,
+        gateway_answers: bool,
+    }
+
+    impl Default for FakeProbes {
+        fn default() -> Self {
+            Self {
+                status: InterfaceStatus {
+                    up: true,
+                    oper_state: String::from("UP"),
+                    rfkill: RfkillState::default(),
+                    connected_ssid: Some(String::from("home")),
+                },
+                ip_config: IpConfig {
+                    addresses: vec![Address {
+                        address: "192.168.1.23".parse().unwrap(),
+                        prefix: 24,
+                        scope: String::from("global"),
+                        dynamic: true,
+                        valid_lifetime: None,
+                    }],
+                    gateways: vec!["192.168.1.1".parse().unwrap()],
+                    ..IpConfig::default()
+                },
+                gateway_answers: true,
+            }
+        }
+    }
+
+    impl Probes for FakeProbes {
+        fn interface_status(&self, _interface: &str) -> Result<InterfaceStatus> {
+            Ok(self.status.clone())
+        }
+
+        fn ip_config(&self, _interface: &str) -> Result<IpConfig> {
+            Ok(self.ip_config.clone())
+        }
+
+        fn reach(&self, _interface: &str, _gateway: IpAddr) -> Result<String> {
+            if self.gateway_answers {
+                Ok(String::from("answers ping"))
+            } else {
+                Err(anyhow!("doesn't answer ping, or ARP"))
+            }
+        }
+
+        fn resolve(&self, host: &str) -> Result<Vec<IpAddr>> {
+            SystemProbes.resolve(host)
+        }
+
+        fn http_get(&self, url: &str) -> Result<http::Response> {
+            SystemProbes.http_get(url)
+        }
+    }
+
+    fn outcomes(probes: &FakeProbes, url: &str) -> (bool, Vec<(Stage, Outcome)>) {
+        let mut checks = vec![];
+        let probe = Probe {
+            url: url.to_owned(),
+            ..Probe::default()
+        };
+        let passed = diagnose(probes, "wlan0", &probe, |check| {
+            checks.push((check.stage, check.outcome))
+        });
+        (passed, checks)
+    }
+
+    fn passed(detail: &str) -> Outcome {
+        Outcome::Passed(detail.to_owned())
+    }
+
+    fn failed(reason: &str) -> Outcome {
+        Outcome::Failed(reason.to_owned())
+    }
+
+    #[test]
+    fn all_checks_pass() {
+        let url = serve_http("HTTP/1.1 204 No Content\r\n\r\n");
+
+        assert_eq!(
+            outcomes(&FakeProbes::default(), &url),
+            (
+                true,
+                vec![
+                    (Stage::LinkUp, passed("state UP")),
+                    (Stage::Associated, passed("to home")),
+                    (Stage::Address, passed("192.168.1.23/24")),
+                    (Stage::Gateway, passed("192.168.1.1 answers ping")),
+                    (Stage::Dns, passed("127.0.0.1 is 127.0.0.1")),
+                    (Stage::Internet, passed("HTTP 204")),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn later_checks_are_skipped() {
+        let mut probes = FakeProbes::default();
+        probes.status.connected_ssid = None;
+
+        assert_eq!(
+            outcomes(&probes, "http://127.0.0.1:9/").1,
+            vec![
+                (Stage::LinkUp, passed("state UP")),
+                (Stage::Associated, failed("not connected to a network")),
+                (Stage::Address, Outcome::Skipped),
+                (Stage::Gateway, Outcome::Skipped),
+                (Stage::Dns, Outcome::Skipped),
+                (Stage::Internet, Outcome::Skipped),
+            ]
+        );
+    }
+
+    #[test]
+    fn silent_gateway_and_intercepted_probe() {
+        let url = serve_http("HTTP/1.1 302 Found\r\nLocation: http://portal.example/\r\n\r\n");
+        let probes = FakeProbes {
+            gateway_answers: false,
+            ..FakeProbes::default()
+        };
+
+        let (all_passed, checks) = outcomes(&probes, &url);
+        assert!(!all_passed);
+        assert_eq!(
+            checks[3..],
+            [
+                (
+                    Stage::Gateway,
+                    failed("192.168.1.1 doesn't answer ping, or ARP")
+                ),
+                (Stage::Dns, passed("127.0.0.1 is 127.0.0.1")),
+                (
+                    Stage::Internet,
+                    failed("captive portal, log in at http://portal.example/")
+                ),
+            ]
+        );
+    }
+}
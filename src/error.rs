@@ -0,0 +1,21 @@
+use std::fmt;
+
+/// Errors callers need to tell apart, e.g. to pick the exit code of the CLI.
+#[derive(Debug)]
+pub enum SwelfiError {
+    NoInterface,
+    PermissionDenied(String),
+    Parse(String),
+}
+
+impl fmt::Display for SwelfiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SwelfiError::NoInterface => write!(f, "there is no wlan interface"),
+            SwelfiError::PermissionDenied(reason) => write!(f, "permission denied: {}", reason),
+            SwelfiError::Parse(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SwelfiError {}
@@ -0,0 +1,292 @@
+use crate::{InterfaceStatus, WirelessNetwork};
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::{fmt, str::FromStr};
+
+/// Bumped on every incompatible change of the exported fields, see the `schema_*` tests below.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Format {
+    Json,
+    JsonLines,
+    Csv,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Json, Format::JsonLines, Format::Csv];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::JsonLines => "jsonl",
+            Format::Csv => "csv",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(Format::Json),
+            "jsonl" => Ok(Format::JsonLines),
+            "csv" => Ok(Format::Csv),
+            _ => Err(anyhow!("invalid format: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+/// What the GUI exports.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Content {
+    Scan,
+    Status,
+}
+
+impl Content {
+    pub const ALL: [Content; 2] = [Content::Scan, Content::Status];
+}
+
+impl fmt::Display for Content {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Content::Scan => write!(f, "scan"),
+            Content::Status => write!(f, "status"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Scan<'a> {
+    schema_version: u32,
+    interface: &'a str,
+    networks: &'a [WirelessNetwork],
+}
+
+#[derive(Serialize)]
+struct ScanLine<'a> {
+    schema_version: u32,
+    interface: &'a str,
+    #[serde(flatten)]
+    network: &'a WirelessNetwork,
+}
+
+#[derive(Serialize)]
+struct Status<'a> {
+    schema_version: u32,
+    interface: &'a str,
+    #[serde(flatten)]
+    status: &'a InterfaceStatus,
+}
+
+/// Formats the result of a scan, JSON lines and CSV have one network per line.
+pub fn networks(interface: &str, networks: &[WirelessNetwork], format: Format) -> Result<String> {
+    match format {
+        Format::Json => Ok(serde_json::to_string_pretty(&Scan {
+            schema_version: SCHEMA_VERSION,
+            interface,
+            networks,
+        })? + "\n"),
+        Format::JsonLines => networks
+            .iter()
+            .map(|network| {
+                serde_json::to_string(&ScanLine {
+                    schema_version: SCHEMA_VERSION,
+                    interface,
+                    network,
+                })
+                .map(|line| line + "\n")
+                .map_err(|e| e.into())
+            })
+            .collect(),
+        Format::Csv => {
+            let mut out = csv_row(&[
+                "schema_version",
+                "interface",
+                "address",
+                "essid",
+                "frequency",
+                "quality_value",
+                "quality_limit",
+                "security_type",
+            ]);
+            for wn in networks {
+                out.push_str(&csv_row(&[
+                    &SCHEMA_VERSION.to_string(),
+                    interface,
+                    &wn.address,
+                    &wn.essid,
+                    &wn.frequency.to_string(),
+                    &wn.quality.value.to_string(),
+                    &wn.quality.limit.to_string(),
+                    security_type_name(wn)?.as_str(),
+                ]));
+            }
+            Ok(out)
+        }
+    }
+}
+
+/// Formats the status of an interface, as a single object, or row.
+pub fn status(interface: &str, status: &InterfaceStatus, format: Format) -> Result<String> {
+    let export = Status {
+        schema_version: SCHEMA_VERSION,
+        interface,
+        status,
+    };
+    match format {
+        Format::Json => Ok(serde_json::to_string_pretty(&export)? + "\n"),
+        Format::JsonLines => Ok(serde_json::to_string(&export)? + "\n"),
+        Format::Csv => Ok(csv_row(&[
+            "schema_version",
+            "interface",
+            "up",
+            "oper_state",
+            "rfkill_index",
+            "soft_blocked",
+            "hard_blocked",
+            "connected_ssid",
+        ]) + &csv_row(&[
+            &SCHEMA_VERSION.to_string(),
+            interface,
+            &status.up.to_string(),
+            &status.oper_state,
+            &status
+                .rfkill
+                .index
+                .map(|index| index.to_string())
+                .unwrap_or_default(),
+            &status.rfkill.soft_blocked.to_string(),
+            &status.rfkill.hard_blocked.to_string(),
+            status.connected_ssid.as_deref().unwrap_or_default(),
+        ])),
+    }
+}
+
+// use the same name as in JSON
+fn security_type_name(wn: &WirelessNetwork) -> Result<String> {
+    match serde_json::to_value(&wn.security_type)? {
+        serde_json::Value::String(name) => Ok(name),
+        _ => Err(anyhow!("security type isn't serialized as a string")),
+    }
+}
+
+/// Quotes fields containing separators, quotes, or line breaks as described in RFC 4180.
+fn csv_row(fields: &[&str]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect();
+    fields.join(",") + "\r\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_nw, rfkill::RfkillState};
+
+    const SCAN: &str = "Cell 09 - Address: D4:1A:D1:51:67:F2
+                    Channel:6
+                    Frequency:2.437 GHz (Channel 6)
+                    Quality=42/70  Signal level=-68 dBm
+                    Encryption key:on
+                    ESSID:\"some network\"
+                    Extra: Last beacon: 2216ms ago
+                    IE: IEEE 802.11i/WPA2 Version 1
+                        Group Cipher : CCMP
+                        Pairwise Ciphers (1) : CCMP
+                        Authentication Suites (1) : PSK";
+
+    fn valid_network() -> Vec<WirelessNetwork> {
+        parse_nw(SCAN).unwrap().1
+    }
+
+    #[test]
+    fn schema_json() {
+        assert_eq!(
+            networks("wlp64s0", &valid_network(), Format::Json).unwrap(),
+            r#"{
+  "schema_version": 1,
+  "interface": "wlp64s0",
+  "networks": [
+    {
+      "address": "D4:1A:D1:51:67:F2",
+      "quality": {
+        "value": 42,
+        "limit": 70
+      },
+      "frequency": 2.437,
+      "essid": "some network",
+      "security_type": "wpa2"
+    }
+  ]
+}
+"#
+        );
+    }
+
+    #[test]
+    fn schema_json_lines() {
+        assert_eq!(
+            networks("wlp64s0", &valid_network(), Format::JsonLines).unwrap(),
+            r#"{"schema_version":1,"interface":"wlp64s0","address":"D4:1A:D1:51:67:F2","quality":{"value":42,"limit":70},"frequency":2.437,"essid":"some network","security_type":"wpa2"}
+"#
+        );
+    }
+
+    #[test]
+    fn schema_csv() {
+        assert_eq!(
+            networks("wlp64s0", &valid_network(), Format::Csv).unwrap(),
+            "schema_version,interface,address,essid,frequency,quality_value,quality_limit,security_type\r\n\
+             1,wlp64s0,D4:1A:D1:51:67:F2,some network,2.437,42,70,wpa2\r\n"
+        );
+    }
+
+    #[test]
+    fn schema_status() {
+        let status = InterfaceStatus {
+            up: true,
+            oper_state: String::from("UP"),
+            rfkill: RfkillState {
+                index: Some(0),
+                soft_blocked: false,
+                hard_blocked: false,
+            },
+            connected_ssid: Some(String::from("some network")),
+        };
+
+        assert_eq!(
+            super::status("wlp64s0", &status, Format::JsonLines).unwrap(),
+            r#"{"schema_version":1,"interface":"wlp64s0","up":true,"oper_state":"UP","rfkill":{"index":0,"soft_blocked":false,"hard_blocked":false},"connected_ssid":"some network"}
+"#
+        );
+        assert_eq!(
+            super::status("wlp64s0", &status, Format::Csv).unwrap(),
+            "schema_version,interface,up,oper_state,rfkill_index,soft_blocked,hard_blocked,connected_ssid\r\n\
+             1,wlp64s0,true,UP,0,false,false,some network\r\n"
+        );
+    }
+
+    #[test]
+    fn csv_quoting() {
+        assert_eq!(
+            csv_row(&["plain", "with, comma", "with \"quotes\""]),
+            "plain,\"with, comma\",\"with \"\"quotes\"\"\"\r\n"
+        );
+    }
+}
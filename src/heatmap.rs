@@ -0,0 +1,267 @@
+use crate::survey::Sample;
+use anyhow::Result;
+use image::{imageops::FilterType, Rgba, RgbaImage};
+use std::{collections::BTreeSet, fmt, path::Path};
+
+/// Cells across the width of the heatmap, it's scaled up to the floor plan.
+pub const CELLS: u32 = 160;
+/// Of the heatmap over the floor plan.
+const OPACITY: f32 = 0.55;
+/// Closer samples weigh more, with the distance to this power.
+const POWER: f32 = 2.0;
+const WEAK: [f32; 3] = [220.0, 50.0, 47.0];
+const FAIR: [f32; 3] = [230.0, 200.0, 0.0];
+const STRONG: [f32; 3] = [40.0, 170.0, 70.0];
+
+/// What the heatmap shows the signal of.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Target {
+    /// The strongest access point of the SSID at each position.
+    Ssid(String),
+    Bssid {
+        ssid: String,
+        bssid: String,
+    },
+}
+
+impl Target {
+    /// The SSIDs and access points in the samples, SSIDs first.
+    pub fn all(samples: &[Sample]) -> Vec<Target> {
+        let networks = samples.iter().flat_map(|sample| &sample.networks);
+        let ssids: BTreeSet<Target> = networks
+            .clone()
+            .map(|wn| Target::Ssid(wn.essid.clone()))
+            .collect();
+        let bssids: BTreeSet<Target> = networks
+            .map(|wn| Target::Bssid {
+                ssid: wn.essid.clone(),
+                bssid: wn.address.clone(),
+            })
+            .collect();
+        ssids.into_iter().chain(bssids).collect()
+    }
+
+    /// Signal quality in percent in the sample, 0 if it wasn't seen.
+    pub fn quality(&self, sample: &Sample) -> u64 {
+        sample
+            .networks
+            .iter()
+            .filter(|wn| match self {
+                Target::Ssid(ssid) => wn.essid == *ssid,
+                Target::Bssid { bssid, .. } => wn.address == *bssid,
+            })
+            .map(|wn| wn.quality.percent())
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Ssid(ssid) => write!(f, "{}", ssid),
+            Target::Bssid { ssid, bssid } => write!(f, "{} ({})", ssid, bssid),
+        }
+    }
+}
+
+/// The signal between the samples, weighted by inverse distance. Positions are in cells.
+fn interpolate(points: &[([f32; 2], f32)], x: f32, y: f32) -> f32 {
+    let mut weighted = 0.0;
+    let mut weights = 0.0;
+    for ([px, py], value) in points {
+        let distance = ((px - x).powi(2) + (py - y).powi(2)).sqrt();
+        if distance < 1e-3 {
+            return *value;
+        }
+        let weight = 1.0 / distance.powf(POWER);
+        weighted += weight * value;
+        weights += weight;
+    }
+    if weights == 0.0 {
+        0.0
+    } else {
+        weighted / weights
+    }
+}
+
+/// From red for no signal, over yellow to green for a full signal.
+pub fn color(percent: f32) -> [u8; 3] {
+    let t = (percent / 100.0).clamp(0.0, 1.0);
+    let (from, to, t) = if t < 0.5 {
+        (WEAK, FAIR, t * 2.0)
+    } else {
+        (FAIR, STRONG, (t - 0.5) * 2.0)
+    };
+    [0, 1, 2].map(|i| (from[i] + (to[i] - from[i]) * t).round() as u8)
+}
+
+/// The heatmap of the target for a floor plan of the aspect ratio, `CELLS` wide and transparent
+/// where there are no samples.
+pub fn render(samples: &[Sample], target: &Target, aspect_ratio: f32) -> RgbaImage {
+    let width = CELLS;
+    let height = ((CELLS as f32 / aspect_ratio).round() as u32).max(1);
+    let points: Vec<([f32; 2], f32)> = samples
+        .iter()
+        .filter_map(|sample| {
+            let [x, y] = sample.position?;
+            Some((
+                [x * width as f32, y * height as f32],
+                target.quality(sample) as f32,
+            ))
+        })
+        .collect();
+    if points.is_empty() {
+        return RgbaImage::new(width, height);
+    }
+    RgbaImage::from_fn(width, height, |x, y| {
+        let [r, g, b] = color(interpolate(&points, x as f32 + 0.5, y as f32 + 0.5));
+        Rgba([r, g, b, (OPACITY * 255.0) as u8])
+    })
+}
+
+/// Saves the floor plan with the heatmap and the positions of the samples on top as PNG.
+pub fn export(
+    floor_plan: &RgbaImage,
+    samples: &[Sample],
+    target: &Target,
+    path: &Path,
+) -> Result<()> {
+    let (width, height) = floor_plan.dimensions();
+    let heat = image::imageops::resize(
+        &render(samples, target, width as f32 / height as f32),
+        width,
+        height,
+        FilterType::Triangle,
+    );
+    let mut out = floor_plan.clone();
+    for (pixel, heat) in out.pixels_mut().zip(heat.pixels()) {
+        let alpha = heat[3] as f32 / 255.0;
+        for i in 0..3 {
+            pixel[i] = (pixel[i] as f32 * (1.0 - alpha) + heat[i] as f32 * alpha).round() as u8;
+        }
+    }
+
+    let radius = (width.max(height) as f32 / 150.0).max(3.0);
+    for [x, y] in samples.iter().filter_map(|sample| sample.position) {
+        let (cx, cy) = (x * width as f32, y * height as f32);
+        let x_range = (cx - radius).max(0.0) as u32..((cx + radius) as u32).min(width);
+        for px in x_range {
+            let y_range = (cy - radius).max(0.0) as u32..((cy + radius) as u32).min(height);
+            for py in y_range {
+                if (px as f32 - cx).powi(2) + (py as f32 - cy).powi(2) <= radius.powi(2) {
+                    out.put_pixel(px, py, Rgba([0, 0, 0, 255]));
+                }
+            }
+        }
+    }
+    out.save_with_format(path, image::ImageFormat::Png)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::{AppState, Repaint},
+        parse_access_points,
+    };
+
+    /// Measures like clicking on the floor plan, the access points are scanned by `iwlist`.
+    fn sample(position: [f32; 2], access_points: &[(&str, &str, u64)]) -> Sample {
+        let mut scan = String::from("wlan0     Scan completed :\n");
+        for (i, (essid, address, quality)) in access_points.iter().enumerate() {
+            scan.push_str(&format!(
+                "          Cell {:02} - Address: {}\n\
+                 \x20                   Frequency:2.412 GHz (Channel 1)\n\
+                 \x20                   Quality={}/70  Signal level=-60 dBm\n\
+                 \x20                   Encryption key:on\n\
+                 \x20                   ESSID:\"{}\"\n\
+                 \x20                   IE: IEEE 802.11i/WPA2 Version 1\n",
+                i + 1,
+                address,
+                quality,
+                essid
+            ));
+        }
+        let mut app_state = AppState::new(vec![], String::from("wlan0"));
+        let (background_sender, _background_receiver) = std::sync::mpsc::channel();
+        app_state.measure_at(position, &background_sender, &Repaint::new(|| ()));
+        app_state.update_networks(
+            String::from("wlan0"),
+            Some(parse_access_points(&scan).unwrap()),
+        );
+        app_state.measurements.remove(0)
+    }
+
+    #[test]
+    fn targets_and_quality() {
+        let samples = [
+            sample(
+                [0.1, 0.1],
+                &[
+                    ("home", "AA:00:00:00:00:02", 21),
+                    ("home", "AA:00:00:00:00:01", 56),
+                ],
+            ),
+            sample([0.9, 0.9], &[("guest", "AA:00:00:00:00:03", 28)]),
+        ];
+        let home = Target::Ssid(String::from("home"));
+        let weaker_home_ap = Target::Bssid {
+            ssid: String::from("home"),
+            bssid: String::from("AA:00:00:00:00:02"),
+        };
+        let guest_ap = Target::Bssid {
+            ssid: String::from("guest"),
+            bssid: String::from("AA:00:00:00:00:03"),
+        };
+
+        let targets = Target::all(&samples);
+        assert_eq!(targets.len(), 5);
+        assert_eq!(targets[0], Target::Ssid(String::from("guest")));
+        assert_eq!(targets[2], guest_ap);
+        assert_eq!(guest_ap.to_string(), "guest (AA:00:00:00:00:03)");
+        assert_eq!(home.quality(&samples[0]), 80);
+        assert_eq!(home.quality(&samples[1]), 0);
+        // not only the strongest access point of an SSID is measured
+        assert!(targets.contains(&weaker_home_ap));
+        assert_eq!(weaker_home_ap.quality(&samples[0]), 30);
+    }
+
+    #[test]
+    fn interpolate_between_samples() {
+        let points = [([0.0, 0.0], 100.0), ([10.0, 0.0], 0.0)];
+        assert_eq!(interpolate(&points, 0.0, 0.0), 100.0);
+        assert_eq!(interpolate(&points, 5.0, 0.0), 50.0);
+        assert!(interpolate(&points, 2.0, 0.0) > 90.0);
+        assert_eq!(interpolate(&[], 1.0, 1.0), 0.0);
+
+        assert_eq!(color(0.0), [220, 50, 47]);
+        assert_eq!(color(50.0), [230, 200, 0]);
+        assert_eq!(color(150.0), [40, 170, 70]);
+    }
+
+    #[test]
+    fn export_png() {
+        let target = Target::Ssid(String::from("home"));
+        let samples = [
+            sample([0.1, 0.5], &[("home", "AA:00:00:00:00:01", 70)]),
+            sample([0.9, 0.5], &[("home", "AA:00:00:00:00:01", 0)]),
+        ];
+        let floor_plan = RgbaImage::from_pixel(200, 100, Rgba([255, 255, 255, 255]));
+        let path = std::env::temp_dir().join(format!("swelfi-heatmap-{}.png", std::process::id()));
+
+        export(&floor_plan, &samples, &target, &path).unwrap();
+        let exported = image::open(&path).unwrap().into_rgba8();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(exported.dimensions(), (200, 100));
+        // the positions are marked
+        assert_eq!(exported.get_pixel(20, 50), &Rgba([0, 0, 0, 255]));
+        // green near the strong sample, red near the weak one
+        let strong = exported.get_pixel(30, 20);
+        assert!(strong[1] > strong[0], "{:?}", strong);
+        let weak = exported.get_pixel(170, 20);
+        assert!(weak[0] > weak[1], "{:?}", weak);
+    }
+}
@@ -0,0 +1,1142 @@
+use crate::{error::SwelfiError, rfkill};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::{
+    fmt,
+    io::{BufRead, BufReader, Read, Write},
+    net::IpAddr,
+    os::unix::{
+        fs::PermissionsExt,
+        io::AsRawFd,
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+pub const SOCKET_PATH: &str = "/run/swelfi/helper.sock";
+
+const ACTION_SCAN: &str = "org.swelfi.helper.scan";
+const ACTION_SWITCH_RADIO: &str = "org.swelfi.helper.switch-radio";
+const ACTION_CONNECT: &str = "org.swelfi.helper.connect";
+
+const OK: &str = "ok";
+const ERROR: &str = "error ";
+const DENIED: &str = "denied ";
+// IFNAMSIZ in linux/if.h includes the trailing nul byte
+const MAX_INTERFACE_LEN: usize = 15;
+const MAX_SSID_LEN: usize = 32;
+const MAX_DNS_SERVERS: usize = 8;
+/// Longer request lines are turned away, the longest valid one is a connect request with a raw
+/// key and options, or 8 IPv6 DNS servers.
+pub const MAX_LINE: u64 = 1024;
+/// How long the helper waits for a request, and for the client to take the response.
+pub const SERVER_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long the client waits for the response, polkit may ask for a password first.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(120);
+/// Connections handled at the same time, more are turned away.
+pub const MAX_CONNECTIONS: usize = 16;
+/// The commands renewing the lease of an interface, followed by its name.
+const DHCP_CLIENTS: [(&str, &[&str]); 2] = [("networkctl", &["renew"]), ("dhcpcd", &["--rebind"])];
+
+/// The operations the privileged helper is willing to do on behalf of the unprivileged GUI.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Request {
+    Scan(String),
+    SetLink(String, bool),
+    SetRfkill(u32, bool),
+    SetAllRfkill(bool),
+    /// Interface, SSID and passphrase, SSIDs are arbitrary bytes, so they are sent hex encoded.
+    Connect(String, Vec<u8>, Option<String>, ConnectOptions),
+    Disconnect(String),
+    /// The networks saved in wpa_supplicant for the interface.
+    SavedNetworks(String),
+    /// DNS servers of the interface, no servers revert to the ones from DHCP.
+    SetDns(String, Vec<IpAddr>),
+    /// Asks the DHCP client of the interface to renew its lease.
+    RenewLease(String),
+}
+
+/// How wpa_supplicant picks the MAC address for a network, see `mac_addr` in
+/// wpa_supplicant.conf(5).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MacPolicy {
+    Permanent,
+    Random,
+    /// A random address, which keeps the vendor part of the permanent one.
+    RandomKeepOui,
+}
+
+impl MacPolicy {
+    fn mac_addr(&self) -> &'static str {
+        match self {
+            MacPolicy::Permanent => "0",
+            MacPolicy::Random => "1",
+            MacPolicy::RandomKeepOui => "2",
+        }
+    }
+}
+
+impl FromStr for MacPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "permanent" => Ok(MacPolicy::Permanent),
+            "random" => Ok(MacPolicy::Random),
+            "random-keep-oui" => Ok(MacPolicy::RandomKeepOui),
+            _ => Err(anyhow!("invalid mac policy: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for MacPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacPolicy::Permanent => write!(f, "permanent"),
+            MacPolicy::Random => write!(f, "random"),
+            MacPolicy::RandomKeepOui => write!(f, "random-keep-oui"),
+        }
+    }
+}
+
+/// Settings of the network added by a connect request, besides the SSID and passphrase, they
+/// are sent as `key=value` after the passphrase.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ConnectOptions {
+    /// Only associate with this access point.
+    pub bssid: Option<String>,
+    pub mac: Option<MacPolicy>,
+}
+
+impl ConnectOptions {
+    fn parse(options: &[&str]) -> Result<Self> {
+        let mut connect_options = Self::default();
+        for option in options {
+            match option.split_once('=') {
+                Some(("bssid", bssid)) if connect_options.bssid.is_none() => {
+                    connect_options.bssid = Some(bssid.to_owned())
+                }
+                Some(("mac", mac)) if connect_options.mac.is_none() => {
+                    connect_options.mac = Some(mac.parse()?)
+                }
+                _ => return Err(anyhow!("invalid connect option: {}", option)),
+            }
+        }
+        Ok(connect_options)
+    }
+
+    fn to_words(&self) -> String {
+        let bssid = self.bssid.iter().map(|bssid| format!(" bssid={}", bssid));
+        let mac = self.mac.iter().map(|mac| format!(" mac={}", mac));
+        bssid.chain(mac).collect()
+    }
+}
+
+impl Request {
+    /// Parses and validates a single request line, as sent by the client.
+    pub fn parse(line: &str) -> Result<Self> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let request = match parts.as_slice() {
+            ["scan", interface] => Request::Scan(interface.to_string()),
+            ["link", interface, state] => Request::SetLink(interface.to_string(), on_off(state)?),
+            ["connect", interface, ssid, rest @ ..] => {
+                // options contain a '=', which isn't a hex digit
+                let (passphrase, options) = match rest {
+                    [passphrase, options @ ..] if !passphrase.contains('=') => {
+                        (Some(*passphrase), options)
+                    }
+                    options => (None, options),
+                };
+                Request::Connect(
+                    interface.to_string(),
+                    from_hex(ssid)?,
+                    passphrase
+                        .map(|passphrase| {
+                            String::from_utf8(from_hex(passphrase)?)
+                                .map_err(|_| anyhow!("passphrase isn't valid utf-8"))
+                        })
+                        .transpose()?,
+                    ConnectOptions::parse(options)?,
+                )
+            }
+            ["disconnect", interface] => Request::Disconnect(interface.to_string()),
+            ["saved", interface] => Request::SavedNetworks(interface.to_string()),
+            ["renew", interface] => Request::RenewLease(interface.to_string()),
+            ["dns", interface, servers @ ..] => Request::SetDns(
+                interface.to_string(),
+                servers
+                    .iter()
+                    .map(|server| {
+                        server
+                            .parse::<IpAddr>()
+                            .map_err(|_| anyhow!("invalid dns server: {}", server))
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            ["rfkill", "all", state] => Request::SetAllRfkill(block_unblock(state)?),
+            ["rfkill", index, state] => Request::SetRfkill(
+                index
+                    .parse::<u32>()
+                    .map_err(|_| anyhow!("invalid rfkill index: {}", index))?,
+                block_unblock(state)?,
+            ),
+            _ => return Err(anyhow!("invalid request: {}", line.trim())),
+        };
+        request.validate()?;
+        Ok(request)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Request::Scan(interface)
+            | Request::SetLink(interface, _)
+            | Request::Disconnect(interface)
+            | Request::SavedNetworks(interface)
+            | Request::RenewLease(interface) => validate_interface(interface),
+            Request::Connect(interface, ssid, passphrase, options) => {
+                validate_interface(interface)?;
+                if ssid.is_empty() || ssid.len() > MAX_SSID_LEN {
+                    return Err(anyhow!("invalid ssid length: {}", ssid.len()));
+                }
+                if let Some(ref bssid) = options.bssid {
+                    validate_bssid(bssid)?;
+                }
+                match passphrase {
+                    Some(passphrase) => validate_passphrase(passphrase),
+                    None => Ok(()),
+                }
+            }
+            Request::SetDns(interface, servers) => {
+                validate_interface(interface)?;
+                if servers.len() > MAX_DNS_SERVERS {
+                    return Err(anyhow!("too many dns servers: {}", servers.len()));
+                }
+                Ok(())
+            }
+            Request::SetRfkill(_, _) | Request::SetAllRfkill(_) => Ok(()),
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        match self {
+            Request::Scan(interface) => format!("scan {}\n", interface),
+            Request::SetLink(interface, on) => {
+                format!("link {} {}\n", interface, if *on { "up" } else { "down" })
+            }
+            Request::SetRfkill(index, blocked) => {
+                format!("rfkill {} {}\n", index, block_str(*blocked))
+            }
+            Request::SetAllRfkill(blocked) => format!("rfkill all {}\n", block_str(*blocked)),
+            Request::Connect(interface, ssid, None, options) => format!(
+                "connect {} {}{}\n",
+                interface,
+                to_hex(ssid),
+                options.to_words()
+            ),
+            Request::Connect(interface, ssid, Some(passphrase), options) => format!(
+                "connect {} {} {}{}\n",
+                interface,
+                to_hex(ssid),
+                to_hex(passphrase.as_bytes()),
+                options.to_words()
+            ),
+            Request::Disconnect(interface) => format!("disconnect {}\n", interface),
+            Request::SavedNetworks(interface) => format!("saved {}\n", interface),
+            Request::RenewLease(interface) => format!("renew {}\n", interface),
+            Request::SetDns(interface, servers) => format!(
+                "dns {}{}\n",
+                interface,
+                servers
+                    .iter()
+                    .map(|server| format!(" {}", server))
+                    .collect::<String>()
+            ),
+        }
+    }
+
+    /// The polkit action, the client needs to be authorized for, to make this request.
+    pub fn action_id(&self) -> &'static str {
+        match self {
+            Request::Scan(_) | Request::SavedNetworks(_) => ACTION_SCAN,
+            Request::SetLink(_, _) | Request::SetRfkill(_, _) | Request::SetAllRfkill(_) => {
+                ACTION_SWITCH_RADIO
+            }
+            Request::Connect(_, _, _, _)
+            | Request::Disconnect(_)
+            | Request::SetDns(_, _)
+            | Request::RenewLease(_) => ACTION_CONNECT,
+        }
+    }
+}
+
+fn validate_interface(interface: &str) -> Result<()> {
+    if interface.is_empty()
+        || interface.len() > MAX_INTERFACE_LEN
+        || interface == "."
+        || interface == ".."
+        // would be taken as an option by 'iwlist' and 'ip'
+        || interface.starts_with('-')
+        || !interface
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    {
+        return Err(anyhow!("invalid interface name: {}", interface));
+    }
+    Ok(())
+}
+
+/// A MAC address, like D4:1A:D1:51:67:F2.
+pub fn validate_bssid(bssid: &str) -> Result<()> {
+    let octets: Vec<&str> = bssid.split(':').collect();
+    if octets.len() != 6
+        || !octets
+            .iter()
+            .all(|octet| octet.len() == 2 && octet.chars().all(|c| c.is_ascii_hexdigit()))
+    {
+        return Err(anyhow!("invalid bssid: {}", bssid));
+    }
+    Ok(())
+}
+
+/// WPA passphrases are 8 to 63 printable ascii characters, or 64 hex digits for a raw key.
+pub fn validate_passphrase(passphrase: &str) -> Result<()> {
+    let is_raw_key = passphrase.len() == 64 && passphrase.chars().all(|c| c.is_ascii_hexdigit());
+    let is_passphrase = (8..=63).contains(&passphrase.len())
+        && passphrase.chars().all(|c| c.is_ascii_graphic() || c == ' ');
+    if !is_raw_key && !is_passphrase {
+        return Err(anyhow!("invalid passphrase"));
+    }
+    Ok(())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn from_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err(anyhow!("invalid hex string: {}", hex));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|_| anyhow!("invalid hex string: {}", hex))
+        })
+        .collect()
+}
+
+fn on_off(state: &str) -> Result<bool> {
+    match state {
+        "up" => Ok(true),
+        "down" => Ok(false),
+        _ => Err(anyhow!("invalid link state: {}", state)),
+    }
+}
+
+fn block_unblock(state: &str) -> Result<bool> {
+    match state {
+        "block" => Ok(true),
+        "unblock" => Ok(false),
+        _ => Err(anyhow!("invalid rfkill state: {}", state)),
+    }
+}
+
+fn block_str(blocked: bool) -> &'static str {
+    if blocked {
+        "block"
+    } else {
+        "unblock"
+    }
+}
+
+/// The privileged side of the requests.
+pub trait Operations {
+    /// Returns the raw output of the scan, parsing is left to the unprivileged client.
+    fn scan(&self, interface: &str) -> Result<String>;
+    fn set_link(&self, interface: &str, on: bool) -> Result<()>;
+    fn set_rfkill(&self, index: Option<u32>, blocked: bool) -> Result<()>;
+    fn connect(
+        &self,
+        interface: &str,
+        ssid: &[u8],
+        passphrase: Option<&str>,
+        options: &ConnectOptions,
+    ) -> Result<()>;
+    fn disconnect(&self, interface: &str) -> Result<()>;
+    /// Returns the raw output of 'wpa_cli list_networks'.
+    fn saved_networks(&self, interface: &str) -> Result<String>;
+    fn set_dns(&self, interface: &str, servers: &[IpAddr]) -> Result<()>;
+    fn renew_lease(&self, interface: &str) -> Result<()>;
+}
+
+pub struct SystemOperations;
+
+impl Operations for SystemOperations {
+    fn scan(&self, interface: &str) -> Result<String> {
+        let output = Command::new("iwlist").args([interface, "s"]).output()?;
+        if !output.status.success() {
+            return Err(anyhow!("getting wireless interfaces using 'iwlist' failed"));
+        }
+        String::from_utf8(output.stdout)
+            .map_err(|_| anyhow!("output of 'iwlist' wasn't valid utf-8"))
+    }
+
+    fn set_link(&self, interface: &str, on: bool) -> Result<()> {
+        let on_off = if on { "up" } else { "down" };
+        let output = Command::new("ip")
+            .args(["link", "set", interface, on_off])
+            .output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "'ip link set {} {}' failed: {}",
+                interface,
+                on_off,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    fn set_rfkill(&self, index: Option<u32>, blocked: bool) -> Result<()> {
+        match index {
+            Some(index) => rfkill::set_blocked(index, blocked),
+            None => rfkill::set_all_blocked(blocked),
+        }
+    }
+
+    fn connect(
+        &self,
+        interface: &str,
+        ssid: &[u8],
+        passphrase: Option<&str>,
+        options: &ConnectOptions,
+    ) -> Result<()> {
+        let id = wpa_cli(interface, &["add_network"])?;
+        // unquoted ssids are read as hex by wpa_supplicant
+        wpa_cli(interface, &["set_network", &id, "ssid", &to_hex(ssid)])?;
+        match passphrase {
+            Some(passphrase) if passphrase.len() == 64 => {
+                wpa_cli(interface, &["set_network", &id, "psk", passphrase])?
+            }
+            Some(passphrase) => wpa_cli(
+                interface,
+                &["set_network", &id, "psk", &format!("\"{}\"", passphrase)],
+            )?,
+            None => wpa_cli(interface, &["set_network", &id, "key_mgmt", "NONE"])?,
+        };
+        if let Some(ref bssid) = options.bssid {
+            wpa_cli(interface, &["set_network", &id, "bssid", bssid])?;
+        }
+        if let Some(mac) = options.mac {
+            wpa_cli(interface, &["set_network", &id, "mac_addr", mac.mac_addr()])?;
+        }
+        wpa_cli(interface, &["select_network", &id])?;
+        Ok(())
+    }
+
+    fn disconnect(&self, interface: &str) -> Result<()> {
+        wpa_cli(interface, &["disconnect"]).map(|_| ())
+    }
+
+    fn saved_networks(&self, interface: &str) -> Result<String> {
+        wpa_cli(interface, &["list_networks"])
+    }
+
+    fn set_dns(&self, interface: &str, servers: &[IpAddr]) -> Result<()> {
+        let servers: Vec<String> = servers.iter().map(|server| server.to_string()).collect();
+        let mut command = Command::new("resolvectl");
+        if servers.is_empty() {
+            command.args(["revert", interface]);
+        } else {
+            command.args(["dns", interface]).args(&servers);
+        }
+        let output = command.output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "'resolvectl' failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Tries the DHCP clients in turn, until one of them manages the interface.
+    fn renew_lease(&self, interface: &str) -> Result<()> {
+        let mut errors = vec![];
+        for (program, args) in DHCP_CLIENTS {
+            match Command::new(program).args(args).arg(interface).output() {
+                Ok(output) if output.status.success() => return Ok(()),
+                Ok(output) => errors.push(format!(
+                    "'{}' failed: {}",
+                    program,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )),
+                Err(e) => errors.push(format!("running '{}' failed: {}", program, e)),
+            }
+        }
+        Err(anyhow!(
+            "renewing the lease of {} isn't supported: {}",
+            interface,
+            errors.join(", ")
+        ))
+    }
+}
+
+/// Runs a command against the wpa_supplicant instance of the interface, which answers "FAIL"
+/// on errors, but still exits successfully.
+fn wpa_cli(interface: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new("wpa_cli")
+        .args(["-i", interface])
+        .args(args)
+        .output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    if !output.status.success() || stdout.starts_with("FAIL") {
+        return Err(anyhow!("'wpa_cli {}' failed: {}", args[0], stdout));
+    }
+    Ok(stdout)
+}
+
+/// Reads a line of at most `MAX_LINE` bytes, it's empty at the end of the stream and lacks the
+/// trailing '\n' if it's longer.
+pub fn read_line<R: BufRead>(reader: R) -> Result<String> {
+    let mut line = String::new();
+    reader.take(MAX_LINE).read_line(&mut line)?;
+    Ok(line)
+}
+
+/// Reads a single request from the stream, checks it with `authorize` and answers it with
+/// either "ok", followed by the payload, or "error", followed by the reason.
+pub fn handle_connection<S, O, A>(mut stream: S, operations: &O, authorize: A) -> Result<()>
+where
+    S: Read + Write,
+    O: Operations,
+    A: Fn(&Request) -> Result<()>,
+{
+    let line = read_line(BufReader::new(&mut stream))?;
+    let request = if line.ends_with('\n') {
+        Request::parse(&line)
+    } else {
+        Err(anyhow!("request is longer than {} bytes", MAX_LINE))
+    };
+    let request = request.and_then(|request| {
+        if let Err(e) = authorize(&request) {
+            return Err(SwelfiError::PermissionDenied(e.to_string()).into());
+        }
+        Ok(request)
+    });
+    let response = request.and_then(|request| {
+        // don't log passphrases
+        log::info!("handling {} request", request.action_id());
+        match request {
+            Request::Scan(interface) => operations.scan(&interface),
+            Request::SetLink(interface, on) => {
+                operations.set_link(&interface, on).map(|_| String::new())
+            }
+            Request::SetRfkill(index, blocked) => operations
+                .set_rfkill(Some(index), blocked)
+                .map(|_| String::new()),
+            Request::SetAllRfkill(blocked) => {
+                operations.set_rfkill(None, blocked).map(|_| String::new())
+            }
+            Request::Connect(interface, ssid, passphrase, options) => operations
+                .connect(&interface, &ssid, passphrase.as_deref(), &options)
+                .map(|_| String::new()),
+            Request::Disconnect(interface) => {
+                operations.disconnect(&interface).map(|_| String::new())
+            }
+            Request::SavedNetworks(interface) => operations.saved_networks(&interface),
+            Request::SetDns(interface, servers) => operations
+                .set_dns(&interface, &servers)
+                .map(|_| String::new()),
+            Request::RenewLease(interface) => {
+                operations.renew_lease(&interface).map(|_| String::new())
+            }
+        }
+    });
+
+    match response {
+        Ok(payload) => write!(stream, "{}\n{}", OK, payload)?,
+        Err(e) => {
+            log::error!("Error while handling request: {}", e);
+            match e.downcast_ref::<SwelfiError>() {
+                Some(SwelfiError::PermissionDenied(reason)) => {
+                    writeln!(stream, "{}{}", DENIED, reason)?
+                }
+                _ => writeln!(stream, "{}{}", ERROR, e)?,
+            }
+        }
+    }
+    stream.flush()?;
+    Ok(())
+}
+
+/// Sends a request and returns the payload of the response.
+pub fn send_request<S: Read + Write>(mut stream: S, request: &Request) -> Result<String> {
+    request.validate()?;
+    stream.write_all(request.to_line().as_bytes())?;
+    stream.flush()?;
+
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => {
+                anyhow!("swelfi helper didn't answer in time")
+            }
+            _ => e.into(),
+        })?;
+    match response.split_once('\n') {
+        Some((OK, payload)) => Ok(payload.to_owned()),
+        Some((status, _)) if status.starts_with(ERROR) => Err(anyhow!(
+            "swelfi helper: {}",
+            status.trim_start_matches(ERROR)
+        )),
+        Some((status, _)) if status.starts_with(DENIED) => {
+            let reason = status.trim_start_matches(DENIED).to_owned();
+            Err(SwelfiError::PermissionDenied(reason).into())
+        }
+        _ => Err(anyhow!("invalid response from swelfi helper")),
+    }
+}
+
+/// Reads the SSIDs from the tab separated output of 'wpa_cli list_networks', which starts with
+/// a header line.
+fn parse_saved_networks(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split('\t').nth(1))
+        .filter(|ssid| !ssid.is_empty())
+        .map(|ssid| ssid.to_owned())
+        .collect()
+}
+
+/// Client for the helper, which runs as a system service listening on a unix socket.
+pub struct Client {
+    socket_path: PathBuf,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self {
+            socket_path: PathBuf::from(SOCKET_PATH),
+        }
+    }
+}
+
+impl Client {
+    pub fn scan(&self, interface: &str) -> Result<String> {
+        self.request(&Request::Scan(interface.to_owned()))
+    }
+
+    pub fn set_link(&self, interface: &str, on: bool) -> Result<()> {
+        self.request(&Request::SetLink(interface.to_owned(), on))
+            .map(|_| ())
+    }
+
+    pub fn set_rfkill(&self, index: u32, blocked: bool) -> Result<()> {
+        self.request(&Request::SetRfkill(index, blocked))
+            .map(|_| ())
+    }
+
+    pub fn set_all_rfkill(&self, blocked: bool) -> Result<()> {
+        self.request(&Request::SetAllRfkill(blocked)).map(|_| ())
+    }
+
+    pub fn connect(
+        &self,
+        interface: &str,
+        ssid: &str,
+        passphrase: Option<&str>,
+        options: &ConnectOptions,
+    ) -> Result<()> {
+        self.request(&Request::Connect(
+            interface.to_owned(),
+            ssid.as_bytes().to_vec(),
+            passphrase.map(|p| p.to_owned()),
+            options.clone(),
+        ))
+        .map(|_| ())
+    }
+
+    pub fn disconnect(&self, interface: &str) -> Result<()> {
+        self.request(&Request::Disconnect(interface.to_owned()))
+            .map(|_| ())
+    }
+
+    pub fn saved_networks(&self, interface: &str) -> Result<Vec<String>> {
+        self.request(&Request::SavedNetworks(interface.to_owned()))
+            .map(|output| parse_saved_networks(&output))
+    }
+
+    pub fn set_dns(&self, interface: &str, servers: &[IpAddr]) -> Result<()> {
+        self.request(&Request::SetDns(interface.to_owned(), servers.to_vec()))
+            .map(|_| ())
+    }
+
+    pub fn renew_lease(&self, interface: &str) -> Result<()> {
+        self.request(&Request::RenewLease(interface.to_owned()))
+            .map(|_| ())
+    }
+
+    fn request(&self, request: &Request) -> Result<String> {
+        let stream = UnixStream::connect(&self.socket_path).map_err(|e| {
+            let reason = format!(
+                "connecting to swelfi helper at {} failed: {}",
+                self.socket_path.display(),
+                e
+            );
+            if e.kind() == std::io::ErrorKind::PermissionDenied {
+                SwelfiError::PermissionDenied(reason).into()
+            } else {
+                anyhow!(reason)
+            }
+        })?;
+        stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+        stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+        send_request(stream, request)
+    }
+}
+
+/// Counts a connection as handled, until it's dropped.
+pub struct Handling(Arc<AtomicUsize>);
+
+impl Handling {
+    /// Counts the connection, unless `MAX_CONNECTIONS` are handled already.
+    pub fn start(connections: &Arc<AtomicUsize>) -> Option<Self> {
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(Self(connections.clone()))
+    }
+}
+
+impl Drop for Handling {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Runs the helper, this needs to run as root, every request is authorized using polkit.
+pub fn serve(socket_path: &Path) -> Result<()> {
+    if socket_path.exists() {
+        std::fs::remove_file(socket_path)?;
+    }
+    if let Some(parent) = socket_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let listener = UnixListener::bind(socket_path)?;
+    // everyone may connect, authorization happens per request
+    std::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(0o666))?;
+    log::info!("swelfi helper listening on {}", socket_path.display());
+
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::error!("Error while accepting connection: {}", e);
+                continue;
+            }
+        };
+        // a client that doesn't send or read doesn't keep its thread forever
+        if let Err(e) = stream
+            .set_read_timeout(Some(SERVER_TIMEOUT))
+            .and_then(|_| stream.set_write_timeout(Some(SERVER_TIMEOUT)))
+        {
+            log::error!("Error while setting timeouts: {}", e);
+            continue;
+        }
+        let handling = match Handling::start(&connections) {
+            Some(handling) => handling,
+            None => {
+                log::warn!("Turning away connection, too many are handled already");
+                let _ = writeln!(stream, "{}too many connections", ERROR);
+                continue;
+            }
+        };
+        std::thread::spawn(move || {
+            let _handling = handling;
+            let peer = match peer(&stream) {
+                Ok(peer) => peer,
+                Err(e) => {
+                    log::error!("Error while getting peer credentials: {}", e);
+                    return;
+                }
+            };
+            if let Err(e) = handle_connection(stream, &SystemOperations, |request| {
+                polkit_authorize(&peer, request.action_id())
+            }) {
+                log::error!("Error while handling connection: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+struct Peer {
+    pid: i32,
+    uid: u32,
+}
+
+fn peer(stream: &UnixStream) -> Result<Peer> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: cred and len are valid for writes and len is the size of cred
+    let ret = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(Peer {
+        pid: cred.pid,
+        uid: cred.uid,
+    })
+}
+
+fn polkit_authorize(peer: &Peer, action_id: &str) -> Result<()> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", peer.pid))?;
+    let start_time = process_start_time(&stat)
+        .ok_or_else(|| anyhow!("couldn't read start time of process {}", peer.pid))?;
+
+    // pid, start time and uid identify the process without races, see pkcheck(1)
+    let status = Command::new("pkcheck")
+        .args([
+            "--action-id",
+            action_id,
+            "--process",
+            &format!("{},{},{}", peer.pid, start_time, peer.uid),
+            "--allow-user-interaction",
+        ])
+        .status()?;
+    if !status.success() {
+        return Err(anyhow!("not authorized for {}", action_id));
+    }
+    Ok(())
+}
+
+/// The start time is field 22 of /proc/<pid>/stat, counting starts after the process name,
+/// which might contain spaces.
+fn process_start_time(stat: &str) -> Option<u64> {
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(19)?.parse::<u64>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct FakeOperations {
+        calls: Mutex<Vec<Request>>,
+    }
+
+    impl Operations for FakeOperations {
+        fn scan(&self, interface: &str) -> Result<String> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(Request::Scan(interface.to_owned()));
+            Ok(String::from("Cell 01 - Address: D4:1A:D1:51:67:F2\n"))
+        }
+
+        fn set_link(&self, interface: &str, on: bool) -> Result<()> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(Request::SetLink(interface.to_owned(), on));
+            Err(anyhow!(
+                "RTNETLINK answers: Operation not possible due to RF-kill"
+            ))
+        }
+
+        fn set_rfkill(&self, index: Option<u32>, blocked: bool) -> Result<()> {
+            self.calls.lock().unwrap().push(match index {
+                Some(index) => Request::SetRfkill(index, blocked),
+                None => Request::SetAllRfkill(blocked),
+            });
+            Ok(())
+        }
+
+        fn connect(
+            &self,
+            interface: &str,
+            ssid: &[u8],
+            passphrase: Option<&str>,
+            options: &ConnectOptions,
+        ) -> Result<()> {
+            self.calls.lock().unwrap().push(Request::Connect(
+                interface.to_owned(),
+                ssid.to_vec(),
+                passphrase.map(|p| p.to_owned()),
+                options.clone(),
+            ));
+            Ok(())
+        }
+
+        fn disconnect(&self, interface: &str) -> Result<()> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(Request::Disconnect(interface.to_owned()));
+            Ok(())
+        }
+
+        fn saved_networks(&self, interface: &str) -> Result<String> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(Request::SavedNetworks(interface.to_owned()));
+            Ok(String::from(
+                "network id / ssid / bssid / flags\n0\thome\tany\t[CURRENT]\n1\tcafe\tany\t\n",
+            ))
+        }
+
+        fn set_dns(&self, interface: &str, servers: &[IpAddr]) -> Result<()> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(Request::SetDns(interface.to_owned(), servers.to_vec()));
+            Ok(())
+        }
+
+        fn renew_lease(&self, interface: &str) -> Result<()> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(Request::RenewLease(interface.to_owned()));
+            Ok(())
+        }
+    }
+
+    fn roundtrip(request: &Request, authorized: bool) -> (Result<String>, Vec<Request>) {
+        let (client, server) = UnixStream::pair().unwrap();
+        let operations = FakeOperations::default();
+        let response = std::thread::scope(|s| {
+            s.spawn(|| {
+                handle_connection(server, &operations, |_| {
+                    if authorized {
+                        Ok(())
+                    } else {
+                        Err(anyhow!("not authorized"))
+                    }
+                })
+                .unwrap()
+            });
+            send_request(client, request)
+        });
+        (response, operations.calls.into_inner().unwrap())
+    }
+
+    #[test]
+    fn parse_requests() {
+        assert_eq!(
+            Request::parse("scan wlp64s0\n").unwrap(),
+            Request::Scan(String::from("wlp64s0"))
+        );
+        assert_eq!(
+            Request::parse("link wlan0 down\n").unwrap(),
+            Request::SetLink(String::from("wlan0"), false)
+        );
+        assert_eq!(
+            Request::parse("rfkill 2 block\n").unwrap(),
+            Request::SetRfkill(2, true)
+        );
+        assert_eq!(
+            Request::parse("rfkill all unblock\n").unwrap(),
+            Request::SetAllRfkill(false)
+        );
+        assert_eq!(
+            Request::parse("connect wlan0 686f6d65 mac=random bssid=D4:1A:D1:51:67:F2\n").unwrap(),
+            Request::Connect(
+                String::from("wlan0"),
+                b"home".to_vec(),
+                None,
+                ConnectOptions {
+                    bssid: Some(String::from("D4:1A:D1:51:67:F2")),
+                    mac: Some(MacPolicy::Random),
+                }
+            )
+        );
+        assert_eq!(
+            Request::parse("dns wlan0 1.1.1.1 2606:4700:4700::1111\n").unwrap(),
+            Request::SetDns(
+                String::from("wlan0"),
+                vec![
+                    "1.1.1.1".parse().unwrap(),
+                    "2606:4700:4700::1111".parse().unwrap()
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn reject_invalid_requests() {
+        for line in [
+            "",
+            "scan",
+            "scan wlan0 extra",
+            "scan ../../etc",
+            "scan wlan0;reboot",
+            "scan -h",
+            "scan averyveryverylonginterfacename",
+            "link wlan0 sideways",
+            "rfkill -1 block",
+            "rfkill 1 maybe",
+            "iwconfig wlan0",
+            "connect wlan0 zz",
+            "connect wlan0 616",
+            // passphrase "short"
+            "connect wlan0 616263 73686f7274",
+            // 33 byte ssid
+            "connect wlan0 616161616161616161616161616161616161616161616161616161616161616161",
+            "connect wlan0 616263 bssid=D4:1A:D1:51:67",
+            "connect wlan0 616263 bssid=D4:1A:D1:51:67:F2;reboot",
+            "connect wlan0 616263 mac=sometimes",
+            "connect wlan0 616263 mac=random mac=permanent",
+            "connect wlan0 616263 channel=6",
+            "dns wlan0 one.one.one.one",
+            "renew",
+            "renew wlan0;reboot",
+            "dns wlan0 1.1.1.1 1.1.1.1 1.1.1.1 1.1.1.1 1.1.1.1 1.1.1.1 1.1.1.1 1.1.1.1 1.1.1.1",
+        ] {
+            assert!(Request::parse(line).is_err(), "accepted '{}'", line);
+        }
+    }
+
+    #[test]
+    fn request_line_roundtrip() {
+        for request in [
+            Request::Scan(String::from("wlp64s0")),
+            Request::SetLink(String::from("wlan0"), true),
+            Request::SetRfkill(0, false),
+            Request::SetAllRfkill(true),
+            Request::Connect(
+                String::from("wlan0"),
+                b"some network".to_vec(),
+                None,
+                ConnectOptions::default(),
+            ),
+            Request::Connect(
+                String::from("wlan0"),
+                vec![0xff, 0x00, 0x20],
+                Some(String::from("correct horse battery staple")),
+                ConnectOptions {
+                    bssid: Some(String::from("d4:1a:d1:51:67:f2")),
+                    mac: Some(MacPolicy::RandomKeepOui),
+                },
+            ),
+            Request::Disconnect(String::from("wlan0")),
+            Request::SavedNetworks(String::from("wlan0")),
+            Request::SetDns(String::from("wlan0"), vec![]),
+            Request::SetDns(String::from("wlan0"), vec!["9.9.9.9".parse().unwrap()]),
+            Request::RenewLease(String::from("wlan0")),
+        ] {
+            assert_eq!(Request::parse(&request.to_line()).unwrap(), request);
+        }
+    }
+
+    #[test]
+    fn scan_returns_raw_output() {
+        let request = Request::Scan(String::from("wlan0"));
+        let (response, calls) = roundtrip(&request, true);
+
+        assert_eq!(response.unwrap(), "Cell 01 - Address: D4:1A:D1:51:67:F2\n");
+        assert_eq!(calls, vec![request]);
+    }
+
+    #[test]
+    fn saved_networks() {
+        let request = Request::SavedNetworks(String::from("wlan0"));
+        let (response, calls) = roundtrip(&request, true);
+
+        assert_eq!(
+            parse_saved_networks(&response.unwrap()),
+            vec!["home", "cafe"]
+        );
+        assert_eq!(calls, vec![request]);
+        assert!(parse_saved_networks("network id / ssid / bssid / flags\n").is_empty());
+    }
+
+    #[test]
+    fn operation_errors_are_returned() {
+        let request = Request::SetLink(String::from("wlan0"), true);
+        let (response, calls) = roundtrip(&request, true);
+
+        assert_eq!(
+            response.unwrap_err().to_string(),
+            "swelfi helper: RTNETLINK answers: Operation not possible due to RF-kill"
+        );
+        assert_eq!(calls, vec![request]);
+    }
+
+    #[test]
+    fn unauthorized_requests_are_not_run() {
+        let (response, calls) = roundtrip(&Request::SetAllRfkill(true), false);
+
+        assert!(matches!(
+            response.unwrap_err().downcast_ref::<SwelfiError>(),
+            Some(SwelfiError::PermissionDenied(reason)) if reason == "not authorized"
+        ));
+        assert_eq!(calls, vec![]);
+    }
+
+    #[test]
+    fn invalid_requests_are_rejected_by_the_server() {
+        let (mut client, server) = UnixStream::pair().unwrap();
+        let operations = FakeOperations::default();
+        std::thread::scope(|s| {
+            s.spawn(|| handle_connection(server, &operations, |_| Ok(())).unwrap());
+            client.write_all(b"scan ../../etc/passwd\n").unwrap();
+            let mut response = String::new();
+            client.read_to_string(&mut response).unwrap();
+            assert_eq!(response, "error invalid interface name: ../../etc/passwd\n");
+        });
+        assert!(operations.calls.into_inner().unwrap().is_empty());
+    }
+
+    #[test]
+    fn overlong_requests_are_rejected_by_the_server() {
+        let (mut client, server) = UnixStream::pair().unwrap();
+        let operations = FakeOperations::default();
+        std::thread::scope(|s| {
+            s.spawn(|| handle_connection(server, &operations, |_| Ok(())).unwrap());
+            // anything past the limit isn't read, which would reset the connection
+            let line = format!("dns wlan0{}", " 1.1.1.1".repeat(200));
+            client.write_all(&line.as_bytes()[..1024]).unwrap();
+            let mut response = String::new();
+            client.read_to_string(&mut response).unwrap();
+            assert_eq!(response, "error request is longer than 1024 bytes\n");
+        });
+        assert!(operations.calls.into_inner().unwrap().is_empty());
+    }
+
+    #[test]
+    fn start_time_from_stat() {
+        let stat = "1234 (swelfi (gui)) S 1 1234 1234 0 -1 4194560 3417 0 0 0 12 4 0 0 20 0 9 0 8301533 2207932416 19500 18446744073709551615";
+
+        assert_eq!(process_start_time(stat), Some(8301533));
+    }
+}
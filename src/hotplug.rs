@@ -0,0 +1,90 @@
+use crate::{
+    app::{Event, Repaint},
+    get_interface_status, iw, link_name, WirelessInterface,
+};
+use anyhow::{anyhow, Result};
+use std::{
+    io::{BufRead, BufReader},
+    process::{Command, Stdio},
+    sync::mpsc::Sender,
+};
+
+/// Watches rtnetlink link events using 'ip monitor link' and sends the new list of wireless
+/// interfaces, whenever an adapter is plugged in, or removed, as well as the new status of an
+/// interface, whenever it's changed from outside of swelfi.
+pub fn watch_interfaces(
+    event_sender: Sender<Event>,
+    repaint: Repaint,
+    wlan_interfaces: &[WirelessInterface],
+) {
+    let known = interface_names(wlan_interfaces);
+    std::thread::spawn(move || {
+        if let Err(e) = monitor_links(&event_sender, &repaint, known) {
+            log::error!("Error while watching for wlan interfaces: {}", e);
+        }
+    });
+}
+
+fn monitor_links(
+    event_sender: &Sender<Event>,
+    repaint: &Repaint,
+    mut known: Vec<String>,
+) -> Result<()> {
+    let mut child = Command::new("ip")
+        .args(["monitor", "link"])
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("couldn't read output of 'ip monitor'"))?;
+
+    for line in BufReader::new(stdout).lines() {
+        let line = line?;
+        log::debug!("link event: {}", line);
+        let wlan_interfaces = match iw() {
+            Ok(wlan_interfaces) => wlan_interfaces,
+            Err(e) => {
+                log::error!("Error while getting wlan interfaces: {}", e);
+                continue;
+            }
+        };
+        // link events also fire for state changes, only report added or removed interfaces
+        let names = interface_names(&wlan_interfaces);
+        if names != known {
+            known = names;
+            if event_sender
+                .send(Event::UpdateInterfaces(wlan_interfaces))
+                .is_err()
+            {
+                break;
+            }
+            repaint.request_repaint();
+        }
+
+        if let Ok((_, name)) = link_name(&line) {
+            if !known.iter().any(|known_name| known_name == name) {
+                continue;
+            }
+            match get_interface_status(name) {
+                Ok(status) => {
+                    if event_sender
+                        .send(Event::UpdateInterfaceStatus(name.to_owned(), status))
+                        .is_err()
+                    {
+                        break;
+                    }
+                    repaint.request_repaint();
+                }
+                Err(e) => log::error!("Error while getting status of {}: {}", name, e),
+            }
+        }
+    }
+
+    child.kill()?;
+    Err(anyhow!("'ip monitor' stopped"))
+}
+
+fn interface_names(wlan_interfaces: &[WirelessInterface]) -> Vec<String> {
+    wlan_interfaces.iter().map(|wi| wi.name.clone()).collect()
+}
@@ -0,0 +1,184 @@
+use anyhow::{anyhow, Result};
+use std::{
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+/// Probes only need the status, a redirect and a small body.
+const MAX_RESPONSE_SIZE: u64 = 64 * 1024;
+
+/// A minimal HTTP/1.1 response, enough for connectivity probes.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    /// Where a redirect points to.
+    pub location: Option<String>,
+    pub body: String,
+}
+
+/// The host, port and path of a plain http URL, probes have to work before TLS does.
+pub fn parse_url(url: &str) -> Result<(String, u16, String)> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| anyhow!("only http URLs are supported: {}", url))?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        // an IPv6 address without a port
+        Some((host, _)) if authority.ends_with(']') || host.ends_with(':') => (authority, 80),
+        Some((host, port)) => (
+            host,
+            port.parse()
+                .map_err(|_| anyhow!("invalid port in {}", url))?,
+        ),
+        None => (authority, 80),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return Err(anyhow!("no host in {}", url));
+    }
+    Ok((host.to_owned(), port, path.to_owned()))
+}
+
+/// Sends a GET request, redirects aren't followed, as they are what captive portals answer.
+pub fn get(url: &str, timeout: Duration) -> Result<Response> {
+    let (host, port, path) = parse_url(url)?;
+    let address = (host.as_str(), port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow!("{} has no address", host))?;
+    let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    let host = match (host.contains(':'), port) {
+        (true, 80) => format!("[{}]", host),
+        (true, port) => format!("[{}]:{}", host, port),
+        (false, 80) => host,
+        (false, port) => format!("{}:{}", host, port),
+    };
+    // in one write, servers, which answer early, would otherwise reset the connection
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: swelfi\r\nConnection: close\r\n\r\n",
+        path, host
+    );
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = vec![];
+    stream.take(MAX_RESPONSE_SIZE).read_to_end(&mut response)?;
+    parse_response(&String::from_utf8_lossy(&response))
+}
+
+fn parse_response(input: &str) -> Result<Response> {
+    let (head, body) = input.split_once("\r\n\r\n").unwrap_or((input, ""));
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|status_line| status_line.split_whitespace().nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| anyhow!("invalid HTTP response"))?;
+
+    let mut location = None;
+    let mut chunked = false;
+    for (name, value) in lines.filter_map(|line| line.split_once(':')) {
+        let value = value.trim();
+        match name.to_lowercase().as_str() {
+            "location" => location = Some(value.to_owned()),
+            "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+            _ => (),
+        }
+    }
+    Ok(Response {
+        status,
+        location,
+        body: if chunked {
+            dechunk(body)
+        } else {
+            body.to_owned()
+        },
+    })
+}
+
+/// Joins the chunks of a chunked body, a truncated body keeps the chunks read so far.
+fn dechunk(mut input: &str) -> String {
+    let mut body = String::new();
+    while let Some((size, rest)) = input.split_once("\r\n") {
+        let size =
+            match usize::from_str_radix(size.split(';').next().unwrap_or_default().trim(), 16) {
+                Ok(0) | Err(_) => break,
+                Ok(size) => size.min(rest.len()),
+            };
+        body.push_str(rest.get(..size).unwrap_or(rest));
+        input = rest
+            .get(size..)
+            .unwrap_or_default()
+            .trim_start_matches("\r\n");
+    }
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::serve_http;
+
+    #[test]
+    fn urls() {
+        assert_eq!(
+            parse_url("http://connectivitycheck.gstatic.com/generate_204").unwrap(),
+            (
+                String::from("connectivitycheck.gstatic.com"),
+                80,
+                String::from("/generate_204")
+            )
+        );
+        assert_eq!(
+            parse_url("http://127.0.0.1:8080").unwrap(),
+            (String::from("127.0.0.1"), 8080, String::from("/"))
+        );
+        assert_eq!(
+            parse_url("http://[::1]:8080/check").unwrap(),
+            (String::from("::1"), 8080, String::from("/check"))
+        );
+        assert_eq!(
+            parse_url("http://[::1]/").unwrap(),
+            (String::from("::1"), 80, String::from("/"))
+        );
+        for invalid in [
+            "https://example.com/",
+            "example.com",
+            "http://",
+            "http://a:b/",
+        ] {
+            assert!(parse_url(invalid).is_err(), "accepted {}", invalid);
+        }
+    }
+
+    #[test]
+    fn get_from_stand_in() {
+        let url = serve_http("HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n");
+        assert_eq!(
+            get(&url, Duration::from_secs(5)).unwrap(),
+            Response {
+                status: 204,
+                location: None,
+                body: String::new(),
+            }
+        );
+
+        let url = serve_http(
+            "HTTP/1.1 302 Found\r\nLocation: http://portal.example/login\r\n\
+             Transfer-Encoding: chunked\r\n\r\n5\r\nlog i\r\n2\r\nn!\r\n0\r\n\r\n",
+        );
+        assert_eq!(
+            get(&url, Duration::from_secs(5)).unwrap(),
+            Response {
+                status: 302,
+                location: Some(String::from("http://portal.example/login")),
+                body: String::from("log in!"),
+            }
+        );
+    }
+}
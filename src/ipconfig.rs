@@ -0,0 +1,430 @@
+use anyhow::{anyhow, Result};
+use std::{
+    fmt,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+const NETWORKD_LEASES: &str = "/run/systemd/netif/leases";
+const DHCLIENT_LEASES: [&str; 2] = ["/var/lib/dhcp", "/var/lib/dhclient"];
+
+/// The layer 3 configuration of an interface.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IpConfig {
+    pub addresses: Vec<Address>,
+    /// Of the IPv4 and IPv6 default routes through the interface.
+    pub gateways: Vec<IpAddr>,
+    pub dns: Vec<IpAddr>,
+    pub dns_source: DnsSource,
+    /// None, if the address is static, or the DHCP client keeps its leases elsewhere.
+    pub lease: Option<Lease>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Address {
+    pub address: IpAddr,
+    pub prefix: u8,
+    /// Like global, or link.
+    pub scope: String,
+    /// Assigned by DHCP, or SLAAC.
+    pub dynamic: bool,
+    /// None, if it's valid forever.
+    pub valid_lifetime: Option<Duration>,
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum DnsSource {
+    /// The servers of the interface in systemd-resolved.
+    Resolved,
+    /// The global servers from /etc/resolv.conf.
+    #[default]
+    ResolvConf,
+}
+
+impl fmt::Display for DnsSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DnsSource::Resolved => write!(f, "systemd-resolved"),
+            DnsSource::ResolvConf => write!(f, "{}", RESOLV_CONF),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Lease {
+    pub address: Option<IpAddr>,
+    pub server: Option<IpAddr>,
+    pub router: Option<IpAddr>,
+    pub lifetime: Option<Duration>,
+    pub dns: Vec<IpAddr>,
+    pub domain: Option<String>,
+    /// When it expires, as written by dhclient, systemd-networkd doesn't save it.
+    pub expires: Option<String>,
+    /// The file it was read from.
+    pub path: PathBuf,
+}
+
+/// Reads the configuration of the interface, only the addresses are required, everything else
+/// is left empty, if it can't be read.
+pub fn get_ip_config(interface: &str) -> Result<IpConfig> {
+    let addresses = parse_addresses(&run_ip(&["-o", "addr", "show", "dev", interface])?);
+    let mut gateways = vec![];
+    for family in ["-4", "-6"] {
+        match run_ip(&[family, "-o", "route", "show", "default", "dev", interface]) {
+            Ok(output) => gateways.extend(parse_gateways(&output)),
+            Err(e) => log::debug!("Error while getting default routes: {}", e),
+        }
+    }
+    let (dns, dns_source) = match resolved_dns(interface) {
+        Ok(dns) => (dns, DnsSource::Resolved),
+        Err(e) => {
+            log::debug!("Falling back to {}: {}", RESOLV_CONF, e);
+            let resolv_conf = std::fs::read_to_string(RESOLV_CONF).unwrap_or_default();
+            (parse_resolv_conf(&resolv_conf), DnsSource::ResolvConf)
+        }
+    };
+    Ok(IpConfig {
+        addresses,
+        gateways,
+        dns,
+        dns_source,
+        lease: read_lease(interface),
+    })
+}
+
+fn run_ip(args: &[&str]) -> Result<String> {
+    let output = Command::new("ip")
+        .args(args)
+        .output()
+        .map_err(|e| anyhow!("running 'ip' failed: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "'ip {}' failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// `3: wlp64s0    inet 192.168.1.23/24 brd 192.168.1.255 scope global dynamic wlp64s0\
+///        valid_lft 85634sec preferred_lft 85634sec`
+fn parse_addresses(input: &str) -> Vec<Address> {
+    input
+        .lines()
+        .filter_map(|line| {
+            let mut tokens = line
+                .split_whitespace()
+                .skip_while(|token| *token != "inet" && *token != "inet6")
+                .skip(1);
+            let (address, prefix) = tokens.next()?.split_once('/')?;
+            let mut address = Address {
+                address: address.parse().ok()?,
+                prefix: prefix.parse().ok()?,
+                scope: String::new(),
+                dynamic: false,
+                valid_lifetime: None,
+            };
+            while let Some(token) = tokens.next() {
+                match token {
+                    "scope" => address.scope = tokens.next().unwrap_or_default().to_owned(),
+                    "dynamic" => address.dynamic = true,
+                    "valid_lft" => {
+                        address.valid_lifetime = tokens
+                            .next()
+                            .and_then(|lifetime| lifetime.strip_suffix("sec"))
+                            .and_then(|secs| secs.parse().ok())
+                            .map(Duration::from_secs)
+                    }
+                    _ => (),
+                }
+            }
+            Some(address)
+        })
+        .collect()
+}
+
+/// `default via 192.168.1.1 proto dhcp src 192.168.1.23 metric 600`
+fn parse_gateways(input: &str) -> Vec<IpAddr> {
+    input
+        .lines()
+        .filter_map(|line| {
+            let mut tokens = line.split_whitespace().skip_while(|token| *token != "via");
+            tokens.nth(1)?.parse().ok()
+        })
+        .collect()
+}
+
+fn resolved_dns(interface: &str) -> Result<Vec<IpAddr>> {
+    let output = Command::new("resolvectl")
+        .args(["dns", interface])
+        .output()
+        .map_err(|e| anyhow!("running 'resolvectl' failed: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "'resolvectl dns {}' failed: {}",
+            interface,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(parse_resolvectl(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// `Link 3 (wlp64s0): 192.168.1.1 fe80::1%3 1.1.1.1#cloudflare-dns.com`
+fn parse_resolvectl(input: &str) -> Vec<IpAddr> {
+    input
+        .split_once("):")
+        .map(|(_, servers)| servers)
+        .unwrap_or_default()
+        .split_whitespace()
+        .filter_map(|server| {
+            // without the zone of link local addresses and the name for DNS over TLS
+            server.split(['%', '#']).next()?.parse().ok()
+        })
+        .collect()
+}
+
+fn parse_resolv_conf(input: &str) -> Vec<IpAddr> {
+    input
+        .lines()
+        .filter_map(|line| {
+            let mut tokens = line.split_whitespace();
+            if tokens.next()? != "nameserver" {
+                return None;
+            }
+            tokens.next()?.split('%').next()?.parse().ok()
+        })
+        .collect()
+}
+
+/// Looks for the lease in the places systemd-networkd and dhclient keep it.
+fn read_lease(interface: &str) -> Option<Lease> {
+    let networkd = std::fs::read_to_string(format!("/sys/class/net/{}/ifindex", interface))
+        .ok()
+        .map(|ifindex| Path::new(NETWORKD_LEASES).join(ifindex.trim()));
+    if let Some(path) = networkd {
+        if let Ok(input) = std::fs::read_to_string(&path) {
+            return Some(parse_networkd_lease(&input, path));
+        }
+    }
+    DHCLIENT_LEASES.iter().find_map(|directory| {
+        [
+            format!("dhclient.{}.leases", interface),
+            format!("dhclient-{}.leases", interface),
+        ]
+        .into_iter()
+        .map(|file| Path::new(directory).join(file))
+        .find_map(|path| {
+            let input = std::fs::read_to_string(&path).ok()?;
+            parse_dhclient_lease(&input, path)
+        })
+    })
+}
+
+/// `KEY=value` lines, as written to /run/systemd/netif/leases/<ifindex>.
+fn parse_networkd_lease(input: &str, path: PathBuf) -> Lease {
+    let mut lease = Lease {
+        path,
+        ..Lease::default()
+    };
+    for (key, value) in input.lines().filter_map(|line| line.split_once('=')) {
+        match key {
+            "ADDRESS" => lease.address = value.parse().ok(),
+            "SERVER_ADDRESS" => lease.server = value.parse().ok(),
+            "ROUTER" => {
+                lease.router = value
+                    .split_whitespace()
+                    .next()
+                    .and_then(|router| router.parse().ok())
+            }
+            "LIFETIME" => lease.lifetime = value.parse().ok().map(Duration::from_secs),
+            "DNS" => {
+                lease.dns = value
+                    .split_whitespace()
+                    .filter_map(|dns| dns.parse().ok())
+                    .collect()
+            }
+            "DOMAINNAME" => lease.domain = Some(value.to_owned()),
+            _ => (),
+        }
+    }
+    lease
+}
+
+/// The last `lease { ... }` block, dhclient appends a new one on every renewal.
+fn parse_dhclient_lease(input: &str, path: PathBuf) -> Option<Lease> {
+    let block = input
+        .rsplit("lease {")
+        .next()
+        .filter(|_| input.contains("lease {"))?;
+    let mut lease = Lease {
+        path,
+        ..Lease::default()
+    };
+    for statement in block.split(';').map(str::trim) {
+        let statement = statement.trim_start_matches("option ");
+        let (key, value) = match statement.split_once(' ') {
+            Some((key, value)) => (key, value.trim_matches('"')),
+            None => continue,
+        };
+        let addresses = || value.split(',').filter_map(|a| a.trim().parse().ok());
+        match key {
+            "fixed-address" => lease.address = value.parse().ok(),
+            "dhcp-server-identifier" => lease.server = value.parse().ok(),
+            "routers" => lease.router = addresses().next(),
+            "dhcp-lease-time" => lease.lifetime = value.parse().ok().map(Duration::from_secs),
+            "domain-name-servers" => lease.dns = addresses().collect(),
+            "domain-name" => lease.domain = Some(value.to_owned()),
+            // like `4 2026/10/22 06:00:00`, the weekday is left out
+            "expire" => lease.expires = value.split_once(' ').map(|(_, time)| time.to_owned()),
+            _ => (),
+        }
+    }
+    Some(lease)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses() {
+        let input = "3: wlp64s0    inet 192.168.1.23/24 brd 192.168.1.255 scope global dynamic noprefixroute wlp64s0\\       valid_lft 85634sec preferred_lft 85634sec
+3: wlp64s0    inet6 2001:db8::1234/64 scope global dynamic mngtmpaddr \\       valid_lft 86300sec preferred_lft 14300sec
+3: wlp64s0    inet6 fe80::9efc:e8ff:feb8:fa60/64 scope link \\       valid_lft forever preferred_lft forever
+";
+
+        assert_eq!(
+            parse_addresses(input),
+            vec![
+                Address {
+                    address: "192.168.1.23".parse().unwrap(),
+                    prefix: 24,
+                    scope: String::from("global"),
+                    dynamic: true,
+                    valid_lifetime: Some(Duration::from_secs(85634)),
+                },
+                Address {
+                    address: "2001:db8::1234".parse().unwrap(),
+                    prefix: 64,
+                    scope: String::from("global"),
+                    dynamic: true,
+                    valid_lifetime: Some(Duration::from_secs(86300)),
+                },
+                Address {
+                    address: "fe80::9efc:e8ff:feb8:fa60".parse().unwrap(),
+                    prefix: 64,
+                    scope: String::from("link"),
+                    dynamic: false,
+                    valid_lifetime: None,
+                },
+            ]
+        );
+        assert_eq!(parse_addresses(""), vec![]);
+    }
+
+    #[test]
+    fn gateways_and_dns() {
+        assert_eq!(
+            parse_gateways(
+                "default via 192.168.1.1 proto dhcp src 192.168.1.23 metric 600 \n\
+                 default via fe80::1 proto ra metric 600 pref medium\n"
+            ),
+            vec![
+                "192.168.1.1".parse::<IpAddr>().unwrap(),
+                "fe80::1".parse().unwrap()
+            ]
+        );
+        assert_eq!(
+            parse_resolvectl(
+                "Link 3 (wlp64s0): 192.168.1.1 fe80::1%3 1.1.1.1#cloudflare-dns.com\n"
+            ),
+            vec![
+                "192.168.1.1".parse::<IpAddr>().unwrap(),
+                "fe80::1".parse().unwrap(),
+                "1.1.1.1".parse().unwrap()
+            ]
+        );
+        assert!(parse_resolvectl("Link 3 (wlp64s0):\n").is_empty());
+        assert_eq!(
+            parse_resolv_conf("# generated\nnameserver 127.0.0.53\noptions edns0\nsearch lan\n"),
+            vec!["127.0.0.53".parse::<IpAddr>().unwrap()]
+        );
+    }
+
+    #[test]
+    fn networkd_lease() {
+        let input = "# This is private data. Do not parse.
+ADDRESS=192.168.1.23
+NETMASK=255.255.255.0
+ROUTER=192.168.1.1
+SERVER_ADDRESS=192.168.1.1
+LIFETIME=86400
+T1=43200
+DNS=192.168.1.1 9.9.9.9
+DOMAINNAME=lan
+";
+
+        assert_eq!(
+            parse_networkd_lease(input, PathBuf::from("/run/systemd/netif/leases/3")),
+            Lease {
+                address: Some("192.168.1.23".parse().unwrap()),
+                server: Some("192.168.1.1".parse().unwrap()),
+                router: Some("192.168.1.1".parse().unwrap()),
+                lifetime: Some(Duration::from_secs(86400)),
+                dns: vec!["192.168.1.1".parse().unwrap(), "9.9.9.9".parse().unwrap()],
+                domain: Some(String::from("lan")),
+                expires: None,
+                path: PathBuf::from("/run/systemd/netif/leases/3"),
+            }
+        );
+    }
+
+    #[test]
+    fn dhclient_lease() {
+        let input = "lease {
+  interface \"wlp64s0\";
+  fixed-address 10.0.0.5;
+  option dhcp-lease-time 600;
+  expire 1 2026/10/19 08:00:00;
+}
+lease {
+  interface \"wlp64s0\";
+  fixed-address 192.168.1.23;
+  option subnet-mask 255.255.255.0;
+  option routers 192.168.1.1;
+  option dhcp-lease-time 86400;
+  option dhcp-server-identifier 192.168.1.254;
+  option domain-name-servers 192.168.1.1,8.8.8.8;
+  option domain-name \"lan\";
+  renew 3 2026/10/21 12:00:00;
+  expire 4 2026/10/22 06:00:00;
+}
+";
+        let path = PathBuf::from("/var/lib/dhcp/dhclient.wlp64s0.leases");
+
+        assert_eq!(
+            parse_dhclient_lease(input, path.clone()),
+            Some(Lease {
+                address: Some("192.168.1.23".parse().unwrap()),
+                server: Some("192.168.1.254".parse().unwrap()),
+                router: Some("192.168.1.1".parse().unwrap()),
+                lifetime: Some(Duration::from_secs(86400)),
+                dns: vec!["192.168.1.1".parse().unwrap(), "8.8.8.8".parse().unwrap()],
+                domain: Some(String::from("lan")),
+                expires: Some(String::from("2026/10/22 06:00:00")),
+                path: path.clone(),
+            })
+        );
+        assert_eq!(parse_dhclient_lease("", path), None);
+    }
+}
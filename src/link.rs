@@ -0,0 +1,310 @@
+use crate::{error::SwelfiError, get_wireless_interface, WirelessInterface};
+use anyhow::{anyhow, Result};
+use nom::{
+    bytes::complete::{tag, take_till, take_till1, take_until},
+    character::complete::{char, line_ending, not_line_ending},
+    multi::many0,
+    sequence::{preceded, separated_pair, tuple},
+    IResult,
+};
+use std::{fmt, process::Command, str::FromStr, time::Duration};
+
+const STATION: &str = "Station ";
+const SURVEY: &str = "Survey data from ";
+const IN_USE: &str = "[in use]";
+
+/// The active link of an interface, as shown in the connection panel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkInfo {
+    pub interface: WirelessInterface,
+    /// The access point, None while not connected.
+    pub station: Option<Station>,
+    /// In dBm on the channel in use, not every driver reports it.
+    pub noise: Option<i32>,
+}
+
+impl LinkInfo {
+    /// Signal to noise ratio in dB.
+    pub fn snr(&self) -> Option<i32> {
+        Some(self.station.as_ref()?.signal? - self.noise?)
+    }
+}
+
+/// Station info of the access point from `iw dev <interface> station dump`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Station {
+    pub bssid: String,
+    /// In dBm.
+    pub signal: Option<i32>,
+    pub tx_bitrate: Option<Bitrate>,
+    pub rx_bitrate: Option<Bitrate>,
+    pub connected_time: Option<Duration>,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub tx_retries: u64,
+    pub tx_failed: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bitrate {
+    /// In MBit/s.
+    pub rate: f64,
+    /// Only set for HT and newer rates.
+    pub mcs: Option<u8>,
+    /// Spatial streams, only reported for VHT and newer rates.
+    pub nss: Option<u8>,
+}
+
+impl fmt::Display for Bitrate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1} MBit/s", self.rate)?;
+        if let Some(mcs) = self.mcs {
+            write!(f, ", MCS {}", mcs)?;
+        }
+        if let Some(nss) = self.nss {
+            write!(f, ", {} streams", nss)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the link of the interface, it's cheap enough to call every second.
+pub fn get_link_info(interface: &str) -> Result<LinkInfo> {
+    let wlan_interface = get_wireless_interface(interface)?;
+    let station = station(&run_iw(interface, "station")?).ok().map(|(_, s)| s);
+    // noise is optional, some drivers don't support surveys at all
+    let noise = run_iw(interface, "survey")
+        .ok()
+        .and_then(|output| survey_noise(&output));
+    Ok(LinkInfo {
+        interface: wlan_interface,
+        station,
+        noise,
+    })
+}
+
+/// The access point the interface is connected to, None while it isn't.
+pub fn get_bssid(interface: &str) -> Result<Option<String>> {
+    Ok(station(&run_iw(interface, "station")?)
+        .ok()
+        .map(|(_, station)| station.bssid))
+}
+
+fn run_iw(interface: &str, command: &str) -> Result<String> {
+    let output = Command::new("iw")
+        .args(["dev", interface, command, "dump"])
+        .output()
+        .map_err(|e| anyhow!("running 'iw' failed: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "getting {} info for {} using 'iw' failed",
+            command,
+            interface
+        ));
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|_| SwelfiError::Parse(String::from("output of 'iw' wasn't valid utf-8")).into())
+}
+
+/// The first station, for a managed interface that's the access point.
+fn station(input: &str) -> IResult<&str, Station> {
+    let (input, (_, _, bssid, _)) = tuple((
+        take_until(STATION),
+        tag(STATION),
+        take_till(|c| c == ' ' || c == '\n'),
+        not_line_ending,
+    ))(input)?;
+    let (input, fields) = many0(preceded(tuple((line_ending, char('\t'))), field))(input)?;
+
+    let mut station = Station {
+        bssid: bssid.to_owned(),
+        ..Station::default()
+    };
+    for (key, value) in fields {
+        let value = value.trim();
+        match key {
+            "signal" => station.signal = number(value),
+            "tx bitrate" => station.tx_bitrate = bitrate(value),
+            "rx bitrate" => station.rx_bitrate = bitrate(value),
+            "connected time" => station.connected_time = number(value).map(Duration::from_secs),
+            "rx bytes" => station.rx_bytes = number(value).unwrap_or_default(),
+            "tx bytes" => station.tx_bytes = number(value).unwrap_or_default(),
+            "rx packets" => station.rx_packets = number(value).unwrap_or_default(),
+            "tx packets" => station.tx_packets = number(value).unwrap_or_default(),
+            "tx retries" => station.tx_retries = number(value).unwrap_or_default(),
+            "tx failed" => station.tx_failed = number(value).unwrap_or_default(),
+            _ => (),
+        }
+    }
+    Ok((input, station))
+}
+
+/// The number at the start of a value, like `-52` in `-52 [-54, -55] dBm`.
+fn number<T: FromStr>(value: &str) -> Option<T> {
+    value.split_whitespace().next()?.parse().ok()
+}
+
+/// `key:\tvalue`
+fn field(input: &str) -> IResult<&str, (&str, &str)> {
+    separated_pair(
+        take_till1(|c| c == ':' || c == '\n'),
+        char(':'),
+        not_line_ending,
+    )(input)
+}
+
+/// `866.7 MBit/s VHT-MCS 9 80MHz short GI VHT-NSS 2`, or `54.0 MBit/s` for legacy rates.
+fn bitrate(value: &str) -> Option<Bitrate> {
+    let mut tokens = value.split_whitespace();
+    let mut bitrate = Bitrate {
+        rate: tokens.next()?.parse().ok()?,
+        mcs: None,
+        nss: None,
+    };
+    while let Some(token) = tokens.next() {
+        if token == "MCS" || token.ends_with("-MCS") {
+            bitrate.mcs = tokens.next().and_then(|mcs| mcs.parse().ok());
+        } else if token.ends_with("-NSS") {
+            bitrate.nss = tokens.next().and_then(|nss| nss.parse().ok());
+        }
+    }
+    Some(bitrate)
+}
+
+/// The noise of the channel in use, from `iw dev <interface> survey dump`.
+fn survey_noise(input: &str) -> Option<i32> {
+    input
+        .split(SURVEY)
+        .find(|survey| survey.contains(IN_USE))?
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("noise:"))?
+        .trim()
+        .trim_end_matches("dBm")
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Like `12.3 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Like `1h 2m 3s`, leaving out the leading zero parts.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {}s", m, s),
+        (h, m, s) => format!("{}h {}m {}s", h, m, s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATION_DUMP: &str = "Station d4:1a:d1:51:67:f2 (on wlp64s0)
+	inactive time:	1200 ms
+	rx bytes:	123456789
+	rx packets:	123456
+	tx bytes:	12345678
+	tx packets:	12345
+	tx retries:	123
+	tx failed:	4
+	beacon loss:	0
+	rx drop misc:	12
+	signal:  	-52 [-54, -55] dBm
+	signal avg:	-51 [-53, -54] dBm
+	tx bitrate:	866.7 MBit/s VHT-MCS 9 80MHz short GI VHT-NSS 2
+	rx bitrate:	6.0 MBit/s
+	expected throughput:	520.996Mbps
+	authorized:	yes
+	beacon interval:100
+	connected time:	3723 seconds
+	associated at [boottime]:	123.456s
+";
+
+    const SURVEY_DUMP: &str = "Survey data from wlp64s0
+	frequency:			5480 MHz
+Survey data from wlp64s0
+	frequency:			5500 MHz [in use]
+	noise:				-92 dBm
+	channel active time:		1234 ms
+	channel busy time:		56 ms
+Survey data from wlp64s0
+	frequency:			5520 MHz
+	noise:				-95 dBm
+";
+
+    #[test]
+    fn parse_station() {
+        assert_eq!(
+            station(STATION_DUMP).unwrap().1,
+            Station {
+                bssid: String::from("d4:1a:d1:51:67:f2"),
+                signal: Some(-52),
+                tx_bitrate: Some(Bitrate {
+                    rate: 866.7,
+                    mcs: Some(9),
+                    nss: Some(2),
+                }),
+                rx_bitrate: Some(Bitrate {
+                    rate: 6.0,
+                    mcs: None,
+                    nss: None,
+                }),
+                connected_time: Some(Duration::from_secs(3723)),
+                rx_bytes: 123456789,
+                tx_bytes: 12345678,
+                rx_packets: 123456,
+                tx_packets: 12345,
+                tx_retries: 123,
+                tx_failed: 4,
+            }
+        );
+        // not connected
+        assert!(station("").is_err());
+    }
+
+    #[test]
+    fn parse_bitrates() {
+        let mcs = |value| bitrate(value).map(|b| (b.mcs, b.nss));
+
+        assert_eq!(
+            mcs("300.0 MBit/s MCS 15 40MHz short GI"),
+            Some((Some(15), None))
+        );
+        assert_eq!(
+            mcs("1200.9 MBit/s 80MHz HE-MCS 11 HE-NSS 2 HE-GI 0 HE-DCM 0"),
+            Some((Some(11), Some(2)))
+        );
+        assert_eq!(mcs("unknown"), None);
+    }
+
+    #[test]
+    fn parse_noise() {
+        assert_eq!(survey_noise(SURVEY_DUMP), Some(-92));
+        assert_eq!(survey_noise("Survey data from wlp64s0\n"), None);
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(123456789), "117.7 MiB");
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1h 2m 3s");
+    }
+}
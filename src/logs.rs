@@ -0,0 +1,71 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Enough to see what led up to a problem, without growing in long sessions.
+const MAX_RECORDS: usize = 1000;
+/// Kept for the diagnostics bundle, even if `RUST_LOG` doesn't print them.
+const KEPT_LEVEL: LevelFilter = LevelFilter::Info;
+
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Prints like env_logger and keeps the recent records.
+struct Logger {
+    printed: env_logger::Logger,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= KEPT_LEVEL || self.printed.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.printed.matches(record) {
+            self.printed.log(record);
+        }
+        if record.level() > KEPT_LEVEL {
+            return;
+        }
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() == MAX_RECORDS {
+            recent.pop_front();
+        }
+        recent.push_back(format!(
+            "{}.{:03} {:5} {}: {}",
+            time.as_secs(),
+            time.subsec_millis(),
+            record.level(),
+            record.target(),
+            record.args()
+        ));
+    }
+
+    fn flush(&self) {
+        self.printed.flush();
+    }
+}
+
+/// Sets up logging, configured by `RUST_LOG` like env_logger.
+pub fn init() {
+    let printed = env_logger::Builder::from_default_env().build();
+    let max_level = printed.filter().max(KEPT_LEVEL);
+    if log::set_boxed_logger(Box::new(Logger { printed })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// The recent records, the oldest first.
+pub fn recent() -> Vec<String> {
+    RECENT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .cloned()
+        .collect()
+}
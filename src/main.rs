@@ -1,31 +1,67 @@
 use anyhow::{anyhow, Result};
+use app::{AppState, Event, Repaint};
+use backend::Backend;
 use eframe::egui;
+use error::SwelfiError;
 use nom::{
     bytes::complete::{tag, take_until, take_while},
-    character::complete::{digit1, not_line_ending},
+    character::complete::{digit1, line_ending, not_line_ending, u32 as number},
     combinator::opt,
     multi::many0,
     number::complete::double,
-    sequence::{delimited, tuple},
+    sequence::{delimited, preceded, tuple},
     IResult,
 };
-use std::{process::Command, sync::mpsc::Receiver};
-use std::{
-    sync::mpsc::{channel, Sender},
-    time::Duration,
-};
+use serde::{Deserialize, Serialize};
+use settings::{Settings, Theme};
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant, SystemTime};
+use table::TableAction;
 
+mod app;
+mod backend;
+mod bar;
+mod bundle;
+mod cli;
+mod daemon;
+mod diagnose;
+mod error;
+mod export;
 mod fps;
+mod heatmap;
+mod helper;
+mod hotplug;
+mod http;
+mod ipconfig;
+mod link;
+mod logs;
+mod notify;
+mod portal;
+mod replay;
+mod rfkill;
+mod rules;
+mod settings;
+mod simulate;
+mod survey;
+mod table;
+mod tray;
+mod tui;
 
 const INTERFACE: &str = "Interface ";
-const SSID: &str = "ssid ";
 const CELL: &str = "Cell ";
 const FREQUENCY: &str = "Frequency:";
 const QUALITY: &str = "Quality=";
 const ESSID: &str = "ESSID:";
 const IEEE: &str = "IEEE 802.11";
+const LAST_BEACON: &str = "Last beacon: ";
+const STATE: &str = " state ";
+const LINK_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+const IP_CONFIG_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 enum SecurityType {
     Wpa2,
     Wpa3,
@@ -44,73 +80,1159 @@ impl From<&str> for SecurityType {
     }
 }
 
-#[derive(Debug, PartialEq)]
+impl SecurityType {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SecurityType::Wpa2 => "WPA2",
+            SecurityType::Wpa3 => "WPA3",
+            SecurityType::Wpa => "WPA",
+            SecurityType::Invalid => "open",
+        }
+    }
+
+    /// Orders the security types from open to WPA3.
+    pub fn strength(&self) -> u8 {
+        match self {
+            SecurityType::Invalid => 0,
+            SecurityType::Wpa => 1,
+            SecurityType::Wpa2 => 2,
+            SecurityType::Wpa3 => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize)]
+enum Band {
+    #[serde(rename = "2.4")]
+    TwoFour,
+    #[serde(rename = "5")]
+    Five,
+    #[serde(rename = "6")]
+    Six,
+}
+
+impl Band {
+    pub const ALL: [Band; 3] = [Band::TwoFour, Band::Five, Band::Six];
+
+    /// The frequency is in GHz, like the scan results.
+    pub fn of(frequency: f64) -> Self {
+        match frequency {
+            f if f < 3.0 => Band::TwoFour,
+            f if f < 5.925 => Band::Five,
+            _ => Band::Six,
+        }
+    }
+}
+
+impl std::fmt::Display for Band {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Band::TwoFour => write!(f, "2.4 GHz"),
+            Band::Five => write!(f, "5 GHz"),
+            Band::Six => write!(f, "6 GHz"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct WirelessNetwork {
     pub address: String,
     pub quality: Quality,
     pub frequency: f64,
     pub essid: String,
     pub security_type: SecurityType,
+    /// Milliseconds since the last beacon, when the scan finished.
+    #[serde(skip)]
+    pub last_beacon: Option<u64>,
+    /// The access points seen with this SSID, the network is the strongest of them.
+    #[serde(skip)]
+    pub access_points: usize,
 }
 
 impl WirelessNetwork {
+    /// Identifies the network independent of how it's shown, by the access point's address.
     pub fn id(&self) -> String {
-        format!("{} - ({})", self.essid, self.address)
+        self.address.clone()
+    }
+
+    pub fn band(&self) -> Band {
+        Band::of(self.frequency)
+    }
+
+    pub fn channel(&self) -> u32 {
+        let mhz = (self.frequency * 1000.0).round() as u32;
+        match self.band() {
+            Band::TwoFour if mhz == 2484 => 14,
+            Band::TwoFour => mhz.saturating_sub(2407) / 5,
+            Band::Five => mhz.saturating_sub(5000) / 5,
+            Band::Six if mhz == 5935 => 2,
+            Band::Six => mhz.saturating_sub(5950) / 5,
+        }
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 struct Quality {
     pub value: u64,
     pub limit: u64,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+impl Quality {
+    pub fn percent(&self) -> u64 {
+        if self.limit == 0 {
+            return 0;
+        }
+        (self.value * 100 / self.limit).min(100)
+    }
+
+    /// Signal bars from 0 to 4, any signal shows at least one bar.
+    pub fn bars(&self) -> usize {
+        if self.limit == 0 {
+            return 0;
+        }
+        (self.value * 4).div_ceil(self.limit).min(4) as usize
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
 struct WirelessInterface {
     pub name: String,
     pub connected_ssid: Option<String>,
+    /// MAC address of the interface.
+    pub addr: Option<String>,
+    /// Like managed, or monitor.
+    pub iftype: Option<String>,
+    /// Only set while the interface is on a channel, like when it's connected.
+    pub channel: Option<ChannelInfo>,
+    /// In dBm.
+    pub txpower: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+struct ChannelInfo {
+    pub number: u32,
+    /// In MHz.
+    pub frequency: u32,
+    /// In MHz, the width of the channel used, not of the access point.
+    pub width: Option<u32>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+struct InterfaceStatus {
+    pub up: bool,
+    pub oper_state: String,
+    pub rfkill: rfkill::RfkillState,
+    pub connected_ssid: Option<String>,
 }
 
-enum Event {
-    RefreshNetworks(egui::Context, String, Option<Duration>),
-    UpdateNetworks(Option<Vec<WirelessNetwork>>),
+impl InterfaceStatus {
+    pub fn is_on(&self) -> bool {
+        self.up && !self.rfkill.soft_blocked && !self.rfkill.hard_blocked
+    }
 }
 
 struct SwelfiApp {
     app_state: AppState,
     background_event_sender: Sender<Event>,
     event_receiver: Receiver<Event>,
+    repaint: Repaint,
+    /// Focus the connected network, once the first scan results arrive.
+    focus_networks: bool,
+    /// Without a tray, closing the window quits.
+    tray: Option<tray::Tray>,
+    quitting: bool,
+    last_refresh: Instant,
+    /// The link is only refreshed while the connection panel is open.
+    last_link_refresh: Option<Instant>,
+    last_ip_config_refresh: Option<Instant>,
+    anonymize_bundle: bool,
+    survey: SurveyPanel,
+}
+
+struct ExportDialog {
+    content: export::Content,
+    format: export::Format,
+    interface: String,
+    path: String,
+}
+
+impl ExportDialog {
+    fn new(interface: &str) -> Self {
+        let mut dialog = Self {
+            content: export::Content::Scan,
+            format: export::Format::Json,
+            interface: interface.to_owned(),
+            path: String::new(),
+        };
+        dialog.path = dialog.default_path();
+        dialog
+    }
+
+    fn default_path(&self) -> String {
+        let directory = std::env::var("HOME").unwrap_or_else(|_| String::from("."));
+        format!(
+            "{}/swelfi-{}-{}.{}",
+            directory,
+            self.interface,
+            self.content,
+            self.format.extension()
+        )
+    }
+}
+
+/// Where to record to and the recording loaded for browsing.
+struct SurveyPanel {
+    record_path: String,
+    load_path: String,
+    locations: Option<Vec<survey::Location>>,
+    selected_location: usize,
+    floor_plan_path: String,
+    floor_plan: Option<FloorPlan>,
+}
+
+/// The floor plan of a site survey, with the heatmap of the measurements on top.
+struct FloorPlan {
+    image: image::RgbaImage,
+    texture: egui::TextureHandle,
+    target: Option<heatmap::Target>,
+    /// Rendered again, when the target, or the number of measurements changes.
+    heat: Option<((heatmap::Target, usize), egui::TextureHandle)>,
+    export_path: String,
+    exported: Option<String>,
+}
+
+impl FloorPlan {
+    fn open(ctx: &egui::Context, path: &str) -> Result<Self> {
+        let image = image::open(path)?.into_rgba8();
+        let size = [image.width() as usize, image.height() as usize];
+        let texture = ctx.load_texture(
+            "floor plan",
+            egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw()),
+            egui::TextureOptions::LINEAR,
+        );
+        let directory = std::env::var("HOME").unwrap_or_else(|_| String::from("."));
+        Ok(Self {
+            image,
+            texture,
+            target: None,
+            heat: None,
+            export_path: format!("{}/swelfi-heatmap.png", directory),
+            exported: None,
+        })
+    }
+
+    /// The heatmap of the target, rendered again if needed.
+    fn heat(
+        &mut self,
+        ctx: &egui::Context,
+        measurements: &[survey::Sample],
+    ) -> Option<&egui::TextureHandle> {
+        let key = (self.target.clone()?, measurements.len());
+        if self
+            .heat
+            .as_ref()
+            .is_none_or(|(rendered, _)| *rendered != key)
+        {
+            let aspect_ratio = self.image.width() as f32 / self.image.height() as f32;
+            let heat = heatmap::render(measurements, &key.0, aspect_ratio);
+            let size = [heat.width() as usize, heat.height() as usize];
+            let texture = ctx.load_texture(
+                "heatmap",
+                egui::ColorImage::from_rgba_unmultiplied(size, heat.as_raw()),
+                egui::TextureOptions::LINEAR,
+            );
+            self.heat = Some((key, texture));
+        }
+        self.heat.as_ref().map(|(_, texture)| texture)
+    }
 }
 
-struct AppState {
-    wlan_interfaces: Vec<WirelessInterface>,
-    selected_wlan_interface: String,
-    wlan_networks: Option<Vec<WirelessNetwork>>,
-    selected_wlan_network: String,
-    connected_wlan_network: Option<String>,
-    wlan_on: bool,
-    frame_history: fps::FrameHistory,
+impl Default for SurveyPanel {
+    fn default() -> Self {
+        let directory = std::env::var("HOME").unwrap_or_else(|_| String::from("."));
+        let path = format!("{}/swelfi-survey.jsonl", directory);
+        Self {
+            record_path: path.clone(),
+            load_path: path,
+            locations: None,
+            selected_location: 0,
+            floor_plan_path: format!("{}/floor-plan.png", directory),
+            floor_plan: None,
+        }
+    }
 }
 
 impl SwelfiApp {
     fn new(
-        context: &eframe::CreationContext<'_>,
-        app_state: AppState,
+        repaint: Repaint,
+        mut app_state: AppState,
         background_event_sender: Sender<Event>,
         event_receiver: Receiver<Event>,
+        focus_networks: bool,
     ) -> Self {
-        background_event_sender
-            .send(Event::RefreshNetworks(
-                context.egui_ctx.clone(),
-                app_state.selected_wlan_interface.clone(),
-                None,
-            ))
-            .expect("can send on channel");
+        app_state.refresh(&background_event_sender, &repaint);
+        app_state.detect_portal(&background_event_sender, &repaint);
         log::info!("sent event...waiting");
+        let tray = match tray::Tray::spawn(&app_state, repaint.clone()) {
+            Ok(tray) => Some(tray),
+            Err(e) => {
+                log::warn!(
+                    "Couldn't create the tray icon, closing the window quits: {}",
+                    e
+                );
+                None
+            }
+        };
         Self {
             app_state,
             background_event_sender,
             event_receiver,
+            repaint,
+            focus_networks,
+            tray,
+            quitting: false,
+            last_refresh: Instant::now(),
+            last_link_refresh: None,
+            last_ip_config_refresh: None,
+            anonymize_bundle: true,
+            survey: SurveyPanel::default(),
+        }
+    }
+}
+
+impl SwelfiApp {
+    /// Reloads the selected interface in the interval from the settings, while it's on.
+    fn auto_refresh(&mut self, ctx: &egui::Context) {
+        let interval = match self.app_state.settings.refresh_interval() {
+            Some(interval) => interval,
+            None => return,
+        };
+        if self.app_state.wlan_on && self.last_refresh.elapsed() >= interval {
+            self.app_state
+                .reload(&self.background_event_sender, &self.repaint);
+            self.last_refresh = Instant::now();
+        }
+        ctx.request_repaint_after(interval.saturating_sub(self.last_refresh.elapsed()));
+    }
+
+    /// Details of the active link, refreshed every second while the section is open.
+    fn connection_ui(&mut self, ui: &mut egui::Ui) {
+        if self
+            .last_link_refresh
+            .is_none_or(|last| last.elapsed() >= LINK_REFRESH_INTERVAL)
+        {
+            self.app_state
+                .refresh_link_info(&self.background_event_sender, &self.repaint);
+            self.last_link_refresh = Some(Instant::now());
+        }
+        ui.ctx().request_repaint_after(LINK_REFRESH_INTERVAL);
+
+        let link_info = match self.app_state.link_info {
+            Some(ref link_info) => link_info,
+            None => {
+                ui.spinner();
+                return;
+            }
+        };
+        let station = match link_info.station {
+            Some(ref station) => station,
+            None => {
+                ui.label("Not connected");
+                return;
+            }
+        };
+        let row = |ui: &mut egui::Ui, name: &str, value: String| {
+            ui.label(name);
+            ui.label(value);
+            ui.end_row();
+        };
+        let bitrate = |bitrate: Option<link::Bitrate>| {
+            bitrate.map_or_else(|| String::from("–"), |bitrate| bitrate.to_string())
+        };
+        egui::Grid::new("connection")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                row(ui, "BSSID", station.bssid.clone());
+                if let Some(channel) = link_info.interface.channel {
+                    let mut text = format!("{} ({} MHz)", channel.number, channel.frequency);
+                    if let Some(width) = channel.width {
+                        text = format!("{}, {} MHz wide", text, width);
+                    }
+                    row(ui, "Channel", text);
+                }
+                let mut signal = station
+                    .signal
+                    .map_or_else(|| String::from("–"), |signal| format!("{} dBm", signal));
+                if let (Some(noise), Some(snr)) = (link_info.noise, link_info.snr()) {
+                    signal = format!("{}, noise {} dBm, SNR {} dB", signal, noise, snr);
+                }
+                row(ui, "Signal", signal);
+                row(ui, "TX bitrate", bitrate(station.tx_bitrate));
+                row(ui, "RX bitrate", bitrate(station.rx_bitrate));
+                if let Some(connected_time) = station.connected_time {
+                    row(ui, "Connected for", link::format_duration(connected_time));
+                }
+                row(
+                    ui,
+                    "Received",
+                    format!(
+                        "{} in {} packets",
+                        link::format_bytes(station.rx_bytes),
+                        station.rx_packets
+                    ),
+                );
+                row(
+                    ui,
+                    "Sent",
+                    format!(
+                        "{} in {} packets, {} retries, {} failed",
+                        link::format_bytes(station.tx_bytes),
+                        station.tx_packets,
+                        station.tx_retries,
+                        station.tx_failed
+                    ),
+                );
+                if let Some(txpower) = link_info.interface.txpower {
+                    row(ui, "TX power", format!("{:.0} dBm", txpower));
+                }
+                if let Some(ref addr) = link_info.interface.addr {
+                    row(ui, "MAC address", addr.clone());
+                }
+            });
+    }
+
+    /// Addresses, routes, DNS and the DHCP lease, refreshed while the section is open.
+    fn ip_config_ui(&mut self, ui: &mut egui::Ui) {
+        if self
+            .last_ip_config_refresh
+            .is_none_or(|last| last.elapsed() >= IP_CONFIG_REFRESH_INTERVAL)
+        {
+            self.app_state
+                .refresh_ip_config(&self.background_event_sender, &self.repaint);
+            self.last_ip_config_refresh = Some(Instant::now());
+        }
+        ui.ctx().request_repaint_after(IP_CONFIG_REFRESH_INTERVAL);
+
+        let ip_config = match self.app_state.ip_config {
+            Some(ref ip_config) => ip_config,
+            None => {
+                ui.spinner();
+                return;
+            }
+        };
+        let row = |ui: &mut egui::Ui, name: &str, value: String| {
+            ui.label(name);
+            ui.label(value);
+            ui.end_row();
+        };
+        let list = |addresses: &[IpAddr]| {
+            if addresses.is_empty() {
+                String::from("–")
+            } else {
+                addresses
+                    .iter()
+                    .map(|address| address.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+        };
+        egui::Grid::new("ip configuration")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                if ip_config.addresses.is_empty() {
+                    row(ui, "Addresses", String::from("–"));
+                }
+                for (i, address) in ip_config.addresses.iter().enumerate() {
+                    let mut text = format!("{}, {}", address, address.scope);
+                    if address.dynamic {
+                        text = format!("{}, dynamic", text);
+                    }
+                    if let Some(lifetime) = address.valid_lifetime {
+                        text = format!("{}, valid for {}", text, link::format_duration(lifetime));
+                    }
+                    row(ui, if i == 0 { "Addresses" } else { "" }, text);
+                }
+                row(ui, "Gateways", list(&ip_config.gateways));
+                row(
+                    ui,
+                    "DNS servers",
+                    format!("{} (from {})", list(&ip_config.dns), ip_config.dns_source),
+                );
+                match ip_config.lease {
+                    Some(ref lease) => {
+                        if let Some(server) = lease.server {
+                            row(ui, "DHCP server", server.to_string());
+                        }
+                        if let Some(lifetime) = lease.lifetime {
+                            row(ui, "Lease time", link::format_duration(lifetime));
+                        }
+                        if let Some(ref expires) = lease.expires {
+                            row(ui, "Lease expires", expires.clone());
+                        }
+                        if let Some(ref domain) = lease.domain {
+                            row(ui, "Domain", domain.clone());
+                        }
+                        row(ui, "Lease file", lease.path.display().to_string());
+                    }
+                    None => row(ui, "DHCP lease", String::from("not found")),
+                }
+            });
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    !self.app_state.renewing_lease,
+                    egui::Button::new("Renew lease"),
+                )
+                .clicked()
+            {
+                self.app_state
+                    .renew_lease(&self.background_event_sender, &self.repaint);
+            }
+            if self.app_state.renewing_lease {
+                ui.spinner();
+            }
+        });
+    }
+
+    /// Runs the connectivity checks on demand and shows how each of them went.
+    fn diagnostics_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!self.app_state.diagnosing, egui::Button::new("Diagnose"))
+                .clicked()
+            {
+                self.app_state
+                    .diagnose(&self.background_event_sender, &self.repaint);
+            }
+            if self.app_state.diagnosing {
+                ui.spinner();
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    !self.app_state.exporting_bundle,
+                    egui::Button::new("Export diagnostics"),
+                )
+                .on_hover_text("Logs, command output, state and driver info for support tickets")
+                .clicked()
+            {
+                self.app_state.export_bundle(
+                    self.anonymize_bundle,
+                    &self.background_event_sender,
+                    &self.repaint,
+                );
+            }
+            ui.checkbox(&mut self.anonymize_bundle, "Anonymize SSIDs and BSSIDs");
+            if self.app_state.exporting_bundle {
+                ui.spinner();
+            }
+        });
+        if let Some(ref path) = self.app_state.bundle_path {
+            ui.label(format!("Saved to {}", path.display()));
+        }
+        if self.app_state.diagnosis.is_empty() {
+            return;
+        }
+        egui::Grid::new("diagnosis")
+            .num_columns(4)
+            .striped(true)
+            .show(ui, |ui| {
+                for check in &self.app_state.diagnosis {
+                    let (symbol, color, detail) = match check.outcome {
+                        diagnose::Outcome::Passed(ref detail) => (
+                            "✔",
+                            egui::Color32::from_rgb(0x4c, 0xaf, 0x50),
+                            detail.as_str(),
+                        ),
+                        diagnose::Outcome::Failed(ref reason) => {
+                            ("✘", ui.visuals().error_fg_color, reason.as_str())
+                        }
+                        diagnose::Outcome::Skipped => {
+                            ("–", ui.visuals().weak_text_color(), "skipped")
+                        }
+                    };
+                    ui.colored_label(color, symbol);
+                    ui.label(check.stage.to_string());
+                    ui.label(detail);
+                    if check.outcome == diagnose::Outcome::Skipped {
+                        ui.label("");
+                    } else {
+                        ui.weak(format!("{} ms", check.duration.as_millis()));
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// Offers to open the login page, while a captive portal holds back the connection.
+    fn portal_ui(&mut self, ui: &mut egui::Ui) {
+        let url = match self.app_state.login_required() {
+            Some(url) => url.to_owned(),
+            None => return,
+        };
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            "Login required, this network has a captive portal",
+        );
+        ui.horizontal(|ui| {
+            if ui.button("Open login page").on_hover_text(&url).clicked() {
+                ui.ctx().open_url(egui::OpenUrl::new_tab(&url));
+            }
+            if ui
+                .add_enabled(
+                    !self.app_state.detecting_portal,
+                    egui::Button::new("Check again"),
+                )
+                .clicked()
+            {
+                self.app_state
+                    .check_portal_again(&self.background_event_sender, &self.repaint);
+            }
+            if self.app_state.detecting_portal {
+                ui.spinner();
+            }
+        });
+    }
+
+    /// Records the scans with a location and browses a recording by location.
+    fn survey_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Location");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.app_state.survey_location)
+                    .hint_text("room 3.14"),
+            )
+            .on_hover_text("Recorded with the following scans");
+            if ui.button("Scan now").clicked() {
+                self.app_state
+                    .reload(&self.background_event_sender, &self.repaint);
+                self.last_refresh = Instant::now();
+            }
+        });
+        ui.horizontal(|ui| match self.app_state.recorder {
+            Some(ref recorder) => {
+                ui.label(format!(
+                    "Recording to {}, {} scans so far",
+                    recorder.path.display(),
+                    recorder.samples
+                ));
+                if ui.button("Stop").clicked() {
+                    self.app_state.stop_recording();
+                }
+            }
+            None => {
+                ui.text_edit_singleline(&mut self.survey.record_path);
+                if ui.button("Record").clicked() {
+                    self.app_state
+                        .start_recording(Path::new(&self.survey.record_path));
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Floor plan");
+            ui.text_edit_singleline(&mut self.survey.floor_plan_path);
+            if ui.button("Open").clicked() {
+                match FloorPlan::open(ui.ctx(), &self.survey.floor_plan_path) {
+                    Ok(floor_plan) => self.survey.floor_plan = Some(floor_plan),
+                    Err(e) => {
+                        self.app_state.error = Some(format!(
+                            "Opening {} failed: {}",
+                            self.survey.floor_plan_path, e
+                        ))
+                    }
+                }
+            }
+        });
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.survey.load_path);
+            if ui.button("Load").clicked() {
+                match survey::Recording::load(Path::new(&self.survey.load_path)) {
+                    Ok(recording) => {
+                        self.survey.locations = Some(recording.locations());
+                        self.survey.selected_location = 0;
+                        self.app_state.measurements = recording
+                            .samples
+                            .into_iter()
+                            .filter(|sample| sample.position.is_some())
+                            .collect();
+                    }
+                    Err(e) => {
+                        self.app_state.error =
+                            Some(format!("Loading {} failed: {}", self.survey.load_path, e))
+                    }
+                }
+            }
+        });
+        let locations = match self.survey.locations {
+            Some(ref locations) if !locations.is_empty() => locations,
+            Some(_) => {
+                ui.label("The recording has no scans.");
+                return;
+            }
+            None => return,
+        };
+        let label = |location: &survey::Location| {
+            format!(
+                "{} ({} scans)",
+                location.label.as_deref().unwrap_or("no location"),
+                location.scans
+            )
+        };
+        let selected = &mut self.survey.selected_location;
+        egui::ComboBox::from_label("Location of the scans")
+            .selected_text(label(&locations[*selected]))
+            .show_ui(ui, |ui| {
+                for (i, location) in locations.iter().enumerate() {
+                    ui.selectable_value(selected, i, label(location));
+                }
+            });
+        let location = &locations[*selected];
+        egui::ScrollArea::vertical()
+            .id_source("survey networks")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                egui::Grid::new("survey")
+                    .num_columns(6)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for title in ["SSID", "BSSID", "Band", "Ch", "Seen", "Signal min/avg/max"] {
+                            ui.strong(title);
+                        }
+                        ui.end_row();
+                        for seen in &location.networks {
+                            ui.label(&seen.network.essid);
+                            ui.monospace(&seen.network.address);
+                            ui.label(seen.network.band().to_string());
+                            ui.label(seen.network.channel().to_string());
+                            ui.label(format!("{}/{}", seen.scans, location.scans));
+                            ui.label(format!("{}/{}/{}%", seen.min, seen.mean, seen.max));
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    /// Takes measurements where the floor plan is clicked and shows their heatmap.
+    fn floor_plan_window(&mut self, ctx: &egui::Context) {
+        let floor_plan = match self.survey.floor_plan {
+            Some(ref mut floor_plan) => floor_plan,
+            None => return,
+        };
+        let measurements = &self.app_state.measurements;
+        let targets = heatmap::Target::all(measurements);
+        if floor_plan
+            .target
+            .as_ref()
+            .is_none_or(|target| !targets.contains(target))
+        {
+            floor_plan.target = targets.first().cloned();
+        }
+        let mut open = true;
+        let mut measure_at = None;
+        egui::Window::new("Floor plan")
+            .open(&mut open)
+            .default_width(640.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let selected = floor_plan
+                        .target
+                        .as_ref()
+                        .map_or_else(|| String::from("no measurements"), |t| t.to_string());
+                    egui::ComboBox::from_label("Signal of")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            for target in targets {
+                                let label = target.to_string();
+                                ui.selectable_value(&mut floor_plan.target, Some(target), label);
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut floor_plan.export_path);
+                    let export = ui
+                        .add_enabled(floor_plan.target.is_some(), egui::Button::new("Export PNG"));
+                    if let (true, Some(target)) = (export.clicked(), &floor_plan.target) {
+                        let path = Path::new(&floor_plan.export_path);
+                        floor_plan.exported = Some(
+                            match heatmap::export(&floor_plan.image, measurements, target, path) {
+                                Ok(()) => format!("Saved to {}", path.display()),
+                                Err(e) => format!("Exporting failed: {}", e),
+                            },
+                        );
+                    }
+                });
+                if let Some(ref exported) = floor_plan.exported {
+                    ui.label(exported);
+                }
+                if self.app_state.survey_position.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Measuring…");
+                    });
+                } else {
+                    ui.label("Click where you are to take a measurement.");
+                }
+
+                let image_size = floor_plan.texture.size_vec2();
+                let scale = (ui.available_width() / image_size.x)
+                    .min(600.0 / image_size.y)
+                    .min(1.0);
+                let (rect, response) =
+                    ui.allocate_exact_size(image_size * scale, egui::Sense::click());
+                let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+                let painter = ui.painter_at(rect);
+                painter.image(floor_plan.texture.id(), rect, uv, egui::Color32::WHITE);
+                if let Some(heat) = floor_plan.heat(ui.ctx(), measurements) {
+                    painter.image(heat.id(), rect, uv, egui::Color32::WHITE);
+                }
+                let at = |[x, y]: [f32; 2]| rect.min + egui::vec2(x, y) * rect.size();
+                for position in measurements.iter().filter_map(|sample| sample.position) {
+                    painter.circle_filled(at(position), 4.0, egui::Color32::BLACK);
+                }
+                if let Some(position) = self.app_state.survey_position {
+                    painter.circle_stroke(
+                        at(position),
+                        6.0,
+                        egui::Stroke::new(2.0, egui::Color32::BLACK),
+                    );
+                }
+                if let Some(pointer) = response
+                    .clicked()
+                    .then(|| response.interact_pointer_pos())
+                    .flatten()
+                {
+                    let position = (pointer - rect.min) / rect.size();
+                    measure_at = Some([position.x, position.y]);
+                }
+            });
+        if let Some(position) = measure_at {
+            self.app_state
+                .measure_at(position, &self.background_event_sender, &self.repaint);
+            self.last_refresh = Instant::now();
+        }
+        if !open {
+            self.survey.floor_plan = None;
+        }
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        let settings = &mut self.app_state.settings;
+        let mut changed = false;
+
+        let mut theme = settings.theme();
+        egui::ComboBox::from_label("Theme")
+            .selected_text(theme.to_string())
+            .show_ui(ui, |ui| {
+                for t in Theme::ALL {
+                    ui.selectable_value(&mut theme, t, t.to_string());
+                }
+            });
+        if theme != settings.theme() {
+            settings.set_theme(theme);
+            ui.ctx().set_visuals(visuals(theme));
+            changed = true;
+        }
+
+        let mut refresh_interval = settings.refresh_interval().map_or(0, |i| i.as_secs());
+        ui.horizontal(|ui| {
+            ui.label("Rescan every");
+            if ui
+                .add(
+                    egui::DragValue::new(&mut refresh_interval)
+                        .clamp_range(0..=3600)
+                        .suffix(" s"),
+                )
+                .changed()
+            {
+                settings.set_refresh_interval(refresh_interval);
+                changed = true;
+            }
+            ui.label("(0 is off)");
+        });
+
+        ui.label("Notifications");
+        let notifications = &mut settings.notifications;
+        changed |= ui
+            .checkbox(&mut notifications.connection_lost, "Connection lost")
+            .changed();
+        changed |= ui
+            .checkbox(&mut notifications.reconnected, "Reconnected")
+            .changed();
+        changed |= ui
+            .checkbox(&mut notifications.connect_failed, "Connecting failed")
+            .changed();
+        changed |= ui
+            .checkbox(
+                &mut notifications.network_in_range,
+                "Saved network in range",
+            )
+            .changed();
+        changed |= ui
+            .checkbox(&mut notifications.login_required, "Login required")
+            .changed();
+        changed |= ui
+            .checkbox(
+                &mut settings.captive_portal.enabled,
+                "Detect captive portals after connecting",
+            )
+            .changed();
+
+        if changed {
+            self.app_state.settings.save();
+        }
+    }
+
+    /// Search and filters of the network list, they don't trigger a rescan.
+    fn filter_ui(&mut self, ui: &mut egui::Ui) {
+        let filter = &mut self.app_state.filter;
+        ui.horizontal_wrapped(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut filter.text)
+                    .hint_text("Search SSID, or BSSID")
+                    .desired_width(180.0),
+            );
+            for band in Band::ALL {
+                let mut shown = !filter.hidden_bands.contains(&band);
+                if ui.checkbox(&mut shown, band.to_string()).changed() {
+                    if shown {
+                        filter.hidden_bands.remove(&band);
+                    } else {
+                        filter.hidden_bands.insert(band);
+                    }
+                }
+            }
+            ui.checkbox(&mut filter.hide_open, "Hide open");
+            ui.checkbox(&mut filter.only_saved, "Only saved");
+        });
+    }
+
+    fn networks_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.strong("Networks");
+            if ui.button("refresh").clicked() {
+                self.app_state
+                    .refresh(&self.background_event_sender, &self.repaint);
+                self.last_refresh = Instant::now();
+            }
+            if ui
+                .add_enabled(
+                    self.app_state.selected_network().is_some(),
+                    egui::Button::new("Connect…"),
+                )
+                .clicked()
+            {
+                self.app_state.open_connect_dialog();
+            }
+            if ui.button("Export…").clicked() {
+                self.app_state.export_dialog =
+                    Some(ExportDialog::new(&self.app_state.selected_wlan_interface));
+            }
+        });
+        self.filter_ui(ui);
+
+        let focus = self.focus_networks
+            && self
+                .app_state
+                .wlan_networks
+                .as_ref()
+                .is_some_and(|networks| !networks.is_empty());
+        if focus {
+            self.focus_networks = false;
+        }
+        match table::network_table(ui, &self.app_state, focus) {
+            Some(TableAction::Select(id)) => self.app_state.selected_wlan_network = id,
+            Some(TableAction::Sort(column)) => {
+                let sort = self.app_state.settings.sort.toggle(column);
+                self.app_state.set_sort(sort);
+                self.app_state.settings.save();
+            }
+            Some(TableAction::Connect(id)) => {
+                self.app_state.selected_wlan_network = id;
+                self.app_state.open_connect_dialog();
+            }
+            None => {}
+        }
+    }
+
+    /// Hooks and DNS changes from swelfi.toml, the newest first.
+    fn rules_log_ui(&mut self, ui: &mut egui::Ui) {
+        if self.app_state.rules_log.is_empty() {
+            ui.label("Nothing happened yet");
+            return;
+        }
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for entry in self.app_state.rules_log.iter().rev() {
+                    let title = format!("{} {} - {}", ago(entry.time), entry.ssid, entry.title);
+                    if entry.success {
+                        ui.label(title);
+                    } else {
+                        ui.colored_label(ui.visuals().error_fg_color, title);
+                    }
+                    if !entry.output.is_empty() {
+                        ui.label(egui::RichText::new(&entry.output).monospace());
+                    }
+                }
+            });
+        if ui.button("Clear").clicked() {
+            self.app_state.rules_log.clear();
+        }
+    }
+
+    /// Runs the actions picked in the tray and hides the window to the tray, instead of quitting.
+    fn handle_tray(&mut self, ctx: &egui::Context) {
+        let commands: Vec<tray::TrayCommand> = match self.tray {
+            Some(ref tray) => tray.commands.try_iter().collect(),
+            None => return,
+        };
+        for command in commands {
+            match command {
+                tray::TrayCommand::Show => show_window(ctx),
+                tray::TrayCommand::ToggleRadio => {
+                    self.app_state
+                        .toggle_wlan(&self.background_event_sender, &self.repaint);
+                }
+                tray::TrayCommand::Connect(ssid) => self.connect_from_tray(ctx, &ssid),
+                tray::TrayCommand::Quit => {
+                    self.quitting = true;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            }
+        }
+
+        if ctx.input(|i| i.viewport().close_requested()) && !self.quitting {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+        }
+        if let Some(ref mut tray) = self.tray {
+            tray.update(&self.app_state);
+        }
+    }
+
+    /// Open networks are connected to right away, for the others the window is shown to
+    /// enter the passphrase.
+    fn connect_from_tray(&mut self, ctx: &egui::Context, ssid: &str) {
+        if self.app_state.connected_wlan_network.as_deref() == Some(ssid) {
+            return;
+        }
+        let id = match self
+            .app_state
+            .wlan_networks
+            .as_ref()
+            .and_then(|networks| networks.iter().find(|wn| wn.essid == ssid))
+        {
+            Some(wn) => wn.id(),
+            None => return,
+        };
+        self.app_state.selected_wlan_network = id;
+        self.app_state.open_connect_dialog();
+        if self
+            .app_state
+            .connect_dialog
+            .as_ref()
+            .is_some_and(|dialog| dialog.secured)
+        {
+            show_window(ctx);
+        } else {
+            self.app_state
+                .connect(&self.background_event_sender, &self.repaint);
+        }
+    }
+
+    fn export_window(&mut self, ctx: &egui::Context) {
+        let mut dialog = match self.app_state.export_dialog.take() {
+            Some(dialog) => dialog,
+            None => return,
+        };
+        let mut open = true;
+        let mut close = false;
+        egui::Window::new("Export")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for content in export::Content::ALL {
+                        let default_path = dialog.default_path();
+                        if ui
+                            .selectable_value(&mut dialog.content, content, content.to_string())
+                            .changed()
+                            && dialog.path == default_path
+                        {
+                            dialog.path = dialog.default_path();
+                        }
+                    }
+                });
+                egui::ComboBox::from_label("Format")
+                    .selected_text(dialog.format.to_string())
+                    .show_ui(ui, |ui| {
+                        for format in export::Format::ALL {
+                            if ui
+                                .selectable_value(&mut dialog.format, format, format.to_string())
+                                .changed()
+                            {
+                                dialog.path = std::path::Path::new(&dialog.path)
+                                    .with_extension(format.extension())
+                                    .to_string_lossy()
+                                    .into_owned();
+                            }
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    ui.label("File");
+                    ui.text_edit_singleline(&mut dialog.path);
+                });
+                if ui.button("Save").clicked() {
+                    match self
+                        .app_state
+                        .export(dialog.content, dialog.format, &dialog.path)
+                    {
+                        Ok(_) => {
+                            log::info!("exported {} to {}", dialog.content, dialog.path);
+                            self.app_state.error = None;
+                            close = true;
+                        }
+                        Err(e) => {
+                            log::error!("Error while exporting {}: {}", dialog.content, e);
+                            self.app_state.error = Some(format!("Export failed: {}", e));
+                        }
+                    }
+                }
+            });
+        if open && !close {
+            self.app_state.export_dialog = Some(dialog);
+        }
+    }
+
+    fn connect_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.app_state.connect_dialog.is_some();
+        let mut connect = false;
+        if let Some(ref mut dialog) = self.app_state.connect_dialog {
+            egui::Window::new(format!("Connect to {}", dialog.ssid))
+                .open(&mut open)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    if dialog.secured {
+                        ui.horizontal(|ui| {
+                            ui.label("Passphrase");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut dialog.passphrase).password(true),
+                            );
+                        });
+                    }
+                    connect = ui.button("Connect").clicked();
+                });
+        }
+        if connect {
+            self.app_state
+                .connect(&self.background_event_sender, &self.repaint);
+        } else if !open {
+            self.app_state.connect_dialog = None;
         }
     }
 }
@@ -121,19 +1243,13 @@ impl eframe::App for SwelfiApp {
             .frame_history
             .on_new_frame(ctx.input(|i| i.time), frame.info().cpu_usage);
         while let Ok(event) = self.event_receiver.try_recv() {
-            if let Event::UpdateNetworks(networks) = event {
-                if let Ok(connected_wlan_network) =
-                    get_connected_network_ssid(&self.app_state.selected_wlan_interface)
-                {
-                    self.app_state.connected_wlan_network = connected_wlan_network;
-                }
-                if let Some(ref networks) = networks {
-                    if !networks.is_empty() {
-                        self.app_state.selected_wlan_network = networks[0].id()
-                    };
-                }
-                self.app_state.wlan_networks = networks;
-            }
+            self.app_state
+                .handle_event(event, &self.background_event_sender, &self.repaint);
+        }
+        self.handle_tray(ctx);
+        self.auto_refresh(ctx);
+        if let Some(rect) = ctx.input(|i| i.viewport().inner_rect) {
+            self.app_state.settings.window_size = Some([rect.width(), rect.height()]);
         }
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("Swelfi");
@@ -142,6 +1258,10 @@ impl eframe::App for SwelfiApp {
                 "Mean CPU usage: {:.2} ms / frame",
                 1e3 * self.app_state.frame_history.mean_frame_time()
             ));
+            if self.app_state.wlan_interfaces.is_empty() {
+                ui.label("No wlan interface found, plug in a wireless adapter to get started.");
+                return;
+            }
             egui::Grid::new("structure")
                 .num_columns(2)
                 .spacing([20.0, 20.0])
@@ -152,183 +1272,203 @@ impl eframe::App for SwelfiApp {
                         .min_col_width(80.0)
                         .show(ui, |ui| {
                             ui.add(egui::Label::new("WLAN Interface"));
+                            let mut selected_wlan_interface =
+                                self.app_state.selected_wlan_interface.clone();
                             egui::ComboBox::from_id_source("wlan interfaces")
                                 .selected_text(&self.app_state.selected_wlan_interface)
                                 .show_ui(ui, |ui| {
                                     self.app_state.wlan_interfaces.iter().for_each(|wi| {
                                         ui.selectable_value(
-                                            &mut self.app_state.selected_wlan_interface,
+                                            &mut selected_wlan_interface,
                                             wi.name.clone(),
                                             wi.name.clone(),
                                         );
                                     });
                                 });
-                            ui.horizontal(|ui| {
-                                ui.add(egui::Label::new("On"));
-                                ui.add(toggle(
-                                    &mut self.app_state,
-                                    self.background_event_sender.clone(),
-                                    ctx.clone(),
-                                ));
-                                ui.add(egui::Label::new("Off"));
-                            });
-                            ui.end_row();
-
-                            ui.with_layout(egui::Layout::top_down(egui::Align::TOP), |ui| {
-                                ui.add(egui::Label::new("Networks"));
-                                if ui.button("refresh").clicked() {
-                                    self.background_event_sender
-                                        .send(Event::RefreshNetworks(
-                                            ctx.clone(),
-                                            self.app_state.selected_wlan_interface.clone(),
-                                            None,
-                                        ))
-                                        .expect("can send on channel");
-                                }
-                            });
+                            self.app_state.change_wlan_interface(
+                                &selected_wlan_interface,
+                                &self.background_event_sender,
+                                &self.repaint,
+                            );
                             ui.vertical(|ui| {
-                                ui.set_width(250.0);
-                                let connected_wlan_network = &self.app_state.connected_wlan_network;
-                                if let Some(ref networks) = self.app_state.wlan_networks {
-                                    networks.iter().for_each(|wn| {
-                                        let mut id = wn.id();
-                                        if let Some(connected_wlan_network) =
-                                            &connected_wlan_network
-                                        {
-                                            if wn.essid == *connected_wlan_network {
-                                                id = format!("{} - connected", id);
-                                            }
-                                        }
-                                        ui.selectable_value(
-                                            &mut self.app_state.selected_wlan_network,
-                                            id.clone(),
-                                            id,
+                                let hard_blocked = self
+                                    .app_state
+                                    .interface_status
+                                    .as_ref()
+                                    .is_some_and(|status| status.rfkill.hard_blocked);
+                                ui.horizontal(|ui| {
+                                    ui.add(egui::Label::new("On"));
+                                    ui.add_enabled(
+                                        !hard_blocked,
+                                        toggle(
+                                            &mut self.app_state,
+                                            &self.background_event_sender,
+                                            &self.repaint,
+                                        ),
+                                    );
+                                    ui.add(egui::Label::new("Off"));
+                                });
+                                if let Some(ref status) = self.app_state.interface_status {
+                                    ui.label(format!("Link state: {}", status.oper_state));
+                                    if status.rfkill.hard_blocked {
+                                        ui.label(
+                                            "Wifi is switched off by a hardware switch, \
+                                             or key and can't be turned on from here",
                                         );
-                                    });
-                                } else {
-                                    ui.spinner();
+                                    } else if status.rfkill.soft_blocked {
+                                        ui.label("Blocked by rfkill");
+                                    }
+                                }
+                                if !self.app_state.rfkill_devices.is_empty() {
+                                    let mut airplane_mode = self.app_state.airplane_mode();
+                                    if ui.checkbox(&mut airplane_mode, "Airplane mode").changed() {
+                                        self.app_state.set_airplane_mode(
+                                            airplane_mode,
+                                            &self.background_event_sender,
+                                            &self.repaint,
+                                        );
+                                    }
+                                }
+                                if let Some(ref ssid) = self.app_state.connecting {
+                                    ui.label(format!("Connecting to {}…", ssid));
+                                }
+                                self.portal_ui(ui);
+                                if let Some(ref error) = self.app_state.error {
+                                    ui.colored_label(ui.visuals().error_fg_color, error);
                                 }
                             });
                             ui.end_row();
                         });
                 });
+            ui.add_space(20.0);
+            self.networks_ui(ui);
+            ui.collapsing("Connection", |ui| self.connection_ui(ui));
+            ui.collapsing("IP configuration", |ui| self.ip_config_ui(ui));
+            ui.collapsing("Diagnostics", |ui| self.diagnostics_ui(ui));
+            ui.collapsing("Site survey", |ui| self.survey_ui(ui));
+            ui.collapsing("Settings", |ui| self.settings_ui(ui));
+            if self.app_state.rules.is_some() {
+                ui.collapsing("Rules log", |ui| self.rules_log_ui(ui));
+            }
         });
+        self.export_window(ctx);
+        self.connect_window(ctx);
+        self.floor_plan_window(ctx);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.app_state.settings.save();
     }
 }
 
 fn main() -> Result<()> {
-    env_logger::init();
+    logs::init();
 
-    // spawn thread for background actions
+    let (command, source) = match cli::parse(std::env::args().skip(1)) {
+        Ok(parsed) => parsed,
+        Err(e) => std::process::exit(cli::usage_error(&e)),
+    };
+    if let Err(e) = backend::install(source) {
+        std::process::exit(cli::error(&e));
+    }
+    match command {
+        cli::Command::Gui {
+            focus_networks,
+            overrides,
+        } => run_gui(focus_networks, overrides),
+        cli::Command::Helper => helper::serve(std::path::Path::new(helper::SOCKET_PATH)),
+        cli::Command::Tui { overrides } => tui::run(overrides),
+        cli::Command::Daemon => daemon::serve(&daemon::socket_path()),
+        command => std::process::exit(cli::run(command)),
+    }
+}
+
+fn run_gui(focus_networks: bool, overrides: settings::Overrides) -> Result<()> {
+    let mut settings = Settings::load();
+    settings.overrides = overrides;
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_always_on_top()
-            .with_inner_size([640.0, 480.0]),
+            .with_inner_size(settings.window_size.unwrap_or([640.0, 480.0])),
         ..Default::default()
     };
     let (background_event_sender, background_event_receiver) = channel::<Event>();
     let (event_sender, event_receiver) = channel::<Event>();
 
-    std::thread::spawn(move || {
-        while let Ok(event) = background_event_receiver.recv() {
-            if let Event::RefreshNetworks(ctx, selected_wlan_interface, wait_time) = event {
-                event_sender
-                    .send(Event::UpdateNetworks(None))
-                    .expect("can send on channel");
-
-                if let Some(wait) = wait_time {
-                    std::thread::sleep(wait);
-                }
+    // spawn thread for background actions
+    app::spawn_background_thread(background_event_receiver, event_sender.clone());
 
-                match scan_for_networks(&selected_wlan_interface) {
-                    Ok(networks) => {
-                        event_sender
-                            .send(Event::UpdateNetworks(Some(networks)))
-                            .expect("can send on channel");
-                        ctx.request_repaint();
-                    }
-                    Err(e) => log::error!("Error while scanning for networks: {}", e),
-                }
-            }
+    let mut app_state = AppState::load(settings)?;
+    app_state.notifier = Some(notify::spawn());
+    match rules::Rules::load() {
+        Ok(rules) => app_state.rules = Some(rules),
+        Err(e) => {
+            log::error!("Error while loading rules: {}", e);
+            app_state.error = Some(e.to_string());
         }
-    });
-
-    let wlan_interfaces = iw()?;
-    if wlan_interfaces.is_empty() {
-        panic!("There is no wlan interface")
-    }
-    let selected_wlan_interface = wlan_interfaces[0].name.clone(); // this is safe, because we
-                                                                   // check for at least one
-                                                                   // interface above
-
-    let selected_wlan_network = String::new();
-    let connected_wlan_network = get_connected_network_ssid(&selected_wlan_interface)?;
-    let app_state = AppState {
-        wlan_interfaces,
-        selected_wlan_interface,
-        wlan_networks: None,
-        selected_wlan_network,
-        connected_wlan_network,
-        wlan_on: true,
-        frame_history: fps::FrameHistory::default(),
-    };
+    }
 
     eframe::run_native(
         "Swelfi",
         options,
-        Box::new(|context| {
+        Box::new(move |context| {
+            context
+                .egui_ctx
+                .set_visuals(visuals(app_state.settings.theme()));
+            let repaint = Repaint::from(context.egui_ctx.clone());
+            app::watch_system(&event_sender, &repaint, &app_state.wlan_interfaces);
             Box::new(SwelfiApp::new(
-                context,
+                repaint,
                 app_state,
                 background_event_sender,
                 event_receiver,
+                focus_networks,
             ))
         }),
     )
     .map_err(|e| anyhow!("eframe error: {}", e))
 }
 
-fn toggle(
-    app_state: &mut AppState,
-    background_sender: Sender<Event>,
-    ctx: egui::Context,
-) -> impl egui::Widget + '_ {
-    move |ui: &mut egui::Ui| toggle_ui(ui, app_state, background_sender, ctx)
+/// How long ago the time was, roughly.
+fn ago(time: SystemTime) -> String {
+    let secs = time.elapsed().map_or(0, |elapsed| elapsed.as_secs());
+    match secs {
+        0..=59 => format!("{}s ago", secs),
+        60..=3599 => format!("{}m ago", secs / 60),
+        _ => format!("{}h ago", secs / 3600),
+    }
+}
+
+fn visuals(theme: Theme) -> egui::Visuals {
+    match theme {
+        Theme::Dark => egui::Visuals::dark(),
+        Theme::Light => egui::Visuals::light(),
+    }
+}
+
+fn show_window(ctx: &egui::Context) {
+    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+}
+
+fn toggle<'a>(
+    app_state: &'a mut AppState,
+    background_sender: &'a Sender<Event>,
+    repaint: &'a Repaint,
+) -> impl egui::Widget + 'a {
+    move |ui: &mut egui::Ui| toggle_ui(ui, app_state, background_sender, repaint)
 }
 
 // custom toggle from egui examples
 fn toggle_ui(
     ui: &mut egui::Ui,
     app_state: &mut AppState,
-    background_sender: Sender<Event>,
-    ctx: egui::Context,
+    background_sender: &Sender<Event>,
+    repaint: &Repaint,
 ) -> egui::Response {
     let desired_size = ui.spacing().interact_size.y * egui::vec2(2.0, 1.0);
     let (rect, mut response) = ui.allocate_exact_size(desired_size, egui::Sense::click());
-    if response.clicked() {
-        // set new value
-        app_state.wlan_on = !app_state.wlan_on;
-
-        // if we set the interface to off, we clear the list
-        if !app_state.wlan_on {
-            app_state.wlan_networks = Some(vec![]);
-        }
+    if response.clicked() && app_state.toggle_wlan(background_sender, repaint) {
         response.mark_changed();
-        match switch_wlan_interface(&app_state.selected_wlan_interface, app_state.wlan_on) {
-            Ok(_) => {
-                if app_state.wlan_on {
-                    background_sender
-                        .send(Event::RefreshNetworks(
-                            ctx,
-                            app_state.selected_wlan_interface.to_owned(),
-                            Some(Duration::from_millis(1000)), // wait for interface to come up
-                                                               // before scanning
-                        ))
-                        .expect("can send on channel");
-                }
-            }
-            Err(e) => log::error!("Error while switching wifi on, or off: {}", e),
-        }
     }
     let on = app_state.wlan_on;
 
@@ -349,58 +1489,134 @@ fn toggle_ui(
 }
 
 fn scan_for_networks(interface: &str) -> Result<Vec<WirelessNetwork>> {
-    let output = Command::new("sudo")
-        .args(["iwlist", interface, "s"])
-        .output()?;
-    if !output.status.success() {
-        return Err(anyhow!("getting wireless interfaces using 'iwlist' failed"));
-    }
-
-    std::str::from_utf8(&output.stdout)
-        .map(|out_str| {
-            parse_nw(out_str)
-                .map(|(_, mut wlan_networks)| {
-                    wlan_networks.sort_by(|wn1, wn2| wn1.essid.cmp(&wn2.essid));
-                    wlan_networks.dedup_by(|wn1, wn2| wn1.essid.eq_ignore_ascii_case(&wn2.essid));
-                    wlan_networks.sort_by(|wn1, wn2| wn2.quality.value.cmp(&wn1.quality.value));
-                    wlan_networks
-                        .into_iter()
-                        .filter(|wn| !wn.essid.is_empty())
-                        .collect()
-                })
-                .map_err(|e| anyhow!("parsing 'iwlist' output failed: {}", e))
+    parse_scan(&backend::get().scan(interface)?)
+}
+
+fn scan_for_access_points(interface: &str) -> Result<Vec<WirelessNetwork>> {
+    parse_access_points(&backend::get().scan(interface)?)
+}
+
+/// The networks in the output of `iwlist <interface> scan`.
+fn parse_scan(output: &str) -> Result<Vec<WirelessNetwork>> {
+    let mut wlan_networks = parse_access_points(output)?;
+    group_access_points(&mut wlan_networks);
+    Ok(wlan_networks)
+}
+
+/// Every access point with an SSID in the output of `iwlist <interface> scan`, not grouped.
+fn parse_access_points(output: &str) -> Result<Vec<WirelessNetwork>> {
+    parse_nw(output)
+        .map(|(_, wlan_networks)| {
+            wlan_networks
+                .into_iter()
+                .filter(|wn| !wn.essid.is_empty())
+                .collect()
         })
-        .map_err(|_| anyhow!("output of 'iwlist' wasn't valid utf-8"))?
+        .map_err(|e| SwelfiError::Parse(format!("parsing 'iwlist' output failed: {}", e)).into())
+}
+
+/// Keeps the strongest access point of every SSID and counts the others, sorted by signal.
+fn group_access_points(wlan_networks: &mut Vec<WirelessNetwork>) {
+    wlan_networks.sort_by_key(|wn| (wn.essid.to_lowercase(), std::cmp::Reverse(wn.quality.value)));
+    wlan_networks.dedup_by(|wn, strongest| {
+        let same = wn.essid.eq_ignore_ascii_case(&strongest.essid);
+        if same {
+            strongest.access_points += wn.access_points;
+        }
+        same
+    });
+    wlan_networks.sort_by_key(|wn| std::cmp::Reverse(wn.quality.value));
 }
 
-fn get_connected_network_ssid(selected_interface: &str) -> Result<Option<String>> {
-    let output = Command::new("iw")
-        .args(["dev", selected_interface, "info"])
-        .output()?;
+fn get_wireless_interface(selected_interface: &str) -> Result<WirelessInterface> {
+    wireless_interface(backend::get(), selected_interface)
+}
 
-    if !output.status.success() {
-        return Err(anyhow!(
-            "getting wireless interface info for {} using 'iw' failed",
-            selected_interface
-        ));
-    }
+fn wireless_interface(
+    backend: &dyn Backend,
+    selected_interface: &str,
+) -> Result<WirelessInterface> {
+    let output = backend.iw_info(selected_interface)?;
+    interface(&output)
+        .map(|(_, wlan_interface)| wlan_interface)
+        .map_err(|e| SwelfiError::Parse(format!("parsing 'iw' output failed: {}", e)).into())
+}
+
+fn get_interface_status(selected_interface: &str) -> Result<InterfaceStatus> {
+    interface_status(backend::get(), selected_interface)
+}
 
-    std::str::from_utf8(&output.stdout)
-        .map(|out_str| {
-            interface(out_str)
-                .map(|(_, wlan_interface)| wlan_interface.connected_ssid)
-                .map_err(|e| anyhow!("parsing 'iw' output failed: {}", e))
+/// The state of the interface, as the backend sees it.
+fn interface_status(backend: &dyn Backend, selected_interface: &str) -> Result<InterfaceStatus> {
+    let output = backend.ip_link(selected_interface)?;
+    let (up, oper_state) = link_state(&output)
+        .map(|(_, (flags, oper_state))| {
+            (
+                flags.split(',').any(|flag| flag == "UP"),
+                oper_state.to_owned(),
+            )
         })
-        .map_err(|_| anyhow!("output of 'iw' wasn't valid utf-8"))?
+        .map_err(|e| SwelfiError::Parse(format!("parsing 'ip' output failed: {}", e)))?;
+
+    let rfkill = backend.rfkill(selected_interface)?;
+
+    let connected_ssid = if up {
+        wireless_interface(backend, selected_interface)?.connected_ssid
+    } else {
+        None
+    };
+
+    Ok(InterfaceStatus {
+        up,
+        oper_state,
+        rfkill,
+        connected_ssid,
+    })
 }
 
-fn switch_wlan_interface(interface: &str, on: bool) -> Result<()> {
-    let on_off = if on { "up" } else { "down" };
+/// Switches the radio using the rfkill switch of the interface's phy, if there is one, otherwise
+/// the link is brought up, or down.
+fn switch_wlan_interface(
+    interface: &str,
+    on: bool,
+    status: Option<&InterfaceStatus>,
+) -> Result<()> {
+    if let Some(status) = status {
+        if let Some(index) = status.rfkill.index {
+            backend::get().set_rfkill(index, !on)?;
+            // the link might have been brought down separately, so bring it back up as well
+            if on && !status.up {
+                set_link(interface, true)?;
+            }
+            return Ok(());
+        }
+    }
+    set_link(interface, on)
+}
+
+fn set_link(interface: &str, on: bool) -> Result<()> {
+    backend::get().set_link(interface, on)
+}
+
+fn link_state(input: &str) -> IResult<&str, (&str, &str)> {
+    tuple((
+        take_until::<_, _, nom::error::Error<_>>("<"),
+        delimited(tag("<"), take_until(">"), tag(">")),
+        take_until(STATE),
+        tag(STATE),
+        take_while(|c: char| !c.is_whitespace()),
+    ))(input)
+    .map(|(inp, (_, flags, _, _, oper_state))| Ok((inp, (flags, oper_state))))?
+}
 
-    Command::new("sudo")
-        .args(["ip", "link", "set", interface, on_off])
-        .output()?;
-    Ok(())
+fn link_name(input: &str) -> IResult<&str, &str> {
+    tuple((
+        opt(tag("Deleted ")),
+        digit1,
+        tag(": "),
+        take_while(|c| c != ':' && c != '@'),
+    ))(input)
+    .map(|(inp, (_, _, _, name))| Ok((inp, name)))?
 }
 
 fn parse_nw(input: &str) -> IResult<&str, Vec<WirelessNetwork>> {
@@ -408,11 +1624,12 @@ fn parse_nw(input: &str) -> IResult<&str, Vec<WirelessNetwork>> {
 }
 
 fn cell(input: &str) -> IResult<&str, WirelessNetwork> {
-    let (input, (address, frequency, quality, essid, security_type)) = tuple((
+    let (input, (address, frequency, quality, essid, last_beacon, security_type)) = tuple((
         network_address,
         network_frequency,
         network_quality,
         network_essid,
+        network_last_beacon,
         network_security_type,
     ))(input)?;
 
@@ -433,6 +1650,8 @@ fn cell(input: &str) -> IResult<&str, WirelessNetwork> {
             security_type,
             frequency,
             quality,
+            last_beacon,
+            access_points: 1,
         },
     ))
 }
@@ -489,28 +1708,44 @@ fn network_essid(input: &str) -> IResult<&str, &str> {
     .map(|(inp, (_, _, essid))| Ok((inp, essid)))?
 }
 
+/// Not every driver reports the last beacon, so it's only looked for up to the next cell,
+/// without consuming the input.
+fn network_last_beacon(input: &str) -> IResult<&str, Option<u64>> {
+    let cell_end = input.find(CELL).unwrap_or(input.len());
+    let last_beacon = input[..cell_end].find(LAST_BEACON).and_then(|start| {
+        let (_, (_, ms, _)) = tuple((
+            tag::<_, _, nom::error::Error<_>>(LAST_BEACON),
+            digit1,
+            tag("ms ago"),
+        ))(&input[start..])
+        .ok()?;
+        ms.parse::<u64>().ok()
+    });
+    Ok((input, last_beacon))
+}
+
+/// Open networks have no IE about it, so the next cell isn't looked at.
 fn network_security_type(input: &str) -> IResult<&str, SecurityType> {
-    tuple((
+    let end = input.find(CELL).unwrap_or(input.len());
+    match tuple((
         take_until::<_, _, nom::error::Error<_>>(IEEE),
         tag(IEEE),
         not_line_ending,
-    ))(input)
-    .map(|(inp, (_, _, security_type_line))| Ok((inp, SecurityType::from(security_type_line))))?
+    ))(&input[..end])
+    {
+        Ok((rest, (_, _, security_type_line))) => Ok((
+            &input[end - rest.len()..],
+            SecurityType::from(security_type_line),
+        )),
+        Err(_) => Ok((&input[end..], SecurityType::Invalid)),
+    }
 }
 
 fn iw() -> Result<Vec<WirelessInterface>> {
-    let output = Command::new("iw").args(["dev"]).output()?;
-    if !output.status.success() {
-        return Err(anyhow!("getting wireless interfaces using 'iw' failed"));
-    }
-
-    std::str::from_utf8(&output.stdout)
-        .map(|out_str| {
-            parse_iw(out_str)
-                .map(|(_, wlan_interfaces)| wlan_interfaces)
-                .map_err(|e| anyhow!("parsing 'iw' output failed: {}", e))
-        })
-        .map_err(|_| anyhow!("output of 'iw' wasn't valid utf-8"))?
+    let output = backend::get().iw_dev()?;
+    parse_iw(&output)
+        .map(|(_, wlan_interfaces)| wlan_interfaces)
+        .map_err(|e| SwelfiError::Parse(format!("parsing 'iw' output failed: {}", e)).into())
 }
 
 fn parse_iw(input: &str) -> IResult<&str, Vec<WirelessInterface>> {
@@ -518,19 +1753,50 @@ fn parse_iw(input: &str) -> IResult<&str, Vec<WirelessInterface>> {
 }
 
 fn interface(input: &str) -> IResult<&str, WirelessInterface> {
-    let (input, (_, _, interface)) =
+    let (input, (_, _, name)) =
         tuple((take_until(INTERFACE), tag(INTERFACE), take_until("\n")))(input)?;
-    let (input, optional_connected_ssid) =
-        opt(tuple((take_until(SSID), tag(SSID), take_until("\n"))))(input)?;
-    let mut connected_ssid = None;
-    if let Some((_, _, ssid)) = optional_connected_ssid {
-        connected_ssid = Some(ssid);
+    // the attributes are indented below the interface, up to the next interface, or phy
+    let (input, lines) = many0(preceded(
+        line_ending,
+        preceded(tag("\t\t"), not_line_ending),
+    ))(input)?;
+    let mut wlan_interface = WirelessInterface {
+        name: name.to_owned(),
+        ..WirelessInterface::default()
+    };
+    for line in lines {
+        match line.split_once(' ') {
+            Some(("ssid", ssid)) => wlan_interface.connected_ssid = Some(ssid.to_owned()),
+            Some(("addr", addr)) => wlan_interface.addr = Some(addr.to_owned()),
+            Some(("type", iftype)) => wlan_interface.iftype = Some(iftype.to_owned()),
+            Some(("channel", _)) => {
+                wlan_interface.channel = channel_info(line).ok().map(|(_, c)| c)
+            }
+            Some(("txpower", txpower)) => {
+                wlan_interface.txpower = txpower.trim_end_matches(" dBm").parse().ok()
+            }
+            _ => (),
+        }
     }
+    Ok((input, wlan_interface))
+}
+
+/// `channel 100 (5500 MHz), width: 80 MHz, center1: 5530 MHz`
+fn channel_info(input: &str) -> IResult<&str, ChannelInfo> {
+    let (input, (_, number, _, frequency, _, width)) = tuple((
+        tag("channel "),
+        number,
+        tag(" ("),
+        number,
+        tag(" MHz)"),
+        opt(preceded(tag(", width: "), number)),
+    ))(input)?;
     Ok((
         input,
-        WirelessInterface {
-            name: interface.to_owned(),
-            connected_ssid: connected_ssid.map(|cs| cs.to_owned()),
+        ChannelInfo {
+            number,
+            frequency,
+            width,
         },
     ))
 }
@@ -539,6 +1805,61 @@ fn interface(input: &str) -> IResult<&str, WirelessInterface> {
 mod tests {
     use super::*;
 
+    pub const VALID_NETWORK: &str = "Cell 09 - Address: D4:1A:D1:51:67:F2
+                    Channel:6
+                    Frequency:2.437 GHz (Channel 6)
+                    Quality=42/70  Signal level=-68 dBm
+                    Encryption key:on
+                    ESSID:\"some network\"
+                    Bit Rates:1 Mb/s; 2 Mb/s; 5.5 Mb/s; 11 Mb/s; 18 Mb/s
+                              24 Mb/s; 36 Mb/s; 54 Mb/s
+                    Bit Rates:6 Mb/s; 9 Mb/s; 12 Mb/s; 48 Mb/s
+                    Mode:Master
+                    Extra:tsf=00000052cabe36b9
+                    Extra: Last beacon: 2216ms ago
+                    IE: Unknown: 00086D696E6B616E6574
+                    IE: Unknown: 010882848B962430486C
+                    IE: Unknown: 030106
+                    IE: Unknown: 0706415420010D14
+                    IE: Unknown: 200100
+                    IE: Unknown: 23021000
+                    IE: Unknown: 2A0104
+                    IE: Unknown: 32040C121860
+                    IE: IEEE 802.11i/WPA2 Version 1
+                        Group Cipher : CCMP
+                        Pairwise Ciphers (1) : CCMP
+                        Authentication Suites (1) : PSK
+                    IE: Unknown: 0B050000130000
+                    IE: Unknown: 2D1ABC091BFFFF000000000000000000000000000000000000000000
+                    IE: Unknown: 3D1606080000000000000000000000000000000000000000
+                    IE: Unknown: 7F080400080000000040
+                    IE: Unknown: DD880050F204104A0001101044000102103B00010310470010F1C8F0ECA8220A216584CCEC11054672102100055A5958454C102300094458333130312D4230102400094458333130312D423010420004313233341054000800060050F2040001101100114458333130312D4230205A7958454C4150100800022008103C0001031049000600372A000120
+                    IE: Unknown: DD090010180200000C0000
+                    IE: Unknown: DD180050F2020101840003A4000027A4000042435E0062322F00";
+
+    /// A local stand-in for an HTTP server, which answers a single request with `response`.
+    /// Returns the URL to request.
+    pub fn serve_http(response: &str) -> String {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/generate_204", listener.local_addr().unwrap());
+        let response = response.to_owned();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![];
+            let mut buffer = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                match stream.read(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => request.extend_from_slice(&buffer[..n]),
+                }
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        url
+    }
+
     #[test]
     fn valid_interface() {
         let input = "phy#0
@@ -554,7 +1875,10 @@ mod tests {
             parse_iw(input).unwrap().1,
             vec![WirelessInterface {
                 name: String::from("wlp64s0"),
-                connected_ssid: Some(String::from("whatever"))
+                connected_ssid: Some(String::from("whatever")),
+                addr: Some(String::from("9c:fc:e8:b8:fa:60")),
+                iftype: Some(String::from("managed")),
+                ..WirelessInterface::default()
             }]
         );
     }
@@ -584,6 +1908,14 @@ mod tests {
             vec![WirelessInterface {
                 name: String::from("wlp64s0"),
                 connected_ssid: Some(String::from("whatever")),
+                addr: Some(String::from("9c:fc:e8:b8:fa:60")),
+                iftype: Some(String::from("managed")),
+                channel: Some(ChannelInfo {
+                    number: 100,
+                    frequency: 5500,
+                    width: Some(80),
+                }),
+                txpower: Some(22.0),
             }]
         );
     }
@@ -625,15 +1957,70 @@ mod tests {
                 WirelessInterface {
                     name: String::from("wlp64s0"),
                     connected_ssid: Some(String::from("whatever")),
+                    addr: Some(String::from("9c:fc:e8:b8:fa:60")),
+                    iftype: Some(String::from("managed")),
+                    channel: Some(ChannelInfo {
+                        number: 100,
+                        frequency: 5500,
+                        width: Some(80),
+                    }),
+                    txpower: Some(22.0),
                 },
                 WirelessInterface {
                     name: String::from("second"),
                     connected_ssid: Some(String::from("whatever")),
+                    addr: Some(String::from("9c:fc:e8:b8:fa:60")),
+                    iftype: Some(String::from("managed")),
+                    channel: Some(ChannelInfo {
+                        number: 100,
+                        frequency: 5500,
+                        width: Some(80),
+                    }),
+                    txpower: Some(22.0),
                 }
             ]
         );
     }
 
+    #[test]
+    fn interface_without_ssid() {
+        let input = "phy#0
+	Interface wlp64s0
+		ifindex 3
+		wdev 0x1
+		addr 9c:fc:e8:b8:fa:60
+		type managed
+		txpower 22.00 dBm
+	Interface second
+		ifindex 4
+		wdev 0x3
+		addr 9c:fc:e8:b8:fa:62
+		ssid whatever
+		type managed
+            ";
+
+        assert_eq!(
+            parse_iw(input).unwrap().1,
+            vec![
+                WirelessInterface {
+                    name: String::from("wlp64s0"),
+                    connected_ssid: None,
+                    addr: Some(String::from("9c:fc:e8:b8:fa:60")),
+                    iftype: Some(String::from("managed")),
+                    channel: None,
+                    txpower: Some(22.0),
+                },
+                WirelessInterface {
+                    name: String::from("second"),
+                    connected_ssid: Some(String::from("whatever")),
+                    addr: Some(String::from("9c:fc:e8:b8:fa:62")),
+                    iftype: Some(String::from("managed")),
+                    ..WirelessInterface::default()
+                },
+            ]
+        );
+    }
+
     #[test]
     fn no_device_interface() {
         let input = "phy#0
@@ -652,6 +2039,41 @@ mod tests {
         assert_eq!(parse_iw(input).unwrap().1, vec![]);
     }
 
+    #[test]
+    fn link_up() {
+        let input = "3: wlp64s0: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc noqueue state UP mode DORMANT group default qlen 1000\\    link/ether 9c:fc:e8:b8:fa:60 brd ff:ff:ff:ff:ff:ff";
+
+        assert_eq!(
+            link_state(input).unwrap().1,
+            ("BROADCAST,MULTICAST,UP,LOWER_UP", "UP")
+        );
+    }
+
+    #[test]
+    fn link_down() {
+        let input = "3: wlp64s0: <BROADCAST,MULTICAST> mtu 1500 qdisc noqueue state DOWN mode DORMANT group default qlen 1000\\    link/ether 9c:fc:e8:b8:fa:60 brd ff:ff:ff:ff:ff:ff";
+
+        let (flags, oper_state) = link_state(input).unwrap().1;
+        assert!(!flags.split(',').any(|flag| flag == "UP"));
+        assert_eq!(oper_state, "DOWN");
+    }
+
+    #[test]
+    fn link_event_name() {
+        assert_eq!(
+            link_name("3: wlp64s0: <BROADCAST,MULTICAST> mtu 1500 qdisc noqueue state DOWN")
+                .unwrap()
+                .1,
+            "wlp64s0"
+        );
+        assert_eq!(
+            link_name("Deleted 5: wlx00c0ca: <BROADCAST,MULTICAST> mtu 1500")
+                .unwrap()
+                .1,
+            "wlx00c0ca"
+        );
+    }
+
     #[test]
     fn valid_network() {
         let input = "Cell 09 - Address: D4:1A:D1:51:67:F2
@@ -697,7 +2119,102 @@ mod tests {
                     limit: 70,
                 },
                 address: String::from("D4:1A:D1:51:67:F2"),
+                last_beacon: Some(2216),
+                access_points: 1,
             }]
         );
     }
+
+    #[test]
+    fn last_beacon_of_the_same_cell() {
+        let input = format!(
+            "Cell 01 - Address: 00:00:00:00:00:01
+                    Frequency:5.5 GHz (Channel 100)
+                    Quality=20/70  Signal level=-90 dBm
+                    ESSID:\"no beacon\"
+                    IE: IEEE 802.11i/WPA2 Version 1
+          {}",
+            VALID_NETWORK
+        );
+
+        let networks = parse_nw(&input).unwrap().1;
+        assert_eq!(
+            networks
+                .iter()
+                .map(|wn| (wn.essid.as_str(), wn.last_beacon))
+                .collect::<Vec<_>>(),
+            vec![("no beacon", None), ("some network", Some(2216))]
+        );
+    }
+
+    #[test]
+    fn security_of_the_same_cell() {
+        let input = format!(
+            "Cell 01 - Address: 00:00:00:00:00:01
+                    Frequency:2.412 GHz (Channel 1)
+                    Quality=20/70  Signal level=-90 dBm
+                    Encryption key:off
+                    ESSID:\"open network\"
+          {}",
+            VALID_NETWORK
+        );
+
+        let networks = parse_nw(&input).unwrap().1;
+        assert_eq!(
+            networks
+                .iter()
+                .map(|wn| (wn.essid.as_str(), wn.security_type.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("open network", SecurityType::Invalid),
+                ("some network", SecurityType::Wpa2)
+            ]
+        );
+    }
+
+    #[test]
+    fn channels_and_bands() {
+        let mut network = parse_nw(VALID_NETWORK).unwrap().1.remove(0);
+        for (frequency, channel, band) in [
+            (2.412, 1, Band::TwoFour),
+            (2.484, 14, Band::TwoFour),
+            (5.18, 36, Band::Five),
+            (5.825, 165, Band::Five),
+            (5.935, 2, Band::Six),
+            (5.955, 1, Band::Six),
+            (6.135, 37, Band::Six),
+        ] {
+            network.frequency = frequency;
+            assert_eq!((network.channel(), network.band()), (channel, band));
+        }
+    }
+
+    #[test]
+    fn group_access_points_by_ssid() {
+        let network = parse_nw(VALID_NETWORK).unwrap().1.remove(0);
+        let access_point = |essid: &str, address: &str, value| WirelessNetwork {
+            essid: essid.to_owned(),
+            address: address.to_owned(),
+            quality: Quality { value, limit: 70 },
+            ..network.clone()
+        };
+        let mut networks = vec![
+            access_point("office", "00:00:00:00:00:01", 20),
+            access_point("cafe", "00:00:00:00:00:02", 30),
+            access_point("Office", "00:00:00:00:00:03", 60),
+            access_point("office", "00:00:00:00:00:04", 40),
+        ];
+
+        group_access_points(&mut networks);
+        assert_eq!(
+            networks
+                .iter()
+                .map(|wn| (wn.id(), wn.access_points))
+                .collect::<Vec<_>>(),
+            vec![
+                (String::from("00:00:00:00:00:03"), 3),
+                (String::from("00:00:00:00:00:02"), 1),
+            ]
+        );
+    }
 }
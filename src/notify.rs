@@ -0,0 +1,285 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::mpsc::{channel, Sender},
+};
+use zbus::{blocking::Connection, zvariant::Value};
+
+const DESTINATION: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+const INTERFACE: &str = "org.freedesktop.Notifications";
+const APP_NAME: &str = "Swelfi";
+/// Let the notification server decide, how long notifications are shown.
+const DEFAULT_TIMEOUT: i32 = -1;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Notification {
+    ConnectionLost(String),
+    Reconnected(String),
+    /// SSID and the reason, why connecting failed.
+    ConnectFailed(String, String),
+    /// A saved network came into range.
+    NetworkInRange(String),
+    /// A captive portal wants the user to log in.
+    LoginRequired(String),
+}
+
+impl Notification {
+    fn summary(&self) -> String {
+        match self {
+            Notification::ConnectionLost(_) => String::from("Wifi disconnected"),
+            Notification::Reconnected(_) => String::from("Wifi reconnected"),
+            Notification::ConnectFailed(ssid, _) => format!("Connecting to {} failed", ssid),
+            Notification::NetworkInRange(_) => String::from("Known network in range"),
+            Notification::LoginRequired(_) => String::from("Login required"),
+        }
+    }
+
+    fn body(&self) -> String {
+        match self {
+            Notification::ConnectionLost(ssid) => format!("The connection to {} dropped", ssid),
+            Notification::Reconnected(ssid) => format!("Connected to {} again", ssid),
+            Notification::ConnectFailed(_, reason) => reason.to_owned(),
+            Notification::NetworkInRange(ssid) => format!("{} is available", ssid),
+            Notification::LoginRequired(ssid) => {
+                format!("{} wants you to log in, before it lets you out", ssid)
+            }
+        }
+    }
+
+    /// Categories from the desktop notifications specification.
+    fn category(&self) -> &'static str {
+        match self {
+            Notification::ConnectionLost(_) => "network.disconnected",
+            Notification::Reconnected(_) => "network.connected",
+            Notification::ConnectFailed(_, _) => "network.error",
+            Notification::NetworkInRange(_) | Notification::LoginRequired(_) => "network",
+        }
+    }
+
+    fn icon(&self) -> &'static str {
+        match self {
+            Notification::ConnectionLost(_) => "network-wireless-disconnected",
+            Notification::Reconnected(_) | Notification::NetworkInRange(_) => {
+                "network-wireless-signal-good"
+            }
+            Notification::ConnectFailed(_, _) => "network-error",
+            Notification::LoginRequired(_) => "dialog-password",
+        }
+    }
+}
+
+/// Which notifications are shown, all of them are on by default.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub connection_lost: bool,
+    pub reconnected: bool,
+    pub connect_failed: bool,
+    pub network_in_range: bool,
+    pub login_required: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            connection_lost: true,
+            reconnected: true,
+            connect_failed: true,
+            network_in_range: true,
+            login_required: true,
+        }
+    }
+}
+
+impl NotificationSettings {
+    pub fn enabled(&self, notification: &Notification) -> bool {
+        match notification {
+            Notification::ConnectionLost(_) => self.connection_lost,
+            Notification::Reconnected(_) => self.reconnected,
+            Notification::ConnectFailed(_, _) => self.connect_failed,
+            Notification::NetworkInRange(_) => self.network_in_range,
+            Notification::LoginRequired(_) => self.login_required,
+        }
+    }
+}
+
+/// Sends notifications to the notification server of the desktop.
+pub struct Notifier {
+    connection: Connection,
+}
+
+impl Notifier {
+    pub fn new(connection: Connection) -> Self {
+        Self { connection }
+    }
+
+    pub fn session() -> Result<Self> {
+        Ok(Self::new(Connection::session()?))
+    }
+
+    /// Shows the notification and returns its id.
+    pub fn send(&self, notification: &Notification) -> Result<u32> {
+        let hints = HashMap::from([("category", Value::from(notification.category()))]);
+        let reply = self.connection.call_method(
+            Some(DESTINATION),
+            PATH,
+            Some(INTERFACE),
+            "Notify",
+            &(
+                APP_NAME,
+                0u32,
+                notification.icon(),
+                notification.summary(),
+                notification.body(),
+                Vec::<&str>::new(),
+                hints,
+                DEFAULT_TIMEOUT,
+            ),
+        )?;
+        Ok(reply.body().deserialize::<u32>()?)
+    }
+}
+
+/// Sends the notifications from a separate thread, so a slow, or missing notification server
+/// doesn't block the front-end.
+pub fn spawn() -> Sender<Notification> {
+    let (sender, receiver) = channel::<Notification>();
+    std::thread::spawn(move || {
+        let notifier = match Notifier::session() {
+            Ok(notifier) => notifier,
+            Err(e) => {
+                log::warn!("Notifications are disabled, there is no session bus: {}", e);
+                // drain the notifications, so the senders don't notice
+                while receiver.recv().is_ok() {}
+                return;
+            }
+        };
+        while let Ok(notification) = receiver.recv() {
+            if let Err(e) = notifier.send(&notification) {
+                log::error!("Error while sending notification: {}", e);
+            }
+        }
+    });
+    sender
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{mpsc::Receiver, Mutex};
+    use zbus::{blocking::connection::Builder, zvariant::OwnedValue, Guid};
+
+    /// App name, summary, body and category of a notification.
+    type Shown = (String, String, String, String);
+
+    /// Records the notifications, like a notification server would show them.
+    struct MockServer {
+        notifications: Mutex<Sender<Shown>>,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.Notifications")]
+    impl MockServer {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            app_name: String,
+            _replaces_id: u32,
+            _app_icon: String,
+            summary: String,
+            body: String,
+            _actions: Vec<String>,
+            hints: HashMap<String, OwnedValue>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            let category = hints
+                .get("category")
+                .and_then(|category| String::try_from(category.clone()).ok())
+                .unwrap_or_default();
+            self.notifications
+                .lock()
+                .unwrap()
+                .send((app_name, summary, body, category))
+                .unwrap();
+            1
+        }
+    }
+
+    /// Connects a notifier to a mock server over a private peer-to-peer bus.
+    fn private_bus() -> (
+        Notifier,
+        Connection,
+        Receiver<Shown>,
+        tokio::runtime::Runtime,
+    ) {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let (client, server) = std::os::unix::net::UnixStream::pair().unwrap();
+        for stream in [&client, &server] {
+            stream.set_nonblocking(true).unwrap();
+        }
+        let client = tokio::net::UnixStream::from_std(client).unwrap();
+        let server = tokio::net::UnixStream::from_std(server).unwrap();
+
+        let (sender, receiver) = channel();
+        let server = std::thread::spawn(move || {
+            Builder::unix_stream(server)
+                .server(Guid::generate())
+                .unwrap()
+                .p2p()
+                .serve_at(
+                    PATH,
+                    MockServer {
+                        notifications: Mutex::new(sender),
+                    },
+                )
+                .unwrap()
+                .build()
+                .unwrap()
+        });
+        let client = Builder::unix_stream(client).p2p().build().unwrap();
+        let server = server.join().unwrap();
+        (Notifier::new(client), server, receiver, runtime)
+    }
+
+    #[test]
+    fn send_notifications() {
+        let (notifier, _server, notifications, _runtime) = private_bus();
+
+        let id = notifier
+            .send(&Notification::ConnectionLost(String::from("home")))
+            .unwrap();
+        assert_eq!(id, 1);
+        assert_eq!(
+            notifications.recv().unwrap(),
+            (
+                String::from("Swelfi"),
+                String::from("Wifi disconnected"),
+                String::from("The connection to home dropped"),
+                String::from("network.disconnected"),
+            )
+        );
+
+        notifier
+            .send(&Notification::ConnectFailed(
+                String::from("cafe"),
+                String::from("swelfi helper: 'wpa_cli select_network' failed: FAIL"),
+            ))
+            .unwrap();
+        let (_, summary, body, category) = notifications.recv().unwrap();
+        assert_eq!(summary, "Connecting to cafe failed");
+        assert_eq!(body, "swelfi helper: 'wpa_cli select_network' failed: FAIL");
+        assert_eq!(category, "network.error");
+    }
+
+    #[test]
+    fn settings() {
+        let settings = NotificationSettings {
+            network_in_range: false,
+            ..Default::default()
+        };
+        assert!(settings.enabled(&Notification::Reconnected(String::from("home"))));
+        assert!(!settings.enabled(&Notification::NetworkInRange(String::from("home"))));
+    }
+}
@@ -0,0 +1,137 @@
+use crate::http;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Answers 204 without content, unless a captive portal intercepts it.
+pub const PROBE_URL: &str = "http://connectivitycheck.gstatic.com/generate_204";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How captive portals are detected, saved as `[captive_portal]` in the settings.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Probe {
+    /// Probes after connecting to a network, diagnostics probe either way.
+    pub enabled: bool,
+    pub url: String,
+    /// What the URL answers without a portal, HTTP 204 is expected, if it isn't set.
+    pub expected_content: Option<String>,
+}
+
+impl Default for Probe {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            url: String::from(PROBE_URL),
+            expected_content: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Portal {
+    /// The probe got the expected answer.
+    None,
+    /// The page to log in at.
+    LoginRequired(String),
+}
+
+impl Probe {
+    /// Requests the probe URL, it has to be resolvable and reachable to tell anything.
+    pub fn detect(&self) -> Result<Portal> {
+        Ok(self.classify(&http::get(&self.url, TIMEOUT)?))
+    }
+
+    /// Any other answer than the expected one means, something intercepted the request.
+    pub fn classify(&self, response: &http::Response) -> Portal {
+        let expected = match self.expected_content {
+            Some(ref content) => response.status == 200 && response.body.trim() == content.trim(),
+            None => response.status == 204,
+        };
+        if expected {
+            return Portal::None;
+        }
+        // portals either redirect to their login page, or serve it instead of the probe
+        Portal::LoginRequired(match response.location {
+            Some(ref location) => self.absolute(location),
+            None => self.url.clone(),
+        })
+    }
+
+    /// Resolves a redirect relative to the probe URL.
+    fn absolute(&self, location: &str) -> String {
+        if location.contains("://") {
+            return location.to_owned();
+        }
+        let rest = self.url.strip_prefix("http://").unwrap_or(&self.url);
+        let authority = rest.split('/').next().unwrap_or_default();
+        match location.strip_prefix('/') {
+            Some(path) => format!("http://{}/{}", authority, path),
+            None => format!("http://{}/{}", authority, location),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::serve_http;
+
+    fn probe(url: &str, expected_content: Option<&str>) -> Probe {
+        Probe {
+            enabled: true,
+            url: url.to_owned(),
+            expected_content: expected_content.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn detect_with_stand_in() {
+        let url = serve_http("HTTP/1.1 204 No Content\r\n\r\n");
+        assert_eq!(probe(&url, None).detect().unwrap(), Portal::None);
+
+        let url = serve_http("HTTP/1.1 302 Found\r\nLocation: /login?next=probe\r\n\r\n");
+        let authority = url.trim_start_matches("http://").split('/').next().unwrap();
+        assert_eq!(
+            probe(&url, None).detect().unwrap(),
+            Portal::LoginRequired(format!("http://{}/login?next=probe", authority))
+        );
+
+        // a portal, which serves its page in place of the expected content
+        let url = serve_http("HTTP/1.1 200 OK\r\n\r\n<html>Welcome to the hotel</html>");
+        assert_eq!(
+            probe(&url, Some("success")).detect().unwrap(),
+            Portal::LoginRequired(url.clone())
+        );
+
+        let url = serve_http("HTTP/1.1 200 OK\r\n\r\nsuccess\n");
+        assert_eq!(probe(&url, Some("success")).detect().unwrap(), Portal::None);
+
+        assert!(probe("http://127.0.0.1:9/", None).detect().is_err());
+    }
+
+    #[test]
+    fn classify_responses() {
+        let probe = probe(PROBE_URL, None);
+        let response = |status, location: Option<&str>| http::Response {
+            status,
+            location: location.map(str::to_owned),
+            body: String::new(),
+        };
+
+        assert_eq!(
+            probe.classify(&response(302, Some("https://login.example/?a=1"))),
+            Portal::LoginRequired(String::from("https://login.example/?a=1"))
+        );
+        assert_eq!(
+            probe.classify(&response(511, None)),
+            Portal::LoginRequired(String::from(PROBE_URL))
+        );
+        assert_eq!(
+            probe.classify(&response(302, Some("portal.html"))),
+            Portal::LoginRequired(String::from(
+                "http://connectivitycheck.gstatic.com/portal.html"
+            ))
+        );
+    }
+}
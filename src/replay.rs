@@ -0,0 +1,463 @@
+use crate::{
+    app::{Event, Repaint},
+    backend::Backend,
+    helper::ConnectOptions,
+    rfkill::RfkillState,
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Write,
+    net::IpAddr,
+    path::Path,
+    sync::{mpsc::Sender, Mutex},
+    time::Instant,
+};
+
+const IW_DEV: &str = "iw dev";
+/// Stands in for the interface of captured outputs, which don't name it.
+const ANY: &str = "*";
+
+fn iw_info(interface: &str) -> String {
+    format!("iw dev {} info", interface)
+}
+
+fn ip_link(interface: &str) -> String {
+    format!("ip -o link show dev {}", interface)
+}
+
+fn scan(interface: &str) -> String {
+    format!("iwlist {} scan", interface)
+}
+
+/// Recorded as one SSID per line.
+fn saved_networks(interface: &str) -> String {
+    format!("saved networks of {}", interface)
+}
+
+/// The output of a command in a session, one JSON object per line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    /// Seconds since the session started.
+    time: f64,
+    /// Like `iw dev wlan0 info`.
+    command: String,
+    #[serde(default)]
+    output: String,
+    /// Why the command failed, instead of its output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Answers with recorded output instead of running the commands.
+#[derive(Debug)]
+pub struct Replay {
+    /// The outputs of every command, in the order they were recorded.
+    outputs: HashMap<String, Vec<Entry>>,
+    started: Instant,
+}
+
+impl Replay {
+    /// Loads a session, or a directory of captured outputs, like an unpacked diagnostics bundle.
+    pub fn load(path: &Path) -> Result<Self> {
+        let entries = if path.is_dir() {
+            captured(path)
+        } else {
+            std::fs::read_to_string(path)
+                .map_err(anyhow::Error::from)
+                .and_then(|contents| session(&contents))
+        }
+        .map_err(|e| anyhow!("replaying {} failed: {}", path.display(), e))?;
+        if entries.is_empty() {
+            return Err(anyhow!("nothing to replay in {}", path.display()));
+        }
+        Ok(Self::new(entries))
+    }
+
+    fn new(entries: Vec<Entry>) -> Self {
+        let mut outputs: HashMap<String, Vec<Entry>> = HashMap::new();
+        for entry in entries {
+            outputs
+                .entry(entry.command.clone())
+                .or_default()
+                .push(entry);
+        }
+        for entries in outputs.values_mut() {
+            entries.sort_by(|a, b| a.time.total_cmp(&b.time));
+        }
+        Self {
+            outputs,
+            started: Instant::now(),
+        }
+    }
+
+    /// The latest output of the command by then, or its first one, if it came later.
+    fn output_at(&self, command: &str, any: &str, time: f64) -> Option<Result<String>> {
+        let entries = self
+            .outputs
+            .get(command)
+            .or_else(|| self.outputs.get(any))?;
+        let entry = entries
+            .iter()
+            .rev()
+            .find(|entry| entry.time <= time)
+            .unwrap_or(&entries[0]);
+        Some(match entry.error {
+            Some(ref error) => Err(anyhow!("{}", error)),
+            None => Ok(entry.output.clone()),
+        })
+    }
+
+    fn output(&self, command: &str, any: &str) -> Option<Result<String>> {
+        self.output_at(command, any, self.started.elapsed().as_secs_f64())
+    }
+
+    fn recorded(&self, command: &str, any: &str) -> Result<String> {
+        self.output(command, any)
+            .unwrap_or_else(|| Err(anyhow!("'{}' wasn't recorded", command)))
+    }
+}
+
+impl Backend for Replay {
+    fn iw_dev(&self) -> Result<String> {
+        self.recorded(IW_DEV, IW_DEV)
+    }
+
+    fn iw_info(&self, interface: &str) -> Result<String> {
+        self.recorded(&iw_info(interface), &iw_info(ANY))
+    }
+
+    /// Captured outputs often leave it out, then the interface is up.
+    fn ip_link(&self, interface: &str) -> Result<String> {
+        self.output(&ip_link(interface), &ip_link(ANY))
+            .unwrap_or_else(|| {
+                Ok(format!(
+                    "1: {}: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 state UP mode DORMANT\n",
+                    interface
+                ))
+            })
+    }
+
+    fn scan(&self, interface: &str) -> Result<String> {
+        self.recorded(&scan(interface), &scan(ANY))
+    }
+
+    fn is_system(&self) -> bool {
+        false
+    }
+
+    /// The switches of the recording's phys aren't known.
+    fn rfkill(&self, _interface: &str) -> Result<RfkillState> {
+        Ok(RfkillState::default())
+    }
+
+    fn watch_rfkill(&self, _event_sender: Sender<Event>, _repaint: Repaint) {}
+
+    /// None, unless they were recorded.
+    fn saved_networks(&self, interface: &str) -> Result<Vec<String>> {
+        match self.output(&saved_networks(interface), &saved_networks(ANY)) {
+            Some(output) => Ok(output?.lines().map(str::to_owned).collect()),
+            None => Ok(vec![]),
+        }
+    }
+}
+
+fn session(contents: &str) -> Result<Vec<Entry>> {
+    let mut entries = vec![];
+    for (number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        entries.push(
+            serde_json::from_str(line)
+                .map_err(|e| anyhow!("line {} isn't a recorded command: {}", number + 1, e))?,
+        );
+    }
+    Ok(entries)
+}
+
+/// Reads `iw-dev.txt`, `iw-info.txt`, `ip-link.txt`, `iwlist-scan.txt` and
+/// `saved-networks.txt`, named like in the diagnostics bundle. With the interface appended, like `iw-info-wlan0.txt`, they're only
+/// replayed for it.
+fn captured(directory: &Path) -> Result<Vec<Entry>> {
+    let mut entries = vec![];
+    for file in std::fs::read_dir(directory)? {
+        let file = file?;
+        let name = file.file_name().to_string_lossy().into_owned();
+        let command = match name.strip_suffix(".txt").and_then(captured_command) {
+            Some(command) => command,
+            None => continue,
+        };
+        let contents = std::fs::read_to_string(file.path())?;
+        // the bundle starts with the command line
+        let output = match contents.strip_prefix("$ ") {
+            Some(rest) => rest.split_once('\n').map_or("", |(_, output)| output),
+            None => &contents,
+        };
+        entries.push(Entry {
+            time: 0.0,
+            command,
+            output: output.to_owned(),
+            error: None,
+        });
+    }
+    Ok(entries)
+}
+
+fn captured_command(stem: &str) -> Option<String> {
+    if stem == "iw-dev" {
+        return Some(IW_DEV.to_owned());
+    }
+    for prefix in ["iw-info", "ip-link", "iwlist-scan", "saved-networks"] {
+        let interface = match stem.strip_prefix(prefix) {
+            Some("") => ANY,
+            Some(rest) => match rest.strip_prefix('-') {
+                Some(interface) => interface,
+                None => continue,
+            },
+            None => continue,
+        };
+        return Some(match prefix {
+            "iw-info" => iw_info(interface),
+            "ip-link" => ip_link(interface),
+            "iwlist-scan" => scan(interface),
+            _ => saved_networks(interface),
+        });
+    }
+    None
+}
+
+/// Records a session of another backend, to replay it later.
+pub struct Recorder<B> {
+    backend: B,
+    file: Mutex<File>,
+    started: Instant,
+}
+
+impl<B: Backend> Recorder<B> {
+    /// Replaces the file, the times are relative to the start of the session.
+    pub fn start(path: &Path, backend: B) -> Result<Self> {
+        Ok(Self {
+            backend,
+            file: Mutex::new(File::create(path)?),
+            started: Instant::now(),
+        })
+    }
+
+    fn record(&self, command: String, result: Result<String>) -> Result<String> {
+        let entry = Entry {
+            time: self.started.elapsed().as_secs_f64(),
+            command,
+            output: result
+                .as_ref()
+                .map_or_else(|_| String::new(), String::clone),
+            error: result.as_ref().err().map(|e| format!("{:#}", e)),
+        };
+        let written = serde_json::to_string(&entry)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| {
+                let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+                Ok(file.write_all(format!("{}\n", line).as_bytes())?)
+            });
+        if let Err(e) = written {
+            log::error!("Error while recording '{}': {}", entry.command, e);
+        }
+        result
+    }
+}
+
+impl<B: Backend> Backend for Recorder<B> {
+    fn iw_dev(&self) -> Result<String> {
+        self.record(IW_DEV.to_owned(), self.backend.iw_dev())
+    }
+
+    fn iw_info(&self, interface: &str) -> Result<String> {
+        self.record(iw_info(interface), self.backend.iw_info(interface))
+    }
+
+    fn ip_link(&self, interface: &str) -> Result<String> {
+        self.record(ip_link(interface), self.backend.ip_link(interface))
+    }
+
+    fn scan(&self, interface: &str) -> Result<String> {
+        self.record(scan(interface), self.backend.scan(interface))
+    }
+
+    fn connect(
+        &self,
+        interface: &str,
+        ssid: &str,
+        passphrase: Option<&str>,
+        options: &ConnectOptions,
+    ) -> Result<()> {
+        self.backend.connect(interface, ssid, passphrase, options)
+    }
+
+    fn disconnect(&self, interface: &str) -> Result<()> {
+        self.backend.disconnect(interface)
+    }
+
+    fn set_link(&self, interface: &str, on: bool) -> Result<()> {
+        self.backend.set_link(interface, on)
+    }
+
+    fn is_system(&self) -> bool {
+        self.backend.is_system()
+    }
+
+    fn rfkill(&self, interface: &str) -> Result<RfkillState> {
+        self.backend.rfkill(interface)
+    }
+
+    fn watch_rfkill(&self, event_sender: Sender<Event>, repaint: Repaint) {
+        self.backend.watch_rfkill(event_sender, repaint)
+    }
+
+    fn set_rfkill(&self, index: u32, blocked: bool) -> Result<()> {
+        self.backend.set_rfkill(index, blocked)
+    }
+
+    fn set_all_rfkill(&self, blocked: bool) -> Result<()> {
+        self.backend.set_all_rfkill(blocked)
+    }
+
+    fn saved_networks(&self, interface: &str) -> Result<Vec<String>> {
+        let output = self
+            .backend
+            .saved_networks(interface)
+            .map(|ssids| ssids.join("\n"));
+        let output = self.record(saved_networks(interface), output)?;
+        Ok(output.lines().map(str::to_owned).collect())
+    }
+
+    fn set_dns(&self, interface: &str, servers: &[IpAddr]) -> Result<()> {
+        self.backend.set_dns(interface, servers)
+    }
+
+    fn renew_lease(&self, interface: &str) -> Result<()> {
+        self.backend.renew_lease(interface)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::VALID_NETWORK;
+
+    const IW_DEV_OUTPUT: &str = "phy#0\n\tInterface wlan0\n\t\tifindex 3\n";
+
+    struct Fake;
+
+    impl Backend for Fake {
+        fn iw_dev(&self) -> Result<String> {
+            Ok(String::from(IW_DEV_OUTPUT))
+        }
+
+        fn iw_info(&self, interface: &str) -> Result<String> {
+            Err(anyhow!("{} is gone", interface))
+        }
+
+        fn ip_link(&self, interface: &str) -> Result<String> {
+            Ok(format!(
+                "3: {}: <BROADCAST> mtu 1500 state DOWN\n",
+                interface
+            ))
+        }
+
+        fn scan(&self, _interface: &str) -> Result<String> {
+            Ok(String::from(VALID_NETWORK))
+        }
+
+        fn saved_networks(&self, _interface: &str) -> Result<Vec<String>> {
+            Ok(vec![String::from("home"), String::from("cafe")])
+        }
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let path =
+            std::env::temp_dir().join(format!("swelfi-session-{}.jsonl", std::process::id()));
+        let recorder = Recorder::start(&path, Fake).unwrap();
+        recorder.iw_dev().unwrap();
+        assert!(recorder.iw_info("wlan0").is_err());
+        recorder.ip_link("wlan0").unwrap();
+        recorder.scan("wlan0").unwrap();
+        recorder.saved_networks("wlan0").unwrap();
+        drop(recorder);
+
+        let replay = Replay::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(replay.iw_dev().unwrap(), IW_DEV_OUTPUT);
+        assert_eq!(
+            replay.iw_info("wlan0").unwrap_err().to_string(),
+            "wlan0 is gone"
+        );
+        assert_eq!(
+            replay.ip_link("wlan0").unwrap(),
+            Fake.ip_link("wlan0").unwrap()
+        );
+        assert_eq!(replay.scan("wlan0").unwrap(), VALID_NETWORK);
+        assert_eq!(replay.saved_networks("wlan0").unwrap(), ["home", "cafe"]);
+        // only what was recorded
+        assert!(replay.scan("wlan1").is_err());
+        assert!(replay.saved_networks("wlan1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn replay_with_timing() {
+        let replay = Replay::new(
+            session(
+                "{\"time\":10.0,\"command\":\"iwlist wlan0 scan\",\"output\":\"later\"}\n\
+                 {\"time\":0.5,\"command\":\"iwlist wlan0 scan\",\"output\":\"first\"}\n\
+                 \n\
+                 {\"time\":20.0,\"command\":\"iwlist wlan0 scan\",\"error\":\"device busy\"}\n",
+            )
+            .unwrap(),
+        );
+        let output = |time| replay.output_at("iwlist wlan0 scan", "", time).unwrap();
+        assert_eq!(output(0.0).unwrap(), "first");
+        assert_eq!(output(9.9).unwrap(), "first");
+        assert_eq!(output(10.0).unwrap(), "later");
+        assert_eq!(output(25.0).unwrap_err().to_string(), "device busy");
+        assert!(replay.output_at("iw dev", "", 0.0).is_none());
+
+        let error = session("{\"time\":0.0,\"command\":\"iw dev\"}\n{}\n").unwrap_err();
+        assert!(error.to_string().starts_with("line 2 "), "{}", error);
+    }
+
+    #[test]
+    fn replay_captured_outputs() {
+        let directory =
+            std::env::temp_dir().join(format!("swelfi-captured-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(
+            directory.join("iw-dev.txt"),
+            format!("$ iw dev\n{}", IW_DEV_OUTPUT),
+        )
+        .unwrap();
+        std::fs::write(directory.join("iwlist-scan.txt"), VALID_NETWORK).unwrap();
+        std::fs::write(directory.join("iwlist-scan-wlan1.txt"), "wlan1").unwrap();
+        std::fs::write(directory.join("iw-link.txt"), "not replayed").unwrap();
+
+        let replay = Replay::load(&directory).unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(replay.iw_dev().unwrap(), IW_DEV_OUTPUT);
+        assert_eq!(replay.scan("wlan0").unwrap(), VALID_NETWORK);
+        assert_eq!(replay.scan("wlan1").unwrap(), "wlan1");
+        assert!(replay.iw_info("wlan0").is_err());
+        assert_eq!(replay.outputs.len(), 3);
+        // without a recorded link state, the interface is up
+        let link = replay.ip_link("wlan0").unwrap();
+        let (flags, state) = crate::link_state(&link).unwrap().1;
+        assert!(flags.split(',').any(|flag| flag == "UP"));
+        assert_eq!(state, "UP");
+
+        assert_eq!(
+            captured_command("iw-info-wl-an0").unwrap(),
+            "iw dev wl-an0 info"
+        );
+        assert_eq!(captured_command("ip-linkx"), None);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::app::{Event, Repaint};
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::{
+    fs::OpenOptions,
+    io::{Read, Write},
+    sync::mpsc::Sender,
+};
+
+const RFKILL_DEVICE: &str = "/dev/rfkill";
+const RFKILL_EVENT_SIZE: usize = 8;
+
+const RFKILL_TYPE_ALL: u8 = 0;
+const RFKILL_TYPE_WLAN: u8 = 1;
+
+const RFKILL_OP_ADD: u8 = 0;
+const RFKILL_OP_DEL: u8 = 1;
+const RFKILL_OP_CHANGE: u8 = 2;
+const RFKILL_OP_CHANGE_ALL: u8 = 3;
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize)]
+pub struct RfkillState {
+    pub index: Option<u32>,
+    pub soft_blocked: bool,
+    pub hard_blocked: bool,
+}
+
+/// An event as read from, or written to /dev/rfkill (struct rfkill_event in linux/rfkill.h).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RfkillEvent {
+    pub index: u32,
+    pub rfkill_type: u8,
+    pub op: u8,
+    pub soft: bool,
+    pub hard: bool,
+}
+
+impl RfkillEvent {
+    pub fn parse(bytes: &[u8; RFKILL_EVENT_SIZE]) -> Self {
+        Self {
+            index: u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            rfkill_type: bytes[4],
+            op: bytes[5],
+            soft: bytes[6] != 0,
+            hard: bytes[7] != 0,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; RFKILL_EVENT_SIZE] {
+        let index = self.index.to_ne_bytes();
+        [
+            index[0],
+            index[1],
+            index[2],
+            index[3],
+            self.rfkill_type,
+            self.op,
+            self.soft as u8,
+            self.hard as u8,
+        ]
+    }
+
+    /// A wlan switch, as reported by the kernel on opening /dev/rfkill.
+    pub fn added(index: u32, soft: bool) -> Self {
+        Self {
+            index,
+            rfkill_type: RFKILL_TYPE_WLAN,
+            op: RFKILL_OP_ADD,
+            soft,
+            hard: false,
+        }
+    }
+
+    /// The soft block of a wlan switch changed.
+    pub fn changed(index: u32, soft: bool) -> Self {
+        Self {
+            op: RFKILL_OP_CHANGE,
+            ..Self::added(index, soft)
+        }
+    }
+
+    pub fn is_removed(&self) -> bool {
+        self.op == RFKILL_OP_DEL
+    }
+}
+
+/// Reads the rfkill switch of the phy backing the given interface from sysfs.
+pub fn state(interface: &str) -> Result<RfkillState> {
+    let phy_path = format!("/sys/class/net/{}/phy80211", interface);
+    let mut rfkill = RfkillState::default();
+    let entries = match std::fs::read_dir(phy_path) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(rfkill), // no phy, so no rfkill switch either
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let index = match file_name
+            .to_string_lossy()
+            .strip_prefix("rfkill")
+            .and_then(|index| index.parse::<u32>().ok())
+        {
+            Some(index) => index,
+            None => continue,
+        };
+        let path = entry.path();
+        rfkill.index = Some(index);
+        rfkill.soft_blocked |= std::fs::read_to_string(path.join("soft"))?.trim() == "1";
+        rfkill.hard_blocked |= std::fs::read_to_string(path.join("hard"))?.trim() == "1";
+    }
+    Ok(rfkill)
+}
+
+/// Sets, or clears the soft block of a single rfkill switch.
+pub fn set_blocked(index: u32, blocked: bool) -> Result<()> {
+    write_event(RfkillEvent {
+        index,
+        rfkill_type: RFKILL_TYPE_WLAN,
+        op: RFKILL_OP_CHANGE,
+        soft: blocked,
+        hard: false,
+    })
+}
+
+/// Sets, or clears the soft block of all radios, e.g. for airplane mode.
+pub fn set_all_blocked(blocked: bool) -> Result<()> {
+    write_event(RfkillEvent {
+        index: 0,
+        rfkill_type: RFKILL_TYPE_ALL,
+        op: RFKILL_OP_CHANGE_ALL,
+        soft: blocked,
+        hard: false,
+    })
+}
+
+fn write_event(event: RfkillEvent) -> Result<()> {
+    let mut device = OpenOptions::new()
+        .write(true)
+        .open(RFKILL_DEVICE)
+        .map_err(|e| anyhow!("opening {} failed: {}", RFKILL_DEVICE, e))?;
+    device
+        .write_all(&event.to_bytes())
+        .map_err(|e| anyhow!("writing to {} failed: {}", RFKILL_DEVICE, e))
+}
+
+/// Reads events from /dev/rfkill and sends them to the UI. On opening, the kernel sends an add
+/// event for every existing switch, after that, every change is reported.
+pub fn watch(event_sender: Sender<Event>, repaint: Repaint) {
+    std::thread::spawn(move || {
+        if let Err(e) = read_events(&event_sender, &repaint) {
+            log::error!("Error while watching rfkill events: {}", e);
+        }
+    });
+}
+
+fn read_events(event_sender: &Sender<Event>, repaint: &Repaint) -> Result<()> {
+    let mut device = OpenOptions::new()
+        .read(true)
+        .open(RFKILL_DEVICE)
+        .map_err(|e| anyhow!("opening {} failed: {}", RFKILL_DEVICE, e))?;
+    let mut buf = [0; RFKILL_EVENT_SIZE];
+    loop {
+        device.read_exact(&mut buf)?;
+        let event = RfkillEvent::parse(&buf);
+        log::debug!("rfkill event: {:?}", event);
+        if event.op != RFKILL_OP_ADD && event.op != RFKILL_OP_DEL && event.op != RFKILL_OP_CHANGE {
+            continue;
+        }
+        if event_sender.send(Event::UpdateRfkill(event)).is_err() {
+            return Ok(());
+        }
+        repaint.request_repaint();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_event() {
+        let mut bytes = [0; RFKILL_EVENT_SIZE];
+        bytes[..4].copy_from_slice(&2u32.to_ne_bytes());
+        bytes[4..].copy_from_slice(&[RFKILL_TYPE_WLAN, RFKILL_OP_CHANGE, 1, 0]);
+
+        assert_eq!(
+            RfkillEvent::parse(&bytes),
+            RfkillEvent {
+                index: 2,
+                rfkill_type: RFKILL_TYPE_WLAN,
+                op: RFKILL_OP_CHANGE,
+                soft: true,
+                hard: false,
+            }
+        );
+    }
+
+    #[test]
+    fn event_roundtrip() {
+        let event = RfkillEvent {
+            index: 0,
+            rfkill_type: RFKILL_TYPE_ALL,
+            op: RFKILL_OP_CHANGE_ALL,
+            soft: true,
+            hard: false,
+        };
+
+        assert_eq!(RfkillEvent::parse(&event.to_bytes()), event);
+    }
+}
@@ -0,0 +1,458 @@
+use crate::{
+    helper::{self, ConnectOptions, MacPolicy},
+    settings, Band, WirelessNetwork,
+};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::{
+    fmt,
+    io::Read,
+    net::IpAddr,
+    os::unix::process::CommandExt,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::{mpsc::channel, Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+const RULES_FILE: &str = "swelfi.toml";
+const DEFAULT_HOOK_TIMEOUT: u64 = 30;
+const HOOK_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Commands started in the background by a hook might keep its output open, so reading the
+/// output only waits this long after the hook exited.
+const OUTPUT_GRACE_TIME: Duration = Duration::from_millis(500);
+/// Output of a hook, which is kept for the log.
+const MAX_HOOK_OUTPUT: usize = 16 * 1024;
+
+/// Settings and hooks for a network, matched by SSID, BSSID, or both.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rule {
+    pub ssid: Option<String>,
+    /// Limits the rule to one access point.
+    pub bssid: Option<String>,
+    /// Access point to connect to, if it's in range.
+    pub preferred_bssid: Option<String>,
+    /// Connects to the strongest access point in the band, if there is no preferred one.
+    pub band: Option<Band>,
+    /// Connect, when the network comes into range while not connected, secured networks need
+    /// the passphrase.
+    pub autoconnect: bool,
+    pub passphrase: Option<String>,
+    pub mac: Option<MacPolicy>,
+    /// DNS servers used instead of the ones from DHCP, while connected.
+    pub dns: Vec<IpAddr>,
+    /// Shell commands run after connecting to, or disconnecting from the network.
+    pub on_connect: Option<String>,
+    pub on_disconnect: Option<String>,
+    /// Seconds, after which hooks are killed.
+    pub hook_timeout: u64,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self {
+            ssid: None,
+            bssid: None,
+            preferred_bssid: None,
+            band: None,
+            autoconnect: false,
+            passphrase: None,
+            mac: None,
+            dns: vec![],
+            on_connect: None,
+            on_disconnect: None,
+            hook_timeout: DEFAULT_HOOK_TIMEOUT,
+        }
+    }
+}
+
+impl Rule {
+    fn validate(&self) -> Result<()> {
+        if self.ssid.is_none() && self.bssid.is_none() {
+            return Err(anyhow!("a network needs an ssid, or a bssid"));
+        }
+        for bssid in self.bssid.iter().chain(self.preferred_bssid.iter()) {
+            helper::validate_bssid(bssid)?;
+        }
+        if let Some(ref passphrase) = self.passphrase {
+            helper::validate_passphrase(passphrase)?;
+        }
+        Ok(())
+    }
+
+    fn matches(&self, ssid: &str, bssids: &[&str]) -> bool {
+        self.ssid.as_ref().is_none_or(|s| s == ssid)
+            && self.bssid.as_ref().is_none_or(|bssid| {
+                bssids
+                    .iter()
+                    .any(|address| address.eq_ignore_ascii_case(bssid))
+            })
+    }
+
+    /// Picks the access point from the scan results, the preferred one, if it's in range, or
+    /// the strongest one in the preferred band.
+    pub fn connect_options(&self, ssid: &str, networks: &[WirelessNetwork]) -> ConnectOptions {
+        let access_points = || networks.iter().filter(|wn| wn.essid == ssid);
+        let bssid = self
+            .preferred_bssid
+            .clone()
+            .filter(|bssid| access_points().any(|wn| wn.address.eq_ignore_ascii_case(bssid)))
+            .or_else(|| {
+                let band = self.band?;
+                access_points()
+                    .filter(|wn| wn.band() == band)
+                    .max_by_key(|wn| wn.quality.value)
+                    .map(|wn| wn.address.clone())
+            });
+        ConnectOptions {
+            bssid,
+            mac: self.mac,
+        }
+    }
+
+    pub fn hook(&self, event: HookEvent, interface: &str, ssid: &str) -> Option<Hook> {
+        let command = match event {
+            HookEvent::Connect => self.on_connect.as_ref(),
+            HookEvent::Disconnect => self.on_disconnect.as_ref(),
+        }?;
+        Some(Hook {
+            interface: interface.to_owned(),
+            ssid: ssid.to_owned(),
+            event,
+            command: command.to_owned(),
+            timeout: Duration::from_secs(self.hook_timeout),
+        })
+    }
+}
+
+/// The rules from `swelfi.toml`, a `[[network]]` table per rule.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rules {
+    #[serde(default, rename = "network")]
+    pub networks: Vec<Rule>,
+}
+
+impl Rules {
+    /// Loads the rules from the config directory, there are none, if the file doesn't exist.
+    pub fn load() -> Result<Self> {
+        let path = match path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(Self::default()),
+        };
+        parse(&std::fs::read_to_string(&path)?)
+            .map_err(|e| anyhow!("invalid rules in {}: {}", path.display(), e))
+    }
+
+    /// The first rule matching the SSID and one of the BSSIDs of its access points.
+    pub fn find(&self, ssid: &str, bssids: &[&str]) -> Option<&Rule> {
+        self.networks.iter().find(|rule| rule.matches(ssid, bssids))
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    settings::config_dir().map(|dir| dir.join(RULES_FILE))
+}
+
+fn parse(contents: &str) -> Result<Rules> {
+    let rules: Rules = toml::from_str(contents)?;
+    for rule in &rules.networks {
+        rule.validate()?;
+    }
+    Ok(rules)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum HookEvent {
+    Connect,
+    Disconnect,
+}
+
+impl fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookEvent::Connect => write!(f, "connect"),
+            HookEvent::Disconnect => write!(f, "disconnect"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Hook {
+    pub interface: String,
+    pub ssid: String,
+    pub event: HookEvent,
+    pub command: String,
+    pub timeout: Duration,
+}
+
+/// What happened, when the rules were applied, shown in the log.
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub time: SystemTime,
+    pub ssid: String,
+    pub title: String,
+    pub success: bool,
+    /// Output of hooks, stdout and stderr are interleaved.
+    pub output: String,
+}
+
+impl LogEntry {
+    pub fn new(ssid: &str, title: String, success: bool, output: String) -> Self {
+        Self {
+            time: SystemTime::now(),
+            ssid: ssid.to_owned(),
+            title,
+            success,
+            output,
+        }
+    }
+
+    pub fn dns(ssid: &str, servers: &[IpAddr], result: Result<()>) -> Self {
+        let action = if servers.is_empty() {
+            String::from("Reverting DNS servers")
+        } else {
+            let servers: Vec<String> = servers.iter().map(|server| server.to_string()).collect();
+            format!("Setting DNS servers to {}", servers.join(", "))
+        };
+        match result {
+            Ok(_) => Self::new(ssid, action, true, String::new()),
+            Err(e) => Self::new(ssid, format!("{} failed", action), false, e.to_string()),
+        }
+    }
+}
+
+/// Runs the hook using `sh -c` and kills it, and everything it started, once it takes longer
+/// than its timeout. The interface, SSID and event are passed in `SWELFI_INTERFACE`,
+/// `SWELFI_SSID` and `SWELFI_EVENT`.
+pub fn run_hook(hook: &Hook) -> LogEntry {
+    let title = |status: &str| format!("{} hook: {}", hook.event, status);
+    let mut child = match Command::new("sh")
+        .arg("-c")
+        .arg(&hook.command)
+        .env("SWELFI_INTERFACE", &hook.interface)
+        .env("SWELFI_SSID", &hook.ssid)
+        .env("SWELFI_EVENT", hook.event.to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => return LogEntry::new(&hook.ssid, title("starting failed"), false, e.to_string()),
+    };
+
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let (done_sender, done) = channel();
+    let pipes: [Option<Box<dyn Read + Send>>; 2] = [
+        child.stdout.take().map(|pipe| Box::new(pipe) as _),
+        child.stderr.take().map(|pipe| Box::new(pipe) as _),
+    ];
+    let readers = pipes.len();
+    for mut pipe in pipes.into_iter().flatten() {
+        let output = output.clone();
+        let done_sender = done_sender.clone();
+        std::thread::spawn(move || {
+            let mut buf = [0; 4096];
+            while let Ok(n @ 1..) = pipe.read(&mut buf) {
+                let mut output = output.lock().expect("output isn't poisoned");
+                let n = n.min(MAX_HOOK_OUTPUT.saturating_sub(output.len()));
+                output.extend_from_slice(&buf[..n]);
+            }
+            let _ = done_sender.send(());
+        });
+    }
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Ok(status),
+            Ok(None) if started.elapsed() >= hook.timeout => {
+                // SAFETY: kill has no memory safety requirements, the negative pid addresses
+                // the process group of the hook
+                unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+                let _ = child.wait();
+                break Err(format!("timed out after {:?}", hook.timeout));
+            }
+            Ok(None) => std::thread::sleep(HOOK_POLL_INTERVAL),
+            Err(e) => break Err(e.to_string()),
+        }
+    };
+    for _ in 0..readers {
+        if done.recv_timeout(OUTPUT_GRACE_TIME).is_err() {
+            break;
+        }
+    }
+    let output = String::from_utf8_lossy(&output.lock().expect("output isn't poisoned"))
+        .trim_end()
+        .to_owned();
+
+    match status {
+        Ok(status) => LogEntry::new(
+            &hook.ssid,
+            title(&status.to_string()),
+            status.success(),
+            output,
+        ),
+        Err(reason) => LogEntry::new(&hook.ssid, title(&reason), false, output),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Quality, SecurityType};
+
+    fn network(address: &str, value: u64, frequency: f64) -> WirelessNetwork {
+        WirelessNetwork {
+            address: address.to_owned(),
+            quality: Quality { value, limit: 70 },
+            frequency,
+            essid: String::from("home"),
+            security_type: SecurityType::Wpa2,
+            last_beacon: None,
+            access_points: 1,
+        }
+    }
+
+    fn hook(command: &str, timeout: Duration) -> Hook {
+        Hook {
+            interface: String::from("wlan0"),
+            ssid: String::from("home"),
+            event: HookEvent::Connect,
+            command: command.to_owned(),
+            timeout,
+        }
+    }
+
+    #[test]
+    fn parse_rules() {
+        let rules = parse(
+            r#"
+            [[network]]
+            ssid = "home"
+            band = "5"
+            autoconnect = true
+            passphrase = "correct horse"
+            mac = "permanent"
+            dns = ["192.168.1.2", "fd00::2"]
+            on_connect = "mount /mnt/nas"
+
+            [[network]]
+            bssid = "D4:1A:D1:51:67:F2"
+            mac = "random"
+            hook_timeout = 5
+            "#,
+        )
+        .unwrap();
+        assert_eq!(rules.networks.len(), 2);
+        let home = &rules.networks[0];
+        assert_eq!(home.band, Some(Band::Five));
+        assert_eq!(home.mac, Some(MacPolicy::Permanent));
+        assert_eq!(home.dns.len(), 2);
+        assert_eq!(home.hook_timeout, DEFAULT_HOOK_TIMEOUT);
+        assert_eq!(rules.networks[1].hook_timeout, 5);
+
+        assert!(parse("").unwrap().networks.is_empty());
+        for invalid in [
+            "[[network]]\nautoconnect = true\n",
+            "[[network]]\nssid = \"home\"\nbssid = \"D4:1A:D1\"\n",
+            "[[network]]\nssid = \"home\"\npassphrase = \"short\"\n",
+            "[[network]]\nssid = \"home\"\nband = \"60\"\n",
+            "[[network]]\nssid = \"home\"\nautoconect = true\n",
+        ] {
+            assert!(parse(invalid).is_err(), "accepted '{}'", invalid);
+        }
+    }
+
+    #[test]
+    fn find_rule() {
+        let rules = parse(
+            r#"
+            [[network]]
+            ssid = "home"
+            bssid = "d4:1a:d1:51:67:f2"
+            on_connect = "upstairs"
+
+            [[network]]
+            ssid = "home"
+            on_connect = "anywhere"
+            "#,
+        )
+        .unwrap();
+        let on_connect = |bssids: &[&str]| {
+            rules
+                .find("home", bssids)
+                .and_then(|rule| rule.on_connect.clone())
+        };
+        assert_eq!(on_connect(&["D4:1A:D1:51:67:F2"]).unwrap(), "upstairs");
+        assert_eq!(on_connect(&["00:11:22:33:44:55"]).unwrap(), "anywhere");
+        assert_eq!(on_connect(&[]).unwrap(), "anywhere");
+        assert!(rules.find("cafe", &[]).is_none());
+    }
+
+    #[test]
+    fn pick_access_point() {
+        let networks = vec![
+            network("00:00:00:00:00:01", 60, 2.412),
+            network("00:00:00:00:00:02", 30, 5.18),
+            network("00:00:00:00:00:03", 40, 5.5),
+        ];
+        let mut rule = Rule {
+            ssid: Some(String::from("home")),
+            band: Some(Band::Five),
+            mac: Some(MacPolicy::Random),
+            ..Default::default()
+        };
+        assert_eq!(
+            rule.connect_options("home", &networks),
+            ConnectOptions {
+                bssid: Some(String::from("00:00:00:00:00:03")),
+                mac: Some(MacPolicy::Random),
+            }
+        );
+
+        rule.preferred_bssid = Some(String::from("00:00:00:00:00:02"));
+        assert_eq!(
+            rule.connect_options("home", &networks).bssid.unwrap(),
+            "00:00:00:00:00:02"
+        );
+
+        // neither the preferred access point, nor one in the band is in range
+        rule.preferred_bssid = Some(String::from("00:00:00:00:00:09"));
+        rule.band = Some(Band::Six);
+        assert_eq!(rule.connect_options("home", &networks).bssid, None);
+    }
+
+    #[test]
+    fn run_hooks() {
+        let entry = run_hook(&hook(
+            "echo \"$SWELFI_EVENT $SWELFI_SSID on $SWELFI_INTERFACE\"; echo oops >&2",
+            Duration::from_secs(5),
+        ));
+        assert!(entry.success);
+        assert_eq!(entry.ssid, "home");
+        assert_eq!(entry.title, "connect hook: exit status: 0");
+        assert!(entry.output.contains("connect home on wlan0"));
+        assert!(entry.output.contains("oops"));
+
+        let entry = run_hook(&hook("exit 3", Duration::from_secs(5)));
+        assert!(!entry.success);
+        assert_eq!(entry.title, "connect hook: exit status: 3");
+    }
+
+    #[test]
+    fn hooks_time_out() {
+        let started = Instant::now();
+        let entry = run_hook(&hook(
+            "echo started; sleep 10 & sleep 10",
+            Duration::from_millis(200),
+        ));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(!entry.success);
+        assert_eq!(entry.title, "connect hook: timed out after 200ms");
+        assert_eq!(entry.output, "started");
+    }
+}
@@ -0,0 +1,456 @@
+use crate::{notify::NotificationSettings, portal, WirelessNetwork};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::{Ordering, Reverse},
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Bumped on incompatible changes to the settings file, with a migration from the previous
+/// version added to `MIGRATIONS`.
+pub const SETTINGS_VERSION: i64 = 2;
+/// Migrations from version n to n + 1, where n is the index + 1.
+const MIGRATIONS: [fn(&mut toml::Table); 1] = [migrate_sort_order];
+
+const SETTINGS_FILE: &str = "settings.toml";
+const DEFAULT_REFRESH_INTERVAL: u64 = 30;
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortColumn {
+    Ssid,
+    #[default]
+    Signal,
+    /// Sorted by frequency, so channels of the same band stay together.
+    Channel,
+    Band,
+    Security,
+    LastSeen,
+}
+
+impl SortColumn {
+    fn compare(&self, a: &WirelessNetwork, b: &WirelessNetwork) -> Ordering {
+        match self {
+            SortColumn::Ssid => a.essid.to_lowercase().cmp(&b.essid.to_lowercase()),
+            SortColumn::Signal => a.quality.value.cmp(&b.quality.value),
+            SortColumn::Channel => a.frequency.total_cmp(&b.frequency),
+            SortColumn::Band => a.band().cmp(&b.band()),
+            SortColumn::Security => a.security_type.strength().cmp(&b.security_type.strength()),
+            // the most recently seen first, networks without a beacon age last
+            SortColumn::LastSeen => a
+                .last_beacon
+                .unwrap_or(u64::MAX)
+                .cmp(&b.last_beacon.unwrap_or(u64::MAX)),
+        }
+    }
+}
+
+impl fmt::Display for SortColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortColumn::Ssid => write!(f, "SSID"),
+            SortColumn::Signal => write!(f, "Signal"),
+            SortColumn::Channel => write!(f, "Channel"),
+            SortColumn::Band => write!(f, "Band"),
+            SortColumn::Security => write!(f, "Security"),
+            SortColumn::LastSeen => write!(f, "Last seen"),
+        }
+    }
+}
+
+/// The column the networks are sorted by, networks which are equal in it are sorted by signal.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sort {
+    pub column: SortColumn,
+    pub descending: bool,
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Self::by(SortColumn::Signal)
+    }
+}
+
+impl Sort {
+    /// The strongest signal comes first, the other columns are ascending.
+    pub fn by(column: SortColumn) -> Self {
+        Self {
+            column,
+            descending: column == SortColumn::Signal,
+        }
+    }
+
+    /// Sorting by the same column again flips the direction.
+    pub fn toggle(&self, column: SortColumn) -> Self {
+        if column == self.column {
+            Self {
+                column,
+                descending: !self.descending,
+            }
+        } else {
+            Self::by(column)
+        }
+    }
+
+    pub fn sort(&self, networks: &mut [WirelessNetwork]) {
+        networks.sort_by_key(|wn| Reverse(wn.quality.value));
+        networks.sort_by(|a, b| {
+            let ordering = self.column.compare(a, b);
+            if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 2] = [Theme::Dark, Theme::Light];
+}
+
+impl FromStr for Theme {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "dark" => Ok(Theme::Dark),
+            "light" => Ok(Theme::Light),
+            _ => Err(anyhow!("unknown theme: {}, use dark, or light", s)),
+        }
+    }
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Theme::Dark => write!(f, "dark"),
+            Theme::Light => write!(f, "light"),
+        }
+    }
+}
+
+/// Settings given on the command line, they are used instead of the saved settings for this
+/// run, but aren't saved.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Overrides {
+    pub interface: Option<String>,
+    pub theme: Option<Theme>,
+    pub refresh_interval: Option<u64>,
+}
+
+/// Settings, which survive a restart, saved as TOML in the XDG config directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: i64,
+    /// The last selected interface.
+    pub interface: Option<String>,
+    pub window_size: Option<[f32; 2]>,
+    pub sort: Sort,
+    /// Seconds between rescans, 0 turns them off.
+    pub refresh_interval: u64,
+    pub theme: Theme,
+    pub notifications: NotificationSettings,
+    pub captive_portal: portal::Probe,
+    #[serde(skip)]
+    pub overrides: Overrides,
+    /// Settings of a newer version aren't overwritten.
+    #[serde(skip)]
+    read_only: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            interface: None,
+            window_size: None,
+            sort: Sort::default(),
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            theme: Theme::default(),
+            notifications: NotificationSettings::default(),
+            captive_portal: portal::Probe::default(),
+            overrides: Overrides::default(),
+            read_only: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the settings, or the defaults, if there are none, or they can't be read.
+    pub fn load() -> Self {
+        let path = match path() {
+            Some(path) => path,
+            None => return Self::default(),
+        };
+        match Self::read(&path) {
+            Ok(settings) => settings,
+            Err(e) => {
+                log::warn!(
+                    "Error while reading settings from {}, using the defaults: {}",
+                    path.display(),
+                    e
+                );
+                Self {
+                    // keep broken, or newer settings around
+                    read_only: path.exists(),
+                    ..Self::default()
+                }
+            }
+        }
+    }
+
+    fn read(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self) {
+        if self.read_only {
+            return;
+        }
+        let path = match path() {
+            Some(path) => path,
+            None => return,
+        };
+        let result = toml::to_string(self)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                Ok(std::fs::write(&path, contents)?)
+            });
+        if let Err(e) = result {
+            log::error!("Error while saving settings to {}: {}", path.display(), e);
+        }
+    }
+
+    pub fn interface(&self) -> Option<&str> {
+        self.overrides
+            .interface
+            .as_deref()
+            .or(self.interface.as_deref())
+    }
+
+    pub fn theme(&self) -> Theme {
+        self.overrides.theme.unwrap_or(self.theme)
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.overrides.theme = None;
+    }
+
+    pub fn refresh_interval(&self) -> Option<std::time::Duration> {
+        match self
+            .overrides
+            .refresh_interval
+            .unwrap_or(self.refresh_interval)
+        {
+            0 => None,
+            secs => Some(std::time::Duration::from_secs(secs)),
+        }
+    }
+
+    pub fn set_refresh_interval(&mut self, secs: u64) {
+        self.refresh_interval = secs;
+        self.overrides.refresh_interval = None;
+    }
+}
+
+/// `$XDG_CONFIG_HOME/swelfi`, or `~/.config/swelfi`.
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|dir| dir.join("swelfi"))
+}
+
+fn path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(SETTINGS_FILE))
+}
+
+/// Version 2 replaced `sort_order` with `sort`, which has more columns and a direction.
+fn migrate_sort_order(table: &mut toml::Table) {
+    let column = match table
+        .remove("sort_order")
+        .as_ref()
+        .and_then(toml::Value::as_str)
+    {
+        Some("name") => "ssid",
+        Some("frequency") => "channel",
+        _ => return,
+    };
+    let sort = toml::Table::from_iter([
+        (String::from("column"), toml::Value::from(column)),
+        (String::from("descending"), toml::Value::from(false)),
+    ]);
+    table.insert(String::from("sort"), toml::Value::Table(sort));
+}
+
+/// Parses the settings and migrates them to the current version, settings without a version
+/// are taken as the current version.
+fn parse(contents: &str) -> Result<Settings> {
+    let mut table: toml::Table = contents.parse()?;
+    let mut version = match table.get("version") {
+        Some(toml::Value::Integer(version)) => *version,
+        Some(_) => return Err(anyhow!("version isn't a number")),
+        None => SETTINGS_VERSION,
+    };
+    if version > SETTINGS_VERSION {
+        return Err(anyhow!(
+            "settings version {} is newer than the supported version {}",
+            version,
+            SETTINGS_VERSION
+        ));
+    }
+    if version < 1 {
+        return Err(anyhow!("invalid settings version: {}", version));
+    }
+    while version < SETTINGS_VERSION {
+        MIGRATIONS[version as usize - 1](&mut table);
+        version += 1;
+    }
+    table.insert(String::from("version"), toml::Value::Integer(version));
+    Ok(table.try_into()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Quality, SecurityType};
+
+    #[test]
+    fn roundtrip() {
+        let settings = Settings {
+            interface: Some(String::from("wlan1")),
+            window_size: Some([800.0, 600.0]),
+            sort: Sort::by(SortColumn::Ssid),
+            theme: Theme::Light,
+            ..Default::default()
+        };
+        let contents = toml::to_string(&settings).unwrap();
+        assert_eq!(parse(&contents).unwrap(), settings);
+    }
+
+    #[test]
+    fn parse_settings() {
+        let settings = parse(concat!(
+            "[sort]\ncolumn = \"last_seen\"\ndescending = false\n",
+            "[notifications]\nreconnected = false\n"
+        ))
+        .unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.sort, Sort::by(SortColumn::LastSeen));
+        assert_eq!(settings.refresh_interval, DEFAULT_REFRESH_INTERVAL);
+        assert!(!settings.notifications.reconnected);
+        assert!(settings.notifications.connection_lost);
+
+        assert!(parse("version = 99\n").is_err());
+        assert!(parse("version = \"one\"\n").is_err());
+        assert!(parse("[sort]\ncolumn = \"random\"\n").is_err());
+    }
+
+    #[test]
+    fn migrate_settings() {
+        let settings =
+            parse("version = 1\nsort_order = \"frequency\"\ntheme = \"light\"\n").unwrap();
+        assert_eq!(settings.version, 2);
+        assert_eq!(settings.sort, Sort::by(SortColumn::Channel));
+        assert_eq!(settings.theme, Theme::Light);
+
+        let settings = parse("version = 1\nsort_order = \"signal\"\n").unwrap();
+        assert_eq!(settings.sort, Sort::default());
+        assert!(parse("version = 0\n").is_err());
+    }
+
+    #[test]
+    fn overrides() {
+        let mut settings = Settings {
+            interface: Some(String::from("wlan0")),
+            overrides: Overrides {
+                interface: Some(String::from("wlan1")),
+                theme: Some(Theme::Light),
+                refresh_interval: Some(0),
+            },
+            ..Default::default()
+        };
+        assert_eq!(settings.interface(), Some("wlan1"));
+        assert_eq!(settings.theme(), Theme::Light);
+        assert_eq!(settings.refresh_interval(), None);
+        assert!(!toml::to_string(&settings).unwrap().contains("wlan1"));
+
+        settings.set_refresh_interval(10);
+        assert_eq!(
+            settings.refresh_interval(),
+            Some(std::time::Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn sort_networks() {
+        let network = |essid: &str, value, frequency, last_beacon| WirelessNetwork {
+            address: String::from("D4:1A:D1:51:67:F2"),
+            quality: Quality { value, limit: 70 },
+            frequency,
+            essid: essid.to_owned(),
+            security_type: if value == 20 {
+                SecurityType::Invalid
+            } else {
+                SecurityType::Wpa2
+            },
+            last_beacon,
+            access_points: 1,
+        };
+        let mut networks = vec![
+            network("b", 30, 5.18, Some(100)),
+            network("A", 20, 2.412, None),
+            network("b", 60, 2.437, Some(2000)),
+        ];
+        let order = |networks: &[WirelessNetwork]| {
+            networks
+                .iter()
+                .map(|wn| wn.quality.value)
+                .collect::<Vec<_>>()
+        };
+
+        let mut sort = Sort::default();
+        sort.sort(&mut networks);
+        assert_eq!(order(&networks), vec![60, 30, 20]);
+        sort = sort.toggle(SortColumn::Signal);
+        sort.sort(&mut networks);
+        assert_eq!(order(&networks), vec![20, 30, 60]);
+
+        for (column, expected) in [
+            (SortColumn::Ssid, vec![20, 60, 30]),
+            (SortColumn::Channel, vec![20, 60, 30]),
+            (SortColumn::Band, vec![60, 20, 30]),
+            (SortColumn::Security, vec![20, 60, 30]),
+            (SortColumn::LastSeen, vec![30, 60, 20]),
+        ] {
+            sort = sort.toggle(column);
+            assert!(!sort.descending);
+            sort.sort(&mut networks);
+            assert_eq!(order(&networks), expected, "sorted by {}", column);
+        }
+        sort = sort.toggle(SortColumn::LastSeen);
+        sort.sort(&mut networks);
+        assert_eq!(order(&networks), vec![20, 60, 30]);
+    }
+}
@@ -0,0 +1,733 @@
+use crate::{
+    app::{Event, Repaint},
+    backend::Backend,
+    helper::ConnectOptions,
+    rfkill::{RfkillEvent, RfkillState},
+};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    f64::consts::TAU,
+    net::IpAddr,
+    path::Path,
+    sync::{mpsc::Sender, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Seconds the signal takes to drift away and back.
+const DRIFT_PERIOD: f64 = 60.0;
+/// Quality is reported out of this, like most drivers do.
+const QUALITY_LIMIT: i64 = 70;
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Security {
+    Open,
+    Wpa,
+    #[default]
+    Wpa2,
+    Wpa3,
+}
+
+/// How connecting to an access point ends.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    #[default]
+    Connects,
+    Fails,
+    /// Whatever passphrase is given.
+    WrongPassword,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccessPoint {
+    pub ssid: String,
+    /// Made up from its position in the scenario, if left out.
+    #[serde(default)]
+    pub bssid: Option<String>,
+    #[serde(default)]
+    pub security: Security,
+    #[serde(default = "default_channel")]
+    pub channel: u32,
+    /// In dBm.
+    #[serde(default = "default_signal")]
+    pub signal: f64,
+    /// How far the signal drifts away from `signal`, in dB.
+    #[serde(default)]
+    pub drift: f64,
+    /// Seconds after the start, when it comes into range.
+    #[serde(default)]
+    pub appears: f64,
+    /// Seconds after the start, when it goes out of range again.
+    #[serde(default)]
+    pub disappears: Option<f64>,
+    /// Connecting needs it, if set, otherwise any passphrase is taken.
+    #[serde(default)]
+    pub passphrase: Option<String>,
+    #[serde(default)]
+    pub connect: Outcome,
+}
+
+fn default_channel() -> u32 {
+    6
+}
+
+fn default_signal() -> f64 {
+    -60.0
+}
+
+impl AccessPoint {
+    fn bssid(&self) -> &str {
+        self.bssid.as_deref().unwrap_or_default()
+    }
+
+    fn in_range(&self, time: f64) -> bool {
+        time >= self.appears && self.disappears.is_none_or(|disappears| time < disappears)
+    }
+
+    /// In dBm, every access point drifts out of step with the others.
+    fn signal_at(&self, time: f64) -> f64 {
+        let phase = self.bssid().bytes().map(f64::from).sum::<f64>();
+        self.signal + self.drift * (TAU * time / DRIFT_PERIOD + phase).sin()
+    }
+
+    /// In MHz, channels above 14 are in the 5 GHz band.
+    fn frequency(&self) -> u32 {
+        match self.channel {
+            14 => 2484,
+            1..=13 => 2407 + 5 * self.channel,
+            _ => 5000 + 5 * self.channel,
+        }
+    }
+}
+
+/// The environment to simulate, see the README for the file format.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    #[serde(default = "default_interfaces")]
+    pub interfaces: Vec<String>,
+    /// Seconds connecting takes.
+    #[serde(default)]
+    pub connect_time: f64,
+    #[serde(default, rename = "access_point")]
+    pub access_points: Vec<AccessPoint>,
+}
+
+fn default_interfaces() -> Vec<String> {
+    vec![String::from("wlan0")]
+}
+
+impl Scenario {
+    pub fn parse(contents: &str) -> Result<Self> {
+        let mut scenario: Scenario = toml::from_str(contents)?;
+        if scenario.interfaces.is_empty() {
+            return Err(anyhow!("a scenario needs an interface"));
+        }
+        if !scenario.connect_time.is_finite() || scenario.connect_time < 0.0 {
+            return Err(anyhow!("invalid connect_time: {}", scenario.connect_time));
+        }
+        for (index, access_point) in scenario.access_points.iter_mut().enumerate() {
+            if access_point.channel == 0 {
+                return Err(anyhow!("invalid channel for {}", access_point.ssid));
+            }
+            access_point
+                .bssid
+                .get_or_insert_with(|| format!("02:00:00:00:01:{:02x}", index + 1));
+        }
+        Ok(scenario)
+    }
+}
+
+/// What an interface is doing.
+#[derive(Debug, Clone, Default)]
+struct Link {
+    down: bool,
+    /// Soft blocked by its rfkill switch, which has the index of the interface.
+    blocked: bool,
+    /// The access point it's connected to.
+    connected: Option<usize>,
+}
+
+/// Answers like the commands would in the scenario, as time goes by.
+pub struct Simulation {
+    scenario: Scenario,
+    links: Mutex<HashMap<String, Link>>,
+    /// The SSIDs connected to, like wpa_supplicant saves them.
+    saved_networks: Mutex<Vec<String>>,
+    /// Get the changes of the rfkill switches.
+    rfkill_watchers: Mutex<Vec<(Sender<Event>, Repaint)>>,
+    started: Instant,
+}
+
+impl Simulation {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("reading {} failed: {}", path.display(), e))?;
+        let scenario = Scenario::parse(&contents)
+            .map_err(|e| anyhow!("invalid scenario {}: {}", path.display(), e))?;
+        Ok(Self::new(scenario))
+    }
+
+    pub fn new(scenario: Scenario) -> Self {
+        let links = scenario
+            .interfaces
+            .iter()
+            .map(|interface| (interface.clone(), Link::default()))
+            .collect();
+        Self {
+            scenario,
+            links: Mutex::new(links),
+            saved_networks: Mutex::new(vec![]),
+            rfkill_watchers: Mutex::new(vec![]),
+            started: Instant::now(),
+        }
+    }
+
+    fn rfkill_index(&self, interface: &str) -> Result<u32> {
+        self.scenario
+            .interfaces
+            .iter()
+            .position(|name| name == interface)
+            .map(|index| index as u32)
+            .ok_or_else(|| anyhow!("{} doesn't exist", interface))
+    }
+
+    /// Like the kernel, blocking brings the interface down, but unblocking doesn't bring it up.
+    fn block(&self, index: u32, blocked: bool) -> Result<()> {
+        let interface = self
+            .scenario
+            .interfaces
+            .get(index as usize)
+            .ok_or_else(|| anyhow!("there is no rfkill switch {}", index))?;
+        self.update(interface, |link| {
+            link.blocked = blocked;
+            if blocked {
+                link.down = true;
+                link.connected = None;
+            }
+        });
+        let mut watchers = self
+            .rfkill_watchers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        watchers.retain(|(event_sender, repaint)| {
+            let sent = event_sender
+                .send(Event::UpdateRfkill(RfkillEvent::changed(index, blocked)))
+                .is_ok();
+            repaint.request_repaint();
+            sent
+        });
+        Ok(())
+    }
+
+    fn time(&self) -> f64 {
+        self.started.elapsed().as_secs_f64()
+    }
+
+    /// The state of the interface, a connection is lost when the access point went away.
+    fn link(&self, interface: &str, time: f64) -> Result<Link> {
+        let mut links = self.links.lock().unwrap_or_else(|e| e.into_inner());
+        let link = links
+            .get_mut(interface)
+            .ok_or_else(|| anyhow!("{} doesn't exist", interface))?;
+        if link
+            .connected
+            .is_some_and(|index| !self.scenario.access_points[index].in_range(time))
+        {
+            link.connected = None;
+        }
+        Ok(link.clone())
+    }
+
+    fn update(&self, interface: &str, update: impl FnOnce(&mut Link)) {
+        let mut links = self.links.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(link) = links.get_mut(interface) {
+            update(link);
+        }
+    }
+
+    /// In the shape of `iw dev`, which is parsed for `iw dev <interface> info` as well.
+    fn interface_at(&self, index: usize, interface: &str, time: f64) -> Result<String> {
+        let link = self.link(interface, time)?;
+        let mut output = format!(
+            "phy#{}\n\tInterface {}\n\t\tifindex {}\n\t\twdev 0x{:x}\n\t\taddr 02:00:00:00:00:{:02x}\n",
+            index,
+            interface,
+            index + 3,
+            index + 1,
+            index + 1
+        );
+        if let Some(access_point) = link.connected.map(|i| &self.scenario.access_points[i]) {
+            output.push_str(&format!("\t\tssid {}\n", access_point.ssid));
+        }
+        output.push_str("\t\ttype managed\n");
+        if let Some(access_point) = link.connected.map(|i| &self.scenario.access_points[i]) {
+            output.push_str(&format!(
+                "\t\tchannel {} ({} MHz), width: 20 MHz, center1: {} MHz\n",
+                access_point.channel,
+                access_point.frequency(),
+                access_point.frequency()
+            ));
+        }
+        output.push_str("\t\ttxpower 22.00 dBm\n");
+        Ok(output)
+    }
+
+    fn iw_dev_at(&self, time: f64) -> Result<String> {
+        let mut output = String::new();
+        for (index, interface) in self.scenario.interfaces.iter().enumerate() {
+            output.push_str(&self.interface_at(index, interface, time)?);
+        }
+        Ok(output)
+    }
+
+    fn iw_info_at(&self, interface: &str, time: f64) -> Result<String> {
+        let index = self.rfkill_index(interface)?;
+        self.interface_at(index as usize, interface, time)
+    }
+
+    fn ip_link_at(&self, interface: &str, time: f64) -> Result<String> {
+        let link = self.link(interface, time)?;
+        let (flags, state) = match (link.down, link.connected) {
+            (true, _) => ("BROADCAST,MULTICAST", "DOWN"),
+            (false, None) => ("NO-CARRIER,BROADCAST,MULTICAST,UP", "DOWN"),
+            (false, Some(_)) => ("BROADCAST,MULTICAST,UP,LOWER_UP", "UP"),
+        };
+        Ok(format!(
+            "3: {}: <{}> mtu 1500 qdisc noqueue state {} mode DORMANT group default qlen 1000\n",
+            interface, flags, state
+        ))
+    }
+
+    /// Like `iwlist`, with the access points in range by then.
+    fn scan_at(&self, interface: &str, time: f64) -> Result<String> {
+        if self.link(interface, time)?.down {
+            return Err(anyhow!("scanning {} failed: network is down", interface));
+        }
+        let in_range: Vec<&AccessPoint> = self
+            .scenario
+            .access_points
+            .iter()
+            .filter(|access_point| access_point.in_range(time))
+            .collect();
+        if in_range.is_empty() {
+            return Ok(format!("{:10}No scan results\n", interface));
+        }
+        let mut output = format!("{:10}Scan completed :\n", interface);
+        for (index, access_point) in in_range.into_iter().enumerate() {
+            let signal = access_point.signal_at(time).round() as i64;
+            output.push_str(&format!(
+                "          Cell {:02} - Address: {}\n\
+                 \x20                   Channel:{}\n\
+                 \x20                   Frequency:{} GHz (Channel {})\n\
+                 \x20                   Quality={}/{}  Signal level={} dBm\n\
+                 \x20                   Encryption key:{}\n\
+                 \x20                   ESSID:\"{}\"\n\
+                 \x20                   Extra: Last beacon: 100ms ago\n",
+                index + 1,
+                access_point.bssid(),
+                access_point.channel,
+                access_point.frequency() as f64 / 1000.0,
+                access_point.channel,
+                (signal + 110).clamp(0, QUALITY_LIMIT),
+                QUALITY_LIMIT,
+                signal,
+                if access_point.security == Security::Open {
+                    "off"
+                } else {
+                    "on"
+                },
+                access_point.ssid
+            ));
+            // the parser tells the security apart by the first of these
+            let version = match access_point.security {
+                Security::Open => continue,
+                Security::Wpa => "WPA",
+                Security::Wpa2 => "WPA2",
+                Security::Wpa3 => "WPA3",
+            };
+            output.push_str(&format!(
+                "                    IE: IEEE 802.11i/{} Version 1\n",
+                version
+            ));
+        }
+        Ok(output)
+    }
+
+    /// Picks the strongest access point of the network in range, or the requested one, and
+    /// returns its index, or why connecting to it fails.
+    fn associate(
+        &self,
+        interface: &str,
+        ssid: &str,
+        passphrase: Option<&str>,
+        options: &ConnectOptions,
+        time: f64,
+    ) -> Result<usize> {
+        if self.link(interface, time)?.down {
+            return Err(anyhow!("{} is down", interface));
+        }
+        let (index, access_point) = self
+            .scenario
+            .access_points
+            .iter()
+            .enumerate()
+            .filter(|(_, access_point)| {
+                access_point.ssid == ssid
+                    && access_point.in_range(time)
+                    && options
+                        .bssid
+                        .as_ref()
+                        .is_none_or(|bssid| bssid.eq_ignore_ascii_case(access_point.bssid()))
+            })
+            .max_by(|(_, a), (_, b)| a.signal_at(time).total_cmp(&b.signal_at(time)))
+            .ok_or_else(|| anyhow!("{} isn't in range", ssid))?;
+        let wrong_password = access_point.connect == Outcome::WrongPassword
+            || (access_point.security != Security::Open
+                && access_point.passphrase.is_some()
+                && access_point.passphrase.as_deref() != passphrase);
+        if access_point.connect == Outcome::Fails {
+            Err(anyhow!("associating with {} failed", ssid))
+        } else if wrong_password {
+            Err(anyhow!("wrong passphrase for {}", ssid))
+        } else {
+            Ok(index)
+        }
+    }
+}
+
+impl Backend for Simulation {
+    fn iw_dev(&self) -> Result<String> {
+        self.iw_dev_at(self.time())
+    }
+
+    fn iw_info(&self, interface: &str) -> Result<String> {
+        self.iw_info_at(interface, self.time())
+    }
+
+    fn ip_link(&self, interface: &str) -> Result<String> {
+        self.ip_link_at(interface, self.time())
+    }
+
+    fn scan(&self, interface: &str) -> Result<String> {
+        self.scan_at(interface, self.time())
+    }
+
+    /// Drops the current connection and takes `connect_time` to connect, or fail.
+    fn connect(
+        &self,
+        interface: &str,
+        ssid: &str,
+        passphrase: Option<&str>,
+        options: &ConnectOptions,
+    ) -> Result<()> {
+        self.link(interface, self.time())?;
+        self.update(interface, |link| link.connected = None);
+        {
+            let mut saved_networks = self
+                .saved_networks
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            if !saved_networks.iter().any(|saved| saved == ssid) {
+                saved_networks.push(ssid.to_owned());
+            }
+        }
+        std::thread::sleep(Duration::from_secs_f64(self.scenario.connect_time));
+        let index = self.associate(interface, ssid, passphrase, options, self.time())?;
+        self.update(interface, |link| link.connected = Some(index));
+        Ok(())
+    }
+
+    fn disconnect(&self, interface: &str) -> Result<()> {
+        self.link(interface, self.time())?;
+        self.update(interface, |link| link.connected = None);
+        Ok(())
+    }
+
+    fn set_link(&self, interface: &str, on: bool) -> Result<()> {
+        if on && self.link(interface, self.time())?.blocked {
+            return Err(anyhow!(
+                "RTNETLINK answers: Operation not possible due to RF-kill"
+            ));
+        }
+        self.update(interface, |link| {
+            link.down = !on;
+            if !on {
+                link.connected = None;
+            }
+        });
+        Ok(())
+    }
+
+    fn is_system(&self) -> bool {
+        false
+    }
+
+    fn rfkill(&self, interface: &str) -> Result<RfkillState> {
+        Ok(RfkillState {
+            index: Some(self.rfkill_index(interface)?),
+            soft_blocked: self.link(interface, self.time())?.blocked,
+            hard_blocked: false,
+        })
+    }
+
+    /// Sends the switches right away, like opening /dev/rfkill does.
+    fn watch_rfkill(&self, event_sender: Sender<Event>, repaint: Repaint) {
+        for (index, interface) in self.scenario.interfaces.iter().enumerate() {
+            let blocked = self
+                .link(interface, self.time())
+                .is_ok_and(|link| link.blocked);
+            let _ = event_sender.send(Event::UpdateRfkill(RfkillEvent::added(
+                index as u32,
+                blocked,
+            )));
+        }
+        repaint.request_repaint();
+        self.rfkill_watchers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((event_sender, repaint));
+    }
+
+    fn set_rfkill(&self, index: u32, blocked: bool) -> Result<()> {
+        self.block(index, blocked)
+    }
+
+    fn set_all_rfkill(&self, blocked: bool) -> Result<()> {
+        for index in 0..self.scenario.interfaces.len() {
+            self.block(index as u32, blocked)?;
+        }
+        Ok(())
+    }
+
+    fn saved_networks(&self, interface: &str) -> Result<Vec<String>> {
+        self.link(interface, self.time())?;
+        Ok(self
+            .saved_networks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone())
+    }
+
+    /// There is no resolver to tell, so the servers are only checked.
+    fn set_dns(&self, interface: &str, servers: &[IpAddr]) -> Result<()> {
+        self.link(interface, self.time())?;
+        log::info!("simulating dns servers {:?} for {}", servers, interface);
+        Ok(())
+    }
+
+    fn renew_lease(&self, interface: &str) -> Result<()> {
+        match self.link(interface, self.time())?.connected {
+            Some(_) => Ok(()),
+            None => Err(anyhow!("{} isn't connected", interface)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_iw, parse_scan, SecurityType};
+
+    const SCENARIO: &str = r#"
+        interfaces = ["wlan0", "wlan1"]
+
+        [[access_point]]
+        ssid = "home"
+        channel = 36
+        signal = -50
+        drift = 10
+        passphrase = "correct horse"
+
+        [[access_point]]
+        ssid = "home"
+        bssid = "02:00:00:00:02:02"
+        signal = -80
+
+        [[access_point]]
+        ssid = "cafe"
+        security = "open"
+        appears = 10
+        disappears = 20
+
+        [[access_point]]
+        ssid = "broken"
+        connect = "fails"
+    "#;
+
+    fn simulation() -> Simulation {
+        Simulation::new(Scenario::parse(SCENARIO).unwrap())
+    }
+
+    fn networks(simulation: &Simulation, time: f64) -> Vec<(String, SecurityType, usize)> {
+        parse_scan(&simulation.scan_at("wlan0", time).unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|wn| (wn.essid, wn.security_type, wn.access_points))
+            .collect()
+    }
+
+    #[test]
+    fn parse_scenarios() {
+        let scenario = Scenario::parse(SCENARIO).unwrap();
+        assert_eq!(scenario.access_points.len(), 4);
+        let home = &scenario.access_points[0];
+        assert_eq!(home.bssid(), "02:00:00:00:01:01");
+        assert_eq!(home.security, Security::Wpa2);
+        assert_eq!(home.frequency(), 5180);
+        assert_eq!(scenario.access_points[1].bssid(), "02:00:00:00:02:02");
+        assert_eq!(scenario.access_points[2].frequency(), 2437);
+
+        assert_eq!(
+            Scenario::parse("").unwrap().interfaces,
+            [String::from("wlan0")]
+        );
+        for invalid in [
+            "interfaces = []",
+            "connect_time = -1.0",
+            "[[access_point]]\nssid = \"x\"\nchannel = 0",
+            "[[access_point]]\nssid = \"x\"\nconnect = \"maybe\"",
+            "[[access_point]]\nssid = \"x\"\ncolor = \"blue\"",
+        ] {
+            assert!(Scenario::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn access_points_come_and_go() {
+        let simulation = simulation();
+        assert_eq!(
+            networks(&simulation, 0.0),
+            [
+                (String::from("home"), SecurityType::Wpa2, 2),
+                (String::from("broken"), SecurityType::Wpa2, 1),
+            ]
+        );
+        let networks_later = networks(&simulation, 15.0);
+        assert!(networks_later.contains(&(String::from("cafe"), SecurityType::Invalid, 1)));
+        assert_eq!(networks(&simulation, 20.0).len(), 2);
+
+        // the signal drifts within its range
+        let home = &simulation.scenario.access_points[0];
+        let signals: Vec<f64> = (0..60).map(|time| home.signal_at(time as f64)).collect();
+        assert!(signals
+            .iter()
+            .all(|signal| (-60.0..=-40.0).contains(signal)));
+        assert!(signals.iter().any(|signal| *signal > -45.0));
+        assert!(signals.iter().any(|signal| *signal < -55.0));
+
+        let interfaces = parse_iw(&simulation.iw_dev_at(0.0).unwrap()).unwrap().1;
+        let names: Vec<&str> = interfaces.iter().map(|wi| wi.name.as_str()).collect();
+        assert_eq!(names, ["wlan0", "wlan1"]);
+        assert!(simulation.iw_info_at("wlan9", 0.0).is_err());
+    }
+
+    #[test]
+    fn connect_outcomes() {
+        let simulation = simulation();
+        let connect = |ssid, passphrase, time| {
+            simulation
+                .associate("wlan0", ssid, passphrase, &ConnectOptions::default(), time)
+                .map(|index| simulation.scenario.access_points[index].bssid().to_owned())
+                .map_err(|e| e.to_string())
+        };
+        assert_eq!(
+            connect("home", Some("correct horse"), 0.0),
+            Ok(String::from("02:00:00:00:01:01"))
+        );
+        assert_eq!(
+            connect("home", Some("wrong"), 0.0),
+            Err(String::from("wrong passphrase for home"))
+        );
+        assert_eq!(
+            connect("broken", None, 0.0),
+            Err(String::from("associating with broken failed"))
+        );
+        assert_eq!(
+            connect("cafe", None, 0.0),
+            Err(String::from("cafe isn't in range"))
+        );
+        assert_eq!(
+            connect("cafe", None, 10.0),
+            Ok(String::from("02:00:00:00:01:03"))
+        );
+        let weak = ConnectOptions {
+            bssid: Some(String::from("02:00:00:00:02:02")),
+            ..Default::default()
+        };
+        assert_eq!(
+            simulation
+                .associate("wlan0", "home", None, &weak, 0.0)
+                .unwrap(),
+            1
+        );
+
+        // connected, until the access point goes away
+        simulation.update("wlan0", |link| link.connected = Some(2));
+        let info = simulation.iw_info_at("wlan0", 15.0).unwrap();
+        let interface = crate::interface(&info).unwrap().1;
+        assert_eq!(interface.connected_ssid.as_deref(), Some("cafe"));
+        assert_eq!(
+            interface.channel.map(|channel| channel.frequency),
+            Some(2437)
+        );
+        assert!(simulation
+            .ip_link_at("wlan0", 15.0)
+            .unwrap()
+            .contains("state UP"));
+        assert_eq!(
+            crate::interface(&simulation.iw_info_at("wlan0", 25.0).unwrap())
+                .unwrap()
+                .1
+                .connected_ssid,
+            None
+        );
+
+        simulation.set_link("wlan0", false).unwrap();
+        assert!(simulation
+            .ip_link_at("wlan0", 0.0)
+            .unwrap()
+            .contains("state DOWN"));
+        assert!(simulation.scan_at("wlan0", 0.0).is_err());
+        assert!(simulation.scan_at("wlan1", 0.0).is_ok());
+    }
+
+    #[test]
+    fn rfkill_and_saved_networks() {
+        let simulation = simulation();
+        let (event_sender, event_receiver) = std::sync::mpsc::channel();
+        simulation.watch_rfkill(event_sender, Repaint::new(|| ()));
+        let events = || -> Vec<(u32, bool)> {
+            event_receiver
+                .try_iter()
+                .map(|event| match event {
+                    Event::UpdateRfkill(event) => (event.index, event.soft),
+                    _ => panic!("expected an rfkill event"),
+                })
+                .collect()
+        };
+        assert_eq!(events(), [(0, false), (1, false)]);
+
+        simulation.set_all_rfkill(true).unwrap();
+        assert!(simulation.rfkill("wlan1").unwrap().soft_blocked);
+        assert!(simulation
+            .ip_link_at("wlan0", 0.0)
+            .unwrap()
+            .contains("state DOWN"));
+        assert!(simulation.set_link("wlan0", true).is_err());
+        simulation.set_rfkill(0, false).unwrap();
+        simulation.set_link("wlan0", true).unwrap();
+        assert!(simulation.set_rfkill(2, true).is_err());
+        assert_eq!(events(), [(0, true), (1, true), (0, false)]);
+
+        assert!(simulation
+            .connect("wlan0", "broken", None, &ConnectOptions::default())
+            .is_err());
+        assert_eq!(simulation.saved_networks("wlan0").unwrap(), ["broken"]);
+        assert!(simulation.renew_lease("wlan0").is_err());
+    }
+}
@@ -0,0 +1,301 @@
+use crate::{export::SCHEMA_VERSION, WirelessNetwork};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A scan in a recording, one JSON object per line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+    pub schema_version: u32,
+    /// Seconds since the unix epoch.
+    pub time: u64,
+    pub interface: String,
+    /// Where the scan was taken, like `room 3.14`.
+    pub location: Option<String>,
+    /// Where it was taken on the floor plan, from 0 to 1 from the top left corner.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<[f32; 2]>,
+    pub networks: Vec<WirelessNetwork>,
+}
+
+impl Sample {
+    /// A scan taken now, blank locations are left out.
+    pub fn new(
+        interface: &str,
+        location: Option<&str>,
+        position: Option<[f32; 2]>,
+        networks: &[WirelessNetwork],
+    ) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            interface: interface.to_owned(),
+            location: location
+                .map(str::trim)
+                .filter(|location| !location.is_empty())
+                .map(str::to_owned),
+            position,
+            networks: networks.to_vec(),
+        }
+    }
+}
+
+/// Appends every scan to a JSON lines file.
+#[derive(Debug)]
+pub struct Recorder {
+    pub path: PathBuf,
+    file: File,
+    /// Scans recorded since it was started.
+    pub samples: usize,
+}
+
+impl Recorder {
+    /// Appends to the file, if it exists already, so a survey can be continued.
+    pub fn start(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            path: path.to_owned(),
+            file,
+            samples: 0,
+        })
+    }
+
+    pub fn record(&mut self, sample: &Sample) -> Result<()> {
+        // a whole line at once, so an interrupted recording only loses the last scan
+        self.file
+            .write_all(format!("{}\n", serde_json::to_string(sample)?).as_bytes())?;
+        self.samples += 1;
+        Ok(())
+    }
+}
+
+/// An access point as seen in the scans of one location.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Seen {
+    pub network: WirelessNetwork,
+    /// In how many of the scans of the location it showed up.
+    pub scans: usize,
+    /// Signal quality in percent.
+    pub min: u64,
+    pub mean: u64,
+    pub max: u64,
+}
+
+/// The scans taken at one location.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    /// None for scans, which weren't labeled.
+    pub label: Option<String>,
+    pub scans: usize,
+    /// The strongest on average first.
+    pub networks: Vec<Seen>,
+}
+
+/// A recording loaded for browsing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recording {
+    pub samples: Vec<Sample>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    fn parse(contents: &str) -> Result<Self> {
+        let mut samples = vec![];
+        for (number, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut sample: Sample = serde_json::from_str(line)
+                .map_err(|e| anyhow!("line {} isn't a scan: {}", number + 1, e))?;
+            if sample.schema_version > SCHEMA_VERSION {
+                return Err(anyhow!(
+                    "line {} was recorded by a newer version of swelfi",
+                    number + 1
+                ));
+            }
+            // every recorded network is a single access point
+            for network in &mut sample.networks {
+                network.access_points = 1;
+            }
+            samples.push(sample);
+        }
+        Ok(Self { samples })
+    }
+
+    /// The scans grouped by location, in the order the locations were first recorded.
+    pub fn locations(&self) -> Vec<Location> {
+        let mut locations: Vec<(Option<String>, Vec<&Sample>)> = vec![];
+        for sample in &self.samples {
+            match locations
+                .iter_mut()
+                .find(|(label, _)| *label == sample.location)
+            {
+                Some((_, samples)) => samples.push(sample),
+                None => locations.push((sample.location.clone(), vec![sample])),
+            }
+        }
+        locations
+            .into_iter()
+            .map(|(label, samples)| Location {
+                label,
+                scans: samples.len(),
+                networks: seen(&samples),
+            })
+            .collect()
+    }
+}
+
+/// Aggregates the signal of every access point over the scans.
+fn seen(samples: &[&Sample]) -> Vec<Seen> {
+    let mut by_address: HashMap<&str, (WirelessNetwork, Vec<u64>)> = HashMap::new();
+    for network in samples.iter().flat_map(|sample| &sample.networks) {
+        by_address
+            .entry(&network.address)
+            .or_insert_with(|| (network.clone(), vec![]))
+            .1
+            .push(network.quality.percent());
+    }
+    let mut seen: Vec<Seen> = by_address
+        .into_values()
+        .map(|(network, signals)| Seen {
+            network,
+            scans: signals.len(),
+            min: signals.iter().copied().min().unwrap_or_default(),
+            mean: signals.iter().sum::<u64>() / signals.len() as u64,
+            max: signals.iter().copied().max().unwrap_or_default(),
+        })
+        .collect();
+    seen.sort_by(|a, b| {
+        b.mean
+            .cmp(&a.mean)
+            .then_with(|| a.network.essid.cmp(&b.network.essid))
+    });
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Quality, SecurityType};
+
+    fn network(essid: &str, address: &str, value: u64) -> WirelessNetwork {
+        WirelessNetwork {
+            address: address.to_owned(),
+            quality: Quality { value, limit: 70 },
+            frequency: 5.18,
+            essid: essid.to_owned(),
+            security_type: SecurityType::Wpa2,
+            last_beacon: None,
+            access_points: 1,
+        }
+    }
+
+    #[test]
+    fn record_and_load() {
+        let path = std::env::temp_dir().join(format!("swelfi-survey-{}.jsonl", std::process::id()));
+        let mut recorder = Recorder::start(&path).unwrap();
+        recorder
+            .record(&Sample::new(
+                "wlan0",
+                Some(" room 3.14 "),
+                None,
+                &[network("home", "AA:00:00:00:00:01", 56)],
+            ))
+            .unwrap();
+        recorder
+            .record(&Sample::new("wlan0", Some(""), Some([0.25, 0.5]), &[]))
+            .unwrap();
+        drop(recorder);
+        // recording again appends
+        Recorder::start(&path)
+            .unwrap()
+            .record(&Sample::new("wlan0", None, None, &[]))
+            .unwrap();
+
+        let recording = Recording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(recording.samples.len(), 3);
+        let sample = &recording.samples[0];
+        assert_eq!(sample.location.as_deref(), Some("room 3.14"));
+        assert_eq!(sample.networks, [network("home", "AA:00:00:00:00:01", 56)]);
+        assert_eq!(sample.position, None);
+        assert_eq!(recording.samples[1].location, None);
+        assert_eq!(recording.samples[1].position, Some([0.25, 0.5]));
+    }
+
+    #[test]
+    fn reject_invalid_lines() {
+        assert!(Recording::parse("\n\n").unwrap().samples.is_empty());
+        let error = Recording::parse(
+            "{\"schema_version\":1,\"time\":0,\"interface\":\"wlan0\",\"location\":null,\"networks\":[]}\n\
+             not json\n",
+        )
+        .unwrap_err();
+        assert!(error.to_string().starts_with("line 2 "), "{}", error);
+        assert!(Recording::parse(
+            "{\"schema_version\":99,\"time\":0,\"interface\":\"wlan0\",\"location\":null,\"networks\":[]}"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn group_by_location() {
+        let sample = |location: Option<&str>, networks| Sample {
+            schema_version: SCHEMA_VERSION,
+            time: 0,
+            interface: String::from("wlan0"),
+            location: location.map(str::to_owned),
+            position: None,
+            networks,
+        };
+        let recording = Recording {
+            samples: vec![
+                sample(
+                    Some("room 3.14"),
+                    vec![
+                        network("home", "AA:00:00:00:00:01", 35),
+                        network("guest", "AA:00:00:00:00:02", 14),
+                    ],
+                ),
+                sample(None, vec![network("home", "AA:00:00:00:00:01", 70)]),
+                sample(
+                    Some("room 3.14"),
+                    vec![network("home", "AA:00:00:00:00:01", 49)],
+                ),
+            ],
+        };
+
+        let locations = recording.locations();
+        assert_eq!(locations.len(), 2);
+        assert_eq!(locations[0].label.as_deref(), Some("room 3.14"));
+        assert_eq!(locations[0].scans, 2);
+        let summary: Vec<(&str, usize, u64, u64, u64)> = locations[0]
+            .networks
+            .iter()
+            .map(|seen| {
+                (
+                    seen.network.essid.as_str(),
+                    seen.scans,
+                    seen.min,
+                    seen.mean,
+                    seen.max,
+                )
+            })
+            .collect();
+        assert_eq!(summary, [("home", 2, 50, 60, 70), ("guest", 1, 20, 20, 20)]);
+        assert_eq!(locations[1].label, None);
+        assert_eq!(locations[1].networks[0].mean, 100);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{
+    app::AppState,
+    settings::{Sort, SortColumn},
+    Quality, WirelessNetwork,
+};
+use eframe::egui;
+use egui_extras::{Column, TableBuilder};
+
+const MAX_HEIGHT: f32 = 300.0;
+/// The header titles, clicking the ones with a column sorts by it.
+const COLUMNS: [(&str, Option<SortColumn>); 8] = [
+    ("Signal", Some(SortColumn::Signal)),
+    ("SSID", Some(SortColumn::Ssid)),
+    ("Security", Some(SortColumn::Security)),
+    ("Band", Some(SortColumn::Band)),
+    ("Ch", Some(SortColumn::Channel)),
+    ("APs", None),
+    ("Seen", Some(SortColumn::LastSeen)),
+    ("Status", None),
+];
+
+/// What was done in the table, it's applied by the caller, as the table only borrows the state.
+#[derive(Debug, Eq, PartialEq)]
+pub enum TableAction {
+    Select(String),
+    Sort(SortColumn),
+    /// Selects the network and opens the connect dialog.
+    Connect(String),
+}
+
+/// The networks, which pass the filter, only the visible rows are laid out, so dense
+/// environments with hundreds of networks stay fast. With `focus`, the connected network is
+/// selected and scrolled to.
+pub fn network_table(ui: &mut egui::Ui, app_state: &AppState, focus: bool) -> Option<TableAction> {
+    let networks = match app_state.wlan_networks {
+        Some(ref networks) => networks,
+        None => {
+            ui.spinner();
+            return None;
+        }
+    };
+    let visible: Vec<&WirelessNetwork> = networks
+        .iter()
+        .filter(|wn| app_state.filter.matches(wn, &app_state.saved_networks))
+        .collect();
+    if app_state.filter.is_active() {
+        ui.label(format!("{} of {} networks", visible.len(), networks.len()));
+    }
+
+    let mut action = None;
+    let connected = app_state.connected_wlan_network.as_ref();
+    let focused = focus
+        .then(|| visible.iter().position(|wn| Some(&wn.essid) == connected))
+        .flatten();
+    if let Some(index) = focused {
+        action = Some(TableAction::Select(visible[index].id()));
+    }
+
+    let row_height = ui.text_style_height(&egui::TextStyle::Body) + 4.0;
+    let mut table = TableBuilder::new(ui)
+        .striped(true)
+        .resizable(true)
+        .sense(egui::Sense::click())
+        .max_scroll_height(MAX_HEIGHT)
+        .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+        .column(Column::auto().at_least(24.0))
+        .column(Column::initial(160.0).at_least(60.0).clip(true))
+        .column(Column::auto())
+        .column(Column::auto())
+        .column(Column::auto())
+        .column(Column::auto())
+        .column(Column::auto())
+        .column(Column::remainder().at_least(80.0));
+    if let Some(index) = focused {
+        table = table.scroll_to_row(index, Some(egui::Align::Center));
+    }
+    let sort = app_state.settings.sort;
+    table
+        .header(row_height, |mut header| {
+            for (title, column) in COLUMNS {
+                header.col(|ui| match column {
+                    Some(column) => {
+                        if sort_header(ui, title, sort, column).clicked() {
+                            action = Some(TableAction::Sort(column));
+                        }
+                    }
+                    None => {
+                        ui.strong(title);
+                    }
+                });
+            }
+        })
+        .body(|body| {
+            body.rows(row_height, visible.len(), |mut row| {
+                let wn = visible[row.index()];
+                row.set_selected(app_state.selected_wlan_network == wn.id());
+                row.col(|ui| signal_bars(ui, &wn.quality));
+                row.col(|ui| {
+                    ui.label(&wn.essid);
+                });
+                row.col(|ui| {
+                    ui.label(wn.security_type.label());
+                });
+                row.col(|ui| {
+                    ui.label(wn.band().to_string());
+                });
+                row.col(|ui| {
+                    ui.label(wn.channel().to_string());
+                });
+                row.col(|ui| {
+                    ui.label(wn.access_points.to_string());
+                });
+                row.col(|ui| {
+                    ui.label(last_seen(wn.last_beacon));
+                });
+                row.col(|ui| {
+                    if Some(&wn.essid) == connected {
+                        ui.label(
+                            egui::RichText::new("connected")
+                                .strong()
+                                .color(ui.visuals().selection.stroke.color),
+                        );
+                        if app_state.login_required().is_some() {
+                            ui.colored_label(ui.visuals().warn_fg_color, "login required");
+                        }
+                    }
+                    if app_state.saved_networks.contains(&wn.essid) {
+                        ui.weak("saved");
+                    }
+                });
+
+                let response = row.response();
+                if response.clicked() {
+                    action = Some(TableAction::Select(wn.id()));
+                }
+                response.context_menu(|ui| {
+                    if ui.button("Connect…").clicked() {
+                        action = Some(TableAction::Connect(wn.id()));
+                        ui.close_menu();
+                    }
+                    if ui.button("Copy SSID").clicked() {
+                        ui.ctx().copy_text(wn.essid.clone());
+                        ui.close_menu();
+                    }
+                    if ui.button("Copy BSSID").clicked() {
+                        ui.ctx().copy_text(wn.address.clone());
+                        ui.close_menu();
+                    }
+                });
+            });
+        });
+    action
+}
+
+fn sort_header(ui: &mut egui::Ui, title: &str, sort: Sort, column: SortColumn) -> egui::Response {
+    let label = match (sort.column == column, sort.descending) {
+        (true, true) => format!("{} ⬇", title),
+        (true, false) => format!("{} ⬆", title),
+        (false, _) => title.to_string(),
+    };
+    ui.add(egui::Button::new(egui::RichText::new(label).strong()).frame(false))
+        .on_hover_text(format!("Sort by {}", column.to_string().to_lowercase()))
+}
+
+/// Four bars of increasing height, the ones above the quality are dimmed.
+fn signal_bars(ui: &mut egui::Ui, quality: &Quality) {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(20.0, 12.0), egui::Sense::hover());
+    let bars = quality.bars();
+    for i in 0..4 {
+        let height = rect.height() * (i + 1) as f32 / 4.0;
+        let left = rect.left() + i as f32 * 5.0;
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(left, rect.bottom() - height),
+            egui::pos2(left + 3.0, rect.bottom()),
+        );
+        let color = if i < bars {
+            ui.visuals().strong_text_color()
+        } else {
+            ui.visuals().weak_text_color().gamma_multiply(0.4)
+        };
+        ui.painter().rect_filled(bar, 1.0, color);
+    }
+    response.on_hover_text(format!("{}%", quality.percent()));
+}
+
+fn last_seen(last_beacon: Option<u64>) -> String {
+    match last_beacon {
+        Some(ms) => format!("{:.1}s ago", ms as f64 / 1000.0),
+        None => String::from("–"),
+    }
+}
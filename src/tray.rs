@@ -0,0 +1,307 @@
+use crate::{app::AppState, app::Repaint, SecurityType};
+use ksni::{
+    blocking::{Handle, TrayMethods},
+    menu::{CheckmarkItem, StandardItem},
+    MenuItem, ToolTip,
+};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// How many networks are listed in the menu.
+const TOP_NETWORKS: usize = 5;
+
+/// Actions picked in the tray, which are run by the front-end.
+#[derive(Debug, Eq, PartialEq)]
+pub enum TrayCommand {
+    Show,
+    ToggleRadio,
+    Connect(String),
+    Quit,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct TrayNetwork {
+    essid: String,
+    signal: u64,
+    secured: bool,
+}
+
+/// The part of the app state shown in the tray, it's only sent to the tray when it changed.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct TrayState {
+    interface: String,
+    wlan_on: bool,
+    connecting: Option<String>,
+    connected: Option<String>,
+    signal: Option<u64>,
+    networks: Vec<TrayNetwork>,
+}
+
+impl From<&AppState> for TrayState {
+    fn from(app_state: &AppState) -> Self {
+        let networks = app_state.wlan_networks.as_deref().unwrap_or_default();
+        let signal = app_state.connected_wlan_network.as_ref().and_then(|ssid| {
+            networks
+                .iter()
+                .find(|wn| wn.essid == *ssid)
+                .map(|wn| wn.quality.percent())
+        });
+        let mut by_signal: Vec<_> = networks.iter().collect();
+        by_signal.sort_by_key(|wn| std::cmp::Reverse(wn.quality.value));
+        let mut top_networks: Vec<TrayNetwork> = vec![];
+        for wn in by_signal {
+            // hidden networks can't be picked from a menu and access points of the same
+            // network only show up once, with the strongest signal
+            if wn.essid.is_empty() || top_networks.iter().any(|tn| tn.essid == wn.essid) {
+                continue;
+            }
+            top_networks.push(TrayNetwork {
+                essid: wn.essid.clone(),
+                signal: wn.quality.percent(),
+                secured: wn.security_type != SecurityType::Invalid,
+            });
+            if top_networks.len() == TOP_NETWORKS {
+                break;
+            }
+        }
+        Self {
+            interface: app_state.selected_wlan_interface.clone(),
+            wlan_on: app_state.wlan_on,
+            connecting: app_state.connecting.clone(),
+            connected: app_state.connected_wlan_network.clone(),
+            signal,
+            networks: top_networks,
+        }
+    }
+}
+
+impl TrayState {
+    fn icon_name(&self) -> &'static str {
+        if self.interface.is_empty() || !self.wlan_on {
+            return "network-wireless-offline";
+        }
+        if self.connecting.is_some() {
+            return "network-wireless-acquiring";
+        }
+        match (&self.connected, self.signal) {
+            (None, _) => "network-wireless-disconnected",
+            (Some(_), None) => "network-wireless-signal-good",
+            (Some(_), Some(signal)) => match signal {
+                0..=19 => "network-wireless-signal-none",
+                20..=39 => "network-wireless-signal-weak",
+                40..=59 => "network-wireless-signal-ok",
+                60..=79 => "network-wireless-signal-good",
+                _ => "network-wireless-signal-excellent",
+            },
+        }
+    }
+
+    fn description(&self) -> String {
+        if self.interface.is_empty() {
+            return String::from("No wlan interface found");
+        }
+        if !self.wlan_on {
+            return format!("{} is switched off", self.interface);
+        }
+        if let Some(ref ssid) = self.connecting {
+            return format!("Connecting to {}…", ssid);
+        }
+        match (&self.connected, self.signal) {
+            (Some(ssid), Some(signal)) => format!("Connected to {} ({}%)", ssid, signal),
+            (Some(ssid), None) => format!("Connected to {}", ssid),
+            (None, _) => String::from("Not connected"),
+        }
+    }
+}
+
+struct SwelfiTray {
+    state: TrayState,
+    command_sender: Sender<TrayCommand>,
+    repaint: Repaint,
+}
+
+impl SwelfiTray {
+    fn send(&self, command: TrayCommand) {
+        if self.command_sender.send(command).is_ok() {
+            self.repaint.request_repaint();
+        }
+    }
+}
+
+impl ksni::Tray for SwelfiTray {
+    fn id(&self) -> String {
+        String::from("swelfi")
+    }
+
+    fn title(&self) -> String {
+        String::from("Swelfi")
+    }
+
+    fn icon_name(&self) -> String {
+        self.state.icon_name().to_owned()
+    }
+
+    fn tool_tip(&self) -> ToolTip {
+        ToolTip {
+            title: String::from("Swelfi"),
+            description: self.state.description(),
+            ..Default::default()
+        }
+    }
+
+    fn activate(&mut self, _x: i32, _y: i32) {
+        self.send(TrayCommand::Show);
+    }
+
+    fn menu(&self) -> Vec<MenuItem<Self>> {
+        let mut menu: Vec<MenuItem<Self>> = vec![StandardItem {
+            label: self.state.description(),
+            enabled: false,
+            ..Default::default()
+        }
+        .into()];
+        if self.state.wlan_on && !self.state.networks.is_empty() {
+            menu.push(MenuItem::Separator);
+            for network in &self.state.networks {
+                let essid = network.essid.clone();
+                menu.push(
+                    CheckmarkItem {
+                        // underscores mark access keys in menu labels
+                        label: format!(
+                            "{} ({}%){}",
+                            network.essid.replace('_', "__"),
+                            network.signal,
+                            if network.secured { " 🔒" } else { "" }
+                        ),
+                        checked: self.state.connected.as_ref() == Some(&network.essid),
+                        activate: Box::new(move |tray: &mut Self| {
+                            tray.send(TrayCommand::Connect(essid.clone()))
+                        }),
+                        ..Default::default()
+                    }
+                    .into(),
+                );
+            }
+        }
+        menu.push(MenuItem::Separator);
+        menu.push(
+            CheckmarkItem {
+                label: String::from("Wifi"),
+                enabled: !self.state.interface.is_empty(),
+                checked: self.state.wlan_on,
+                activate: Box::new(|tray: &mut Self| tray.send(TrayCommand::ToggleRadio)),
+                ..Default::default()
+            }
+            .into(),
+        );
+        menu.push(
+            StandardItem {
+                label: String::from("Open window"),
+                activate: Box::new(|tray: &mut Self| tray.send(TrayCommand::Show)),
+                ..Default::default()
+            }
+            .into(),
+        );
+        menu.push(
+            StandardItem {
+                label: String::from("Quit"),
+                icon_name: String::from("application-exit"),
+                activate: Box::new(|tray: &mut Self| tray.send(TrayCommand::Quit)),
+                ..Default::default()
+            }
+            .into(),
+        );
+        menu
+    }
+}
+
+/// A StatusNotifierItem, which shows the connection state and a menu with the top networks.
+pub struct Tray {
+    handle: Handle<SwelfiTray>,
+    state: TrayState,
+    pub commands: Receiver<TrayCommand>,
+}
+
+impl Tray {
+    /// Registers the tray icon, this fails if there is no tray on the session bus.
+    pub fn spawn(app_state: &AppState, repaint: Repaint) -> Result<Self, ksni::Error> {
+        let (command_sender, commands) = channel();
+        let state = TrayState::from(app_state);
+        let handle = SwelfiTray {
+            state: state.clone(),
+            command_sender,
+            repaint,
+        }
+        .spawn()?;
+        Ok(Self {
+            handle,
+            state,
+            commands,
+        })
+    }
+
+    pub fn update(&mut self, app_state: &AppState) {
+        let state = TrayState::from(app_state);
+        if state != self.state {
+            self.state = state.clone();
+            self.handle.update(move |tray| tray.state = state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Quality, WirelessNetwork};
+
+    fn network(essid: &str, value: u64) -> WirelessNetwork {
+        WirelessNetwork {
+            address: String::from("D4:1A:D1:51:67:F2"),
+            quality: Quality { value, limit: 70 },
+            frequency: 2.437,
+            essid: essid.to_owned(),
+            security_type: SecurityType::Wpa2,
+            last_beacon: None,
+            access_points: 1,
+        }
+    }
+
+    #[test]
+    fn tray_state() {
+        let mut app_state = AppState::new(vec![], String::from("wlan0"));
+        app_state.wlan_networks = Some(vec![
+            network("home", 63),
+            network("home", 40),
+            network("", 35),
+            network("a", 30),
+            network("b", 20),
+            network("c", 14),
+            network("d", 7),
+            network("e", 1),
+        ]);
+        app_state.connected_wlan_network = Some(String::from("home"));
+
+        let state = TrayState::from(&app_state);
+        assert_eq!(state.signal, Some(90));
+        assert_eq!(
+            state
+                .networks
+                .iter()
+                .map(|tn| tn.essid.as_str())
+                .collect::<Vec<_>>(),
+            vec!["home", "a", "b", "c", "d"]
+        );
+        assert_eq!(state.icon_name(), "network-wireless-signal-excellent");
+        assert_eq!(state.description(), "Connected to home (90%)");
+
+        app_state.connected_wlan_network = None;
+        assert_eq!(
+            TrayState::from(&app_state).icon_name(),
+            "network-wireless-disconnected"
+        );
+
+        app_state.wlan_on = false;
+        let state = TrayState::from(&app_state);
+        assert_eq!(state.icon_name(), "network-wireless-offline");
+        assert_eq!(state.description(), "wlan0 is switched off");
+    }
+}
@@ -0,0 +1,379 @@
+use crate::{
+    app::{self, AppState, Event, Repaint},
+    settings::{Overrides, Settings},
+    Quality, WirelessNetwork,
+};
+use anyhow::Result;
+use ratatui::{
+    crossterm::event::{self, Event as TerminalEvent, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Cell, Clear, Paragraph, Row, Table, TableState},
+    DefaultTerminal, Frame,
+};
+use std::{
+    sync::mpsc::{channel, Receiver, Sender},
+    time::{Duration, Instant},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+const SIGNAL_BARS: [char; 4] = ['▂', '▄', '▆', '█'];
+
+struct SwelfiTui {
+    app_state: AppState,
+    background_event_sender: Sender<Event>,
+    event_receiver: Receiver<Event>,
+    repaint: Repaint,
+    table_state: TableState,
+    last_refresh: Instant,
+    quit: bool,
+}
+
+/// Runs the terminal front-end, which shares the state and the background thread with the GUI.
+pub fn run(overrides: Overrides) -> Result<()> {
+    let (background_event_sender, background_event_receiver) = channel::<Event>();
+    let (event_sender, event_receiver) = channel::<Event>();
+    app::spawn_background_thread(background_event_receiver, event_sender.clone());
+
+    let mut settings = Settings::load();
+    settings.overrides = overrides;
+    let app_state = AppState::load(settings)?;
+    // the terminal is redrawn after polling for input anyway
+    let repaint = Repaint::new(|| ());
+    app::watch_system(&event_sender, &repaint, &app_state.wlan_interfaces);
+    app_state.refresh(&background_event_sender, &repaint);
+
+    let mut tui = SwelfiTui {
+        app_state,
+        background_event_sender,
+        event_receiver,
+        repaint,
+        table_state: TableState::default(),
+        last_refresh: Instant::now(),
+        quit: false,
+    };
+
+    let mut terminal = ratatui::try_init()?;
+    let result = tui.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl SwelfiTui {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        while !self.quit {
+            while let Ok(event) = self.event_receiver.try_recv() {
+                self.app_state
+                    .handle_event(event, &self.background_event_sender, &self.repaint);
+            }
+            let refresh_due = self
+                .app_state
+                .settings
+                .refresh_interval()
+                .is_some_and(|interval| self.last_refresh.elapsed() >= interval);
+            if self.app_state.wlan_on && refresh_due {
+                self.app_state
+                    .reload(&self.background_event_sender, &self.repaint);
+                self.last_refresh = Instant::now();
+            }
+            self.sync_selection();
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if event::poll(POLL_INTERVAL)? {
+                if let TerminalEvent::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        self.handle_key(key.code, key.modifiers);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn networks(&self) -> &[WirelessNetwork] {
+        self.app_state.wlan_networks.as_deref().unwrap_or_default()
+    }
+
+    /// Keeps the highlighted row on the selected network, when the list changes.
+    fn sync_selection(&mut self) {
+        let index = self
+            .networks()
+            .iter()
+            .position(|wn| wn.id() == self.app_state.selected_wlan_network);
+        self.table_state.select(index);
+    }
+
+    fn select_network(&mut self, offset: isize) {
+        let networks = self.networks();
+        if networks.is_empty() {
+            return;
+        }
+        let current = self.table_state.selected().unwrap_or(0) as isize;
+        let index = (current + offset).clamp(0, networks.len() as isize - 1) as usize;
+        self.app_state.selected_wlan_network = networks[index].id();
+    }
+
+    fn select_next_interface(&mut self) {
+        let wlan_interfaces = &self.app_state.wlan_interfaces;
+        if wlan_interfaces.is_empty() {
+            return;
+        }
+        let current = wlan_interfaces
+            .iter()
+            .position(|wi| wi.name == self.app_state.selected_wlan_interface)
+            .unwrap_or(0);
+        let next = wlan_interfaces[(current + 1) % wlan_interfaces.len()]
+            .name
+            .clone();
+        self.app_state
+            .change_wlan_interface(&next, &self.background_event_sender, &self.repaint);
+    }
+
+    fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+        if code == KeyCode::Char('c') && modifiers.contains(KeyModifiers::CONTROL) {
+            self.quit = true;
+            return;
+        }
+
+        if let Some(ref mut dialog) = self.app_state.connect_dialog {
+            match code {
+                KeyCode::Esc => self.app_state.connect_dialog = None,
+                KeyCode::Enter => self
+                    .app_state
+                    .connect(&self.background_event_sender, &self.repaint),
+                KeyCode::Backspace => {
+                    dialog.passphrase.pop();
+                }
+                KeyCode::Char(c) if dialog.secured => dialog.passphrase.push(c),
+                _ => (),
+            }
+            return;
+        }
+
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Up | KeyCode::Char('k') => self.select_network(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.select_network(1),
+            KeyCode::Tab | KeyCode::Char('i') => self.select_next_interface(),
+            KeyCode::Char('r') => {
+                self.app_state
+                    .refresh(&self.background_event_sender, &self.repaint);
+                self.last_refresh = Instant::now();
+            }
+            KeyCode::Char('t') | KeyCode::Char(' ') => {
+                let hard_blocked = self
+                    .app_state
+                    .interface_status
+                    .as_ref()
+                    .is_some_and(|status| status.rfkill.hard_blocked);
+                if !hard_blocked {
+                    self.app_state
+                        .toggle_wlan(&self.background_event_sender, &self.repaint);
+                }
+            }
+            KeyCode::Char('a') if !self.app_state.rfkill_devices.is_empty() => {
+                let airplane_mode = !self.app_state.airplane_mode();
+                self.app_state.set_airplane_mode(
+                    airplane_mode,
+                    &self.background_event_sender,
+                    &self.repaint,
+                );
+            }
+            KeyCode::Enter | KeyCode::Char('c') => self.app_state.open_connect_dialog(),
+            _ => (),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, networks, details, footer] = Layout::vertical([
+            Constraint::Length(4),
+            Constraint::Min(5),
+            Constraint::Length(7),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        frame.render_widget(self.header(), header);
+        if self.app_state.wlan_interfaces.is_empty() {
+            frame.render_widget(
+                Paragraph::new(
+                    "No wlan interface found, plug in a wireless adapter to get started.",
+                )
+                .block(Block::bordered().title("Networks")),
+                networks,
+            );
+        } else {
+            let mut table_state = std::mem::take(&mut self.table_state);
+            frame.render_stateful_widget(self.network_table(), networks, &mut table_state);
+            self.table_state = table_state;
+        }
+        frame.render_widget(self.details(), details);
+        frame.render_widget(self.footer(), footer);
+
+        if let Some(ref dialog) = self.app_state.connect_dialog {
+            let area = centered(frame.area(), 50, 5);
+            let text = if dialog.secured {
+                format!(
+                    "Passphrase: {}",
+                    "*".repeat(dialog.passphrase.chars().count())
+                )
+            } else {
+                String::from("Open network, press enter to connect")
+            };
+            frame.render_widget(Clear, area);
+            frame.render_widget(
+                Paragraph::new(vec![
+                    Line::from(text),
+                    Line::from("enter: connect, esc: cancel"),
+                ])
+                .block(Block::bordered().title(format!("Connect to {}", dialog.ssid))),
+                area,
+            );
+        }
+    }
+
+    fn header(&self) -> Paragraph<'_> {
+        let app_state = &self.app_state;
+        let mut interfaces = vec![Span::from("Interface: ")];
+        for wi in &app_state.wlan_interfaces {
+            let span = Span::from(format!(" {} ", wi.name));
+            interfaces.push(if wi.name == app_state.selected_wlan_interface {
+                span.reversed()
+            } else {
+                span
+            });
+        }
+
+        let mut radio = vec![Span::from(format!(
+            "Radio: {}",
+            if app_state.wlan_on { "on" } else { "off" }
+        ))];
+        if let Some(ref status) = app_state.interface_status {
+            radio.push(Span::from(format!("  Link state: {}", status.oper_state)));
+            if status.rfkill.hard_blocked {
+                radio.push(Span::from("  switched off by a hardware switch").red());
+            } else if status.rfkill.soft_blocked {
+                radio.push(Span::from("  blocked by rfkill"));
+            }
+        }
+        if !app_state.rfkill_devices.is_empty() {
+            radio.push(Span::from(format!(
+                "  Airplane mode: {}",
+                if app_state.airplane_mode() {
+                    "on"
+                } else {
+                    "off"
+                }
+            )));
+        }
+
+        Paragraph::new(vec![Line::from(interfaces), Line::from(radio)])
+            .block(Block::bordered().title("Swelfi"))
+    }
+
+    fn network_table(&self) -> Table<'_> {
+        let connected = self.app_state.connected_wlan_network.as_deref();
+        let rows = self.networks().iter().map(|wn| {
+            let is_connected = connected == Some(wn.essid.as_str());
+            let row = Row::new(vec![
+                Cell::from(signal_bars(&wn.quality)),
+                Cell::from(wn.essid.clone()),
+                Cell::from(wn.security_type.label()),
+                Cell::from(format!("{} GHz", wn.frequency)),
+                Cell::from(if is_connected { "connected" } else { "" }),
+            ]);
+            if is_connected {
+                row.add_modifier(Modifier::BOLD)
+            } else {
+                row
+            }
+        });
+
+        let title = if self.app_state.wlan_networks.is_none() {
+            "Networks (scanning…)"
+        } else {
+            "Networks"
+        };
+        Table::new(
+            rows,
+            [
+                Constraint::Length(4),
+                Constraint::Min(20),
+                Constraint::Length(8),
+                Constraint::Length(10),
+                Constraint::Length(9),
+            ],
+        )
+        .header(Row::new(vec!["", "SSID", "SECURITY", "FREQUENCY", ""]).bold())
+        .block(Block::bordered().title(title))
+        .row_highlight_style(Style::new().reversed())
+    }
+
+    fn details(&self) -> Paragraph<'_> {
+        let lines = match self.app_state.selected_network() {
+            Some(wn) => vec![
+                Line::from(format!("SSID:      {}", wn.essid)),
+                Line::from(format!("Address:   {}", wn.address)),
+                Line::from(format!(
+                    "Signal:    {}/{}",
+                    wn.quality.value, wn.quality.limit
+                )),
+                Line::from(format!("Frequency: {} GHz", wn.frequency)),
+                Line::from(format!("Security:  {}", wn.security_type.label())),
+            ],
+            None => vec![Line::from("No network selected")],
+        };
+        Paragraph::new(lines).block(Block::bordered().title("Details"))
+    }
+
+    fn footer(&self) -> Paragraph<'_> {
+        if let Some(ref error) = self.app_state.error {
+            return Paragraph::new(error.as_str()).red();
+        }
+        if let Some(ref ssid) = self.app_state.connecting {
+            return Paragraph::new(format!("Connecting to {}…", ssid));
+        }
+        Paragraph::new(
+            "q: quit  tab: interface  t: radio  a: airplane mode  r: refresh  enter: connect",
+        )
+        .dim()
+    }
+}
+
+/// Up to four bars, depending on the quality of the signal.
+fn signal_bars(quality: &Quality) -> String {
+    let bars = quality.bars();
+    SIGNAL_BARS
+        .iter()
+        .enumerate()
+        .map(|(i, bar)| if i < bars { *bar } else { ' ' })
+        .collect()
+}
+
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bars() {
+        let quality = |value| Quality { value, limit: 70 };
+
+        assert_eq!(signal_bars(&quality(0)), "    ");
+        assert_eq!(signal_bars(&quality(10)), "▂   ");
+        assert_eq!(signal_bars(&quality(42)), "▂▄▆ ");
+        assert_eq!(signal_bars(&quality(70)), "▂▄▆█");
+        assert_eq!(signal_bars(&Quality { value: 5, limit: 0 }), "    ");
+    }
+}
@@ -0,0 +1,261 @@
+//! Runs swelfi against simulated access points, so no wlan hardware is needed.
+
+use serde_json::{json, Value};
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    process::{Child, Command, Output, Stdio},
+    time::{Duration, Instant},
+};
+
+/// How long the daemon gets to connect on its own.
+const AUTOCONNECT_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// A scratch directory standing in for the home, config and runtime directories, with the
+/// scenario in it.
+struct Sandbox {
+    directory: PathBuf,
+}
+
+impl Sandbox {
+    fn new(name: &str, scenario: &str) -> Self {
+        let directory =
+            std::env::temp_dir().join(format!("swelfi-simulate-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(directory.join("config/swelfi")).unwrap();
+        std::fs::write(directory.join("scenario.toml"), scenario).unwrap();
+        Self { directory }
+    }
+
+    fn config(&self, file: &str, contents: &str) {
+        std::fs::write(self.directory.join("config/swelfi").join(file), contents).unwrap();
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_swelfi"));
+        command
+            .arg("--simulate")
+            .arg(self.directory.join("scenario.toml"))
+            .args(args)
+            .env("HOME", &self.directory)
+            .env("XDG_CONFIG_HOME", self.directory.join("config"))
+            .env("XDG_RUNTIME_DIR", &self.directory)
+            .env_remove("RUST_LOG");
+        command
+    }
+
+    fn run(&self, args: &[&str], stdin: &str) -> Output {
+        let mut child = self
+            .command(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(stdin.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap()
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.directory);
+    }
+}
+
+/// Stops the daemon, even if the test fails.
+struct Daemon(Child);
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn cli() {
+    let sandbox = Sandbox::new(
+        "cli",
+        r#"
+        connect_time = 0.1
+
+        [[access_point]]
+        ssid = "home"
+        signal = -50
+        passphrase = "correct horse"
+
+        [[access_point]]
+        ssid = "cafe"
+        security = "open"
+        channel = 36
+        signal = -70
+        connect = "fails"
+
+        [[access_point]]
+        ssid = "later"
+        appears = 600
+        "#,
+    );
+
+    let list = sandbox.run(&["list"], "");
+    assert!(list.status.success(), "{}", stderr(&list));
+    assert!(stdout(&list).contains("wlan0"), "{}", stdout(&list));
+
+    let scan = sandbox.run(&["scan", "--format", "json"], "");
+    assert!(scan.status.success(), "{}", stderr(&scan));
+    let scan: Value = serde_json::from_slice(&scan.stdout).unwrap();
+    let networks: Vec<(&str, &str, f64)> = scan["networks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|network| {
+            (
+                network["essid"].as_str().unwrap(),
+                network["security_type"].as_str().unwrap(),
+                network["frequency"].as_f64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        networks,
+        [("home", "wpa2", 2.437), ("cafe", "invalid", 5.18)]
+    );
+
+    let connect = |ssid, passphrase| {
+        sandbox.run(
+            &["connect", ssid, "--passphrase-stdin"],
+            &format!("{}\n", passphrase),
+        )
+    };
+    let connected = connect("home", "correct horse");
+    assert!(connected.status.success(), "{}", stderr(&connected));
+    for (ssid, passphrase, error) in [
+        ("home", "wrong horse", "wrong passphrase for home"),
+        ("cafe", "", "associating with cafe failed"),
+        ("later", "", "later isn't in range"),
+    ] {
+        let failed = connect(ssid, passphrase);
+        assert_eq!(failed.status.code(), Some(1));
+        assert!(stderr(&failed).contains(error), "{}", stderr(&failed));
+    }
+    for (args, error) in [
+        (&["connect", "home"][..], "home needs a passphrase"),
+        (&["connect", "--", "later"], "later isn't in range"),
+    ] {
+        let failed = sandbox.run(args, "");
+        assert_eq!(failed.status.code(), Some(1));
+        assert!(stderr(&failed).contains(error), "{}", stderr(&failed));
+    }
+
+    let status = sandbox.run(&["status", "--format", "json"], "");
+    assert!(status.status.success(), "{}", stderr(&status));
+    let status: Value = serde_json::from_slice(&status.stdout).unwrap();
+    assert_eq!(status["interface"], "wlan0");
+
+    let radio = sandbox.run(&["radio", "off"], "");
+    assert!(radio.status.success(), "{}", stderr(&radio));
+}
+
+#[test]
+fn daemon_autoconnects() {
+    let sandbox = Sandbox::new(
+        "daemon",
+        r#"
+        [[access_point]]
+        ssid = "cafe"
+        security = "open"
+        signal = -40
+        connect = "fails"
+
+        [[access_point]]
+        ssid = "home"
+        signal = -60
+        drift = 5
+        passphrase = "correct horse"
+        appears = 2
+        "#,
+    );
+    sandbox.config(
+        "settings.toml",
+        "refresh_interval = 1\n\n[captive_portal]\nenabled = false\n",
+    );
+    sandbox.config(
+        "swelfi.toml",
+        r#"
+        [[network]]
+        ssid = "cafe"
+        autoconnect = true
+
+        [[network]]
+        ssid = "home"
+        autoconnect = true
+        passphrase = "correct horse"
+        "#,
+    );
+    let _daemon = Daemon(
+        sandbox
+            .command(&["daemon"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap(),
+    );
+
+    let socket_path = sandbox.directory.join("swelfi.sock");
+    let started = Instant::now();
+    let stream = loop {
+        match UnixStream::connect(&socket_path) {
+            Ok(stream) => break stream,
+            Err(e) if started.elapsed() > AUTOCONNECT_TIMEOUT => panic!("no daemon: {}", e),
+            Err(_) => std::thread::sleep(Duration::from_millis(100)),
+        }
+    };
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut writer = stream;
+    let mut get_state = |id: u64| -> Value {
+        let request = json!({"jsonrpc": "2.0", "id": id, "method": "get_state"});
+        writeln!(writer, "{}", request).unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        serde_json::from_str::<Value>(&line).unwrap()["result"].take()
+    };
+
+    // the strongest network fails, then the other one comes into range
+    let mut errors = vec![];
+    let mut id = 0;
+    let state = loop {
+        id += 1;
+        let state = get_state(id);
+        if let Some(error) = state["error"].as_str() {
+            errors.push(error.to_owned());
+        }
+        if state["connected_ssid"] == "home" || started.elapsed() > AUTOCONNECT_TIMEOUT {
+            break state;
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    };
+    assert_eq!(state["connected_ssid"], "home", "{}", state);
+    assert_eq!(state["interface"], "wlan0");
+    assert_eq!(state["radio_on"], true);
+    assert!(
+        errors
+            .iter()
+            .any(|error| error.contains("associating with cafe failed")),
+        "{:?}",
+        errors
+    );
+}